@@ -0,0 +1,198 @@
+//! The C ABI between c0i and native extensions.
+//!
+//! An extension is a dynamic library exporting two functions:
+//!
+//! ```c
+//! uint32_t c0i_extension_abi_version(void);
+//! int32_t c0i_extension_init(const C0iRegistrar *registrar);
+//! ```
+//!
+//! The host first checks that `c0i_extension_abi_version` returns `C0I_ABI_VERSION`,
+//! then calls `c0i_extension_init`, which names the extension with `set_name`
+//! and adds its functions with `register`. It returns `C0I_OK` if it succeeded.
+//!
+//! Only the types of this crate cross the boundary, so an extension does not
+//! have to be built with the compiler of the host, nor even in Rust.
+//! Nothing may unwind out of an extension.
+
+use std::ffi::c_void;
+
+
+/// Bumped on every change of the types below.
+pub const C0I_ABI_VERSION: u32 = 2;
+
+pub const C0I_OK: i32 = 0;
+pub const C0I_ERROR: i32 = 1;
+
+pub const C0I_ABI_VERSION_SYMBOL: &[u8] = b"c0i_extension_abi_version\0";
+pub const C0I_INIT_SYMBOL: &[u8] = b"c0i_extension_init\0";
+
+pub type AbiVersionFn = unsafe extern "C" fn() -> u32;
+pub type InitFn = unsafe extern "C" fn(registrar: *const C0iRegistrar) -> i32;
+
+
+/// A UTF-8 string which is not owned by the side receiving it.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct C0iStr {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+impl C0iStr {
+    pub fn new(s: &str) -> C0iStr {
+        C0iStr { ptr: s.as_ptr(), len: s.len() }
+    }
+
+    /// # Safety
+    /// `ptr` must point to `len` bytes that live as long as `'a`.
+    pub unsafe fn as_str<'a>(&self) -> Option<&'a str> {
+        if self.ptr.is_null() {
+            return None;
+        }
+        std::str::from_utf8(std::slice::from_raw_parts(self.ptr, self.len)).ok()
+    }
+}
+
+/// The tags of `C0iValue`.
+pub mod tag {
+    pub const NIL: u32 = 0;
+    pub const BOOL: u32 = 1;
+    pub const CHAR: u32 = 2;
+    pub const UINT: u32 = 3;
+    pub const INT: u32 = 4;
+    pub const FLOAT: u32 = 5;
+    pub const STR: u32 = 6;
+    pub const SYM: u32 = 7;
+    /// a value without a C representation, it can only be returned as it was received
+    pub const OPAQUE: u32 = 8;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union C0iPayload {
+    /// any byte but 0 is true, an extension in C may put any there
+    pub boolean: u8,
+    pub character: u32,
+    pub uint: u64,
+    pub int: i64,
+    pub float: f64,
+    pub string: C0iStr,
+    pub opaque: *const c_void,
+}
+
+/// A value of c0i as seen by an extension.
+///
+/// Strings given to an extension live until its function returns.
+/// Strings returned by an extension are copied by the host,
+/// which then hands the value to the `release` of the function.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct C0iValue {
+    pub tag: u32,
+    pub payload: C0iPayload,
+}
+
+impl C0iValue {
+    pub fn nil() -> C0iValue {
+        C0iValue { tag: tag::NIL, payload: C0iPayload { uint: 0 } }
+    }
+
+    pub fn boolean(x: bool) -> C0iValue {
+        C0iValue { tag: tag::BOOL, payload: C0iPayload { boolean: x as u8 } }
+    }
+
+    pub fn character(x: char) -> C0iValue {
+        C0iValue { tag: tag::CHAR, payload: C0iPayload { character: x as u32 } }
+    }
+
+    pub fn uint(x: u64) -> C0iValue {
+        C0iValue { tag: tag::UINT, payload: C0iPayload { uint: x } }
+    }
+
+    pub fn int(x: i64) -> C0iValue {
+        C0iValue { tag: tag::INT, payload: C0iPayload { int: x } }
+    }
+
+    pub fn float(x: f64) -> C0iValue {
+        C0iValue { tag: tag::FLOAT, payload: C0iPayload { float: x } }
+    }
+
+    pub fn string(x: C0iStr) -> C0iValue {
+        C0iValue { tag: tag::STR, payload: C0iPayload { string: x } }
+    }
+
+    pub fn symbol(x: C0iStr) -> C0iValue {
+        C0iValue { tag: tag::SYM, payload: C0iPayload { string: x } }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        if self.tag == tag::BOOL { Some(unsafe { self.payload.boolean } != 0) } else { None }
+    }
+
+    pub fn as_char(&self) -> Option<char> {
+        if self.tag == tag::CHAR { std::char::from_u32(unsafe { self.payload.character }) } else { None }
+    }
+
+    pub fn as_uint(&self) -> Option<u64> {
+        if self.tag == tag::UINT { Some(unsafe { self.payload.uint }) } else { None }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        if self.tag == tag::INT { Some(unsafe { self.payload.int }) } else { None }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        if self.tag == tag::FLOAT { Some(unsafe { self.payload.float }) } else { None }
+    }
+
+    /// The string of a `STR` or `SYM` value.
+    ///
+    /// # Safety
+    /// The string must live as long as `'a`, which holds for arguments during the call.
+    pub unsafe fn as_str<'a>(&self) -> Option<&'a str> {
+        if self.tag == tag::STR || self.tag == tag::SYM {
+            self.payload.string.as_str()
+        } else {
+            None
+        }
+    }
+}
+
+/// A function of an extension.
+///
+/// It returns `C0I_OK` with its result in `out`,
+/// or `C0I_ERROR` with the message of the error in `out`.
+pub type C0iFn = unsafe extern "C" fn(
+    data: *mut c_void,
+    args: *const C0iValue,
+    nargs: usize,
+    out: *mut C0iValue,
+) -> i32;
+
+/// Frees a value an extension returned.
+pub type C0iReleaseFn = unsafe extern "C" fn(data: *mut c_void, value: *mut C0iValue);
+
+#[repr(C)]
+pub struct C0iFunction {
+    pub name: C0iStr,
+    pub min_args: u32,
+    /// `u32::MAX` if the function is variadic
+    pub max_args: u32,
+    /// 0 if the function is not pure
+    pub is_pure: u8,
+    pub call: C0iFn,
+    pub release: Option<C0iReleaseFn>,
+    /// passed to `call` and `release`
+    pub data: *mut c_void,
+}
+
+/// What the host passes to `c0i_extension_init`, it is only valid during the call.
+#[repr(C)]
+pub struct C0iRegistrar {
+    pub abi_version: u32,
+    pub host: *mut c_void,
+    pub set_name: unsafe extern "C" fn(host: *mut c_void, name: C0iStr),
+    /// returns `C0I_ERROR` if the function is malformed or its name is taken
+    pub register: unsafe extern "C" fn(host: *mut c_void, function: *const C0iFunction) -> i32,
+}
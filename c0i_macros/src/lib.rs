@@ -0,0 +1,360 @@
+//! Procedural macros for exposing Rust functions and types to c0i scripts,
+//! they are re-exported by the `c0i` crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, AttributeArgs, Data, DeriveInput, Error, Fields, FnArg, Ident,
+    ItemFn, Lit, Meta, NestedMeta, Type, Variant,
+};
+
+
+/// Binds a function as a native of c0i.
+///
+/// `#[c0i_fn(name = "string-repeat", module = "text", pure)]` on `fn repeat`
+/// keeps the function as it is and adds a `REPEAT_NATIVE` constant,
+/// a `NativeDef` to give to `Interpreter::register` or `scope_register_natives`.
+/// The name defaults to the name of the function with `-` for `_`,
+/// the module to the module path, and natives are not pure unless told so.
+///
+/// The arguments are converted as by `autobind`,
+/// a first parameter of type `&Context` receives the context of the call.
+#[proc_macro_attribute]
+pub fn c0i_fn(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as AttributeArgs);
+    let item = parse_macro_input!(item as ItemFn);
+    c0i_fn_impl(args, item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn c0i_fn_impl(args: AttributeArgs, item: ItemFn) -> Result<TokenStream2, Error> {
+    let sig = &item.sig;
+    let ident = &sig.ident;
+    let mut name = ident.to_string().replace('_', "-");
+    let mut module = quote!(::std::module_path!());
+    let mut is_pure = false;
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(x)) if x.path.is_ident("name") => name = lit_str(&x.lit)?,
+            NestedMeta::Meta(Meta::NameValue(x)) if x.path.is_ident("module") => {
+                let m = lit_str(&x.lit)?;
+                module = quote!(#m);
+            },
+            NestedMeta::Meta(Meta::Path(x)) if x.is_ident("pure") => is_pure = true,
+            x => return Err(Error::new_spanned(x, "expected `name = \"...\"`, `module = \"...\"` or `pure`")),
+        }
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(&sig.generics, "a native can not be generic"));
+    }
+    if let Some(x) = &sig.asyncness {
+        return Err(Error::new_spanned(x, "a native can not be async"));
+    }
+
+    let mut params = sig.inputs.iter().peekable();
+    let ctx = match params.peek() {
+        Some(FnArg::Typed(x)) if is_context(&x.ty) => {
+            params.next();
+            Some(quote!(ctx,))
+        },
+        _ => None,
+    };
+    let mut names = vec![];
+    let mut types = vec![];
+    for (i, param) in params.enumerate() {
+        match param {
+            FnArg::Typed(x) => {
+                names.push(format_ident!("arg{}", i));
+                types.push(&x.ty);
+            },
+            FnArg::Receiver(x) => return Err(Error::new_spanned(x, "a native can not take `self`")),
+        }
+    }
+
+    let vis = &item.vis;
+    let def = format_ident!("{}_NATIVE", ident.to_string().to_uppercase());
+    let doc = format!("The native `{}` bound from `{}`.", name, ident);
+    let ctx_param = if ctx.is_some() { quote!(ctx) } else { quote!(_) };
+    Ok(quote! {
+        #item
+
+        #[doc = #doc]
+        #vis const #def: ::c0i::value::autobind::NativeDef = {
+            fn bound(
+                #ctx_param: &::c0i::value::context::Context,
+                args: ::std::vec::Vec<::c0i::value::Value>
+            ) -> ::c0i::value::result::CResult {
+                let f = |#(#names: #types),*| #ident(#ctx #(#names),*);
+                ::c0i::value::autobind::RustCallable::call(&f, args)
+            }
+            ::c0i::value::autobind::NativeDef {
+                name: #name,
+                module: #module,
+                is_pure: #is_pure,
+                interface: bound,
+            }
+        };
+    })
+}
+
+fn lit_str(lit: &Lit) -> Result<String, Error> {
+    match lit {
+        Lit::Str(x) => Ok(x.value()),
+        x => Err(Error::new_spanned(x, "expected a string")),
+    }
+}
+
+fn is_context(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(x) => match &*x.elem {
+            Type::Path(x) => x.path.segments.last().is_some_and(|x| x.ident == "Context"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+
+/// Converts a value of c0i into a struct or enum.
+///
+/// Structs with named fields are read from a dict keyed by the field names,
+/// tuple structs from a vector of their fields and newtypes from the value they wrap.
+/// Unit variants of enums are read from the symbol of their name,
+/// the other variants from a vector of that symbol followed by the fields,
+/// or by a dict of them for variants with named fields.
+/// `#[c0i(rename = "...")]` sets the key of a field or the symbol of a variant.
+#[proc_macro_derive(FromValue, attributes(c0i))]
+pub fn derive_from_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_value_impl(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Converts a struct or enum into a value of c0i, in the shape `FromValue` reads.
+#[proc_macro_derive(IntoValue, attributes(c0i))]
+pub fn derive_into_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into_value_impl(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn from_value_impl(mut input: DeriveInput) -> Result<TokenStream2, Error> {
+    add_bounds(&mut input, quote!(::c0i::value::autobind::FromValue));
+    let ident = &input.ident;
+    let type_name = ident.to_string();
+    let body = match &input.data {
+        Data::Struct(x) => {
+            let build = from_fields(quote!(#ident), &x.fields, &type_name, quote!(v))?;
+            quote!(Ok(#build))
+        },
+        Data::Enum(x) => {
+            let mut arms = vec![];
+            for variant in &x.variants {
+                let tag = variant_tag(variant)?;
+                let name = &variant.ident;
+                let arm = match &variant.fields {
+                    Fields::Unit => quote!(#tag if items.is_empty() => Ok(#ident::#name),),
+                    Fields::Unnamed(x) => {
+                        let len = x.unnamed.len();
+                        let build = from_items(quote!(#ident::#name), len, quote!(items));
+                        quote!(#tag if items.len() == #len => Ok(#build),)
+                    },
+                    Fields::Named(_) => {
+                        let build = from_fields(
+                            quote!(#ident::#name),
+                            &variant.fields,
+                            &type_name,
+                            quote!(items.pop().unwrap())
+                        )?;
+                        quote!(#tag if items.len() == 1 => Ok(#build),)
+                    },
+                };
+                arms.push(arm);
+            }
+            quote! {
+                let (tag, mut items) = match ::c0i::value::autobind::split_variant(&v) {
+                    Some(x) => x,
+                    None => return Err(::c0i::value::result::CError::InvalidValue(#type_name, v)),
+                };
+                match tag.as_str() {
+                    #(#arms)*
+                    _ => Err(::c0i::value::result::CError::InvalidValue(#type_name, v)),
+                }
+            }
+        },
+        Data::Union(x) => return Err(Error::new_spanned(x.union_token, "unions can not be converted")),
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::c0i::value::autobind::FromValue for #ident #ty_generics #where_clause {
+            #[allow(unused_mut)]
+            fn from_value(v: ::c0i::value::Value)
+                -> ::std::result::Result<Self, ::c0i::value::result::CError>
+            {
+                #body
+            }
+        }
+    })
+}
+
+/// Builds `path` from `value`, which holds the fields in the shape of `fields`.
+fn from_fields(path: TokenStream2, fields: &Fields, type_name: &str, value: TokenStream2)
+    -> Result<TokenStream2, Error>
+{
+    Ok(match fields {
+        Fields::Named(x) => {
+            let mut inits = vec![];
+            for field in &x.named {
+                let name = field.ident.as_ref().unwrap();
+                let key = field_key(field.ident.as_ref().unwrap(), &field.attrs)?;
+                inits.push(quote!(#name: ::c0i::value::autobind::record_field(&record, #key)?,));
+            }
+            quote!({
+                let record = ::c0i::value::autobind::record_of(#value)?;
+                #path { #(#inits)* }
+            })
+        },
+        Fields::Unnamed(x) if x.unnamed.len() == 1 =>
+            quote!(#path(::c0i::value::autobind::FromValue::from_value(#value)?)),
+        Fields::Unnamed(x) => {
+            let len = x.unnamed.len();
+            let build = from_items(path, len, quote!(items));
+            quote!({
+                let items = ::c0i::value::autobind::tuple_items(#value, #len, #type_name)?;
+                #build
+            })
+        },
+        Fields::Unit => quote!({
+            match #value {
+                ::c0i::value::Value::Nil => #path,
+                v => return Err(::c0i::value::result::CError::InvalidValue(#type_name, v)),
+            }
+        }),
+    })
+}
+
+/// Builds `path` from the `len` values of the vector `items`.
+fn from_items(path: TokenStream2, len: usize, items: TokenStream2) -> TokenStream2 {
+    let fields = (0..len).map(|_| quote!(::c0i::value::autobind::FromValue::from_value(items.next().unwrap())?));
+    quote!({
+        let mut items = #items.into_iter();
+        #path(#(#fields),*)
+    })
+}
+
+fn into_value_impl(mut input: DeriveInput) -> Result<TokenStream2, Error> {
+    add_bounds(&mut input, quote!(::c0i::value::autobind::IntoValue));
+    let ident = &input.ident;
+    let body = match &input.data {
+        Data::Struct(x) => match &x.fields {
+            Fields::Named(x) => {
+                let mut entries = vec![];
+                for field in &x.named {
+                    let name = field.ident.as_ref().unwrap();
+                    let key = field_key(name, &field.attrs)?;
+                    entries.push(quote!((#key, ::c0i::value::autobind::IntoValue::into_value(self.#name)),));
+                }
+                quote!(::c0i::value::autobind::record_into_value(vec![#(#entries)*]))
+            },
+            Fields::Unnamed(x) if x.unnamed.len() == 1 =>
+                quote!(::c0i::value::autobind::IntoValue::into_value(self.0)),
+            Fields::Unnamed(x) => {
+                let items = (0..x.unnamed.len()).map(syn::Index::from)
+                    .map(|i| quote!(::c0i::value::autobind::IntoValue::into_value(self.#i),));
+                quote!(::c0i::value::Value::from(vec![#(#items)*]))
+            },
+            Fields::Unit => quote!(::c0i::value::Value::Nil),
+        },
+        Data::Enum(x) => {
+            let mut arms = vec![];
+            for variant in &x.variants {
+                let tag = variant_tag(variant)?;
+                let name = &variant.ident;
+                let arm = match &variant.fields {
+                    Fields::Unit => quote! {
+                        #ident::#name => ::c0i::value::autobind::tagged_into_value(#tag, None),
+                    },
+                    Fields::Unnamed(x) => {
+                        let names: Vec<_> = (0..x.unnamed.len()).map(|i| format_ident!("field{}", i)).collect();
+                        quote! {
+                            #ident::#name(#(#names),*) => ::c0i::value::autobind::tagged_into_value(
+                                #tag,
+                                Some(vec![#(::c0i::value::autobind::IntoValue::into_value(#names)),*])
+                            ),
+                        }
+                    },
+                    Fields::Named(x) => {
+                        let mut names = vec![];
+                        let mut entries = vec![];
+                        for field in &x.named {
+                            let name = field.ident.as_ref().unwrap();
+                            let key = field_key(name, &field.attrs)?;
+                            entries.push(quote!((#key, ::c0i::value::autobind::IntoValue::into_value(#name)),));
+                            names.push(name);
+                        }
+                        quote! {
+                            #ident::#name { #(#names),* } => ::c0i::value::autobind::tagged_into_value(
+                                #tag,
+                                Some(vec![::c0i::value::autobind::record_into_value(vec![#(#entries)*])])
+                            ),
+                        }
+                    },
+                };
+                arms.push(arm);
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        },
+        Data::Union(x) => return Err(Error::new_spanned(x.union_token, "unions can not be converted")),
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::c0i::value::autobind::IntoValue for #ident #ty_generics #where_clause {
+            fn into_value(self) -> ::c0i::value::Value {
+                #body
+            }
+        }
+    })
+}
+
+/// Requires `bound` of every type parameter.
+fn add_bounds(input: &mut DeriveInput, bound: TokenStream2) {
+    let params: Vec<Ident> = input.generics.type_params().map(|x| x.ident.clone()).collect();
+    let where_clause = input.generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(syn::parse_quote!(#param: #bound));
+    }
+}
+
+fn field_key(ident: &Ident, attrs: &[syn::Attribute]) -> Result<String, Error> {
+    Ok(rename(attrs)?.unwrap_or_else(|| ident.to_string()))
+}
+
+fn variant_tag(variant: &Variant) -> Result<String, Error> {
+    Ok(rename(&variant.attrs)?.unwrap_or_else(|| variant.ident.to_string()))
+}
+
+/// Reads `#[c0i(rename = "...")]`.
+fn rename(attrs: &[syn::Attribute]) -> Result<Option<String>, Error> {
+    let mut r = None;
+    for attr in attrs.iter().filter(|x| x.path.is_ident("c0i")) {
+        match attr.parse_meta()? {
+            Meta::List(x) => for item in x.nested {
+                match item {
+                    NestedMeta::Meta(Meta::NameValue(x)) if x.path.is_ident("rename") =>
+                        r = Some(lit_str(&x.lit)?),
+                    x => return Err(Error::new_spanned(x, "expected `rename = \"...\"`")),
+                }
+            },
+            _ => return Err(Error::new_spanned(attr, "expected `c0i(rename = \"...\")`")),
+        }
+    }
+    Ok(r)
+}
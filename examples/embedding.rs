@@ -0,0 +1,59 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use c0i::{c0i_fn, FromValue, IntoValue, Interpreter};
+use c0i::value::Value;
+
+
+#[derive(Debug, FromValue, IntoValue)]
+struct Range {
+    start: u64,
+    end: u64,
+}
+
+/// Exposed to scripts as `range-len`.
+#[c0i_fn(pure)]
+fn range_len(range: Range) -> u64 {
+    range.end.saturating_sub(range.start)
+}
+
+fn main() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define_global("limit", 10u64);
+
+    // natives may capture the state of the host
+    let calls = Arc::new(AtomicU64::new(0));
+    let counter = calls.clone();
+    interpreter.define_fn("count-call", move || counter.fetch_add(1, Ordering::SeqCst) + 1);
+
+    interpreter.register(RANGE_LEN_NATIVE);
+    interpreter.define_global("digits", Range { start: 0, end: 10 });
+
+    let r = interpreter.eval_str("
+        (define (count-to n)
+          (count-call)
+          (cond ((gt? n limit) (error \"too far\" n))
+                (else n)))
+        (count-to 3)");
+    match r {
+        Ok(v) => println!("count-to: {}", v),
+        Err(e) => print!("{}", e),
+    }
+
+    match interpreter.call_function("count-to", &[Value::Uint(42)]) {
+        Ok(v) => println!("count-to: {}", v),
+        Err(e) => print!("{}", e),
+    }
+
+    match interpreter.eval_str("(range-len digits)") {
+        Ok(v) => println!("range-len: {}", v),
+        Err(e) => print!("{}", e),
+    }
+
+    // functions of the script can be kept and called with Rust values
+    let count_to = interpreter.get_function::<(u64,), u64>("count-to").unwrap();
+    println!("count-to: {:?}", count_to.call((7,)));
+
+    println!("limit: {:?}", interpreter.get_global("limit"));
+    println!("calls: {}", calls.load(Ordering::SeqCst));
+}
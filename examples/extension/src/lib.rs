@@ -0,0 +1,227 @@
+//! A native extension for c0i, built as a dynamic library:
+//!
+//! ```text
+//! cargo build -p c0i_extension_example
+//! c0i target/debug/libc0i_extension_example.so
+//! >>> (greet "world")
+//! ```
+//!
+//! or `(load-extension "target/debug/libc0i_extension_example.so")` from a script.
+
+use std::ffi::c_void;
+use std::ptr::null_mut;
+
+use c0i_abi::{
+    C0iFunction, C0iRegistrar, C0iStr, C0iValue, C0I_ABI_VERSION, C0I_ERROR, C0I_OK, C0iFn, tag,
+};
+
+
+#[no_mangle]
+pub extern "C" fn c0i_extension_abi_version() -> u32 {
+    C0I_ABI_VERSION
+}
+
+/// # Safety
+/// `registrar` must be the one the host passes.
+#[no_mangle]
+pub unsafe extern "C" fn c0i_extension_init(registrar: *const C0iRegistrar) -> i32 {
+    let registrar = &*registrar;
+    if registrar.abi_version != C0I_ABI_VERSION {
+        return C0I_ERROR;
+    }
+    (registrar.set_name)(registrar.host, C0iStr::new("example"));
+    let functions: [(&str, u32, u32, C0iFn); 4] = [
+        ("greet", 1, 1, greet),
+        ("checked-div", 2, 2, checked_div),
+        ("count", 0, u32::MAX, count),
+        ("first", 1, u32::MAX, first),
+    ];
+    for (name, min_args, max_args, call) in functions.iter() {
+        let function = C0iFunction {
+            name: C0iStr::new(name),
+            min_args: *min_args,
+            max_args: *max_args,
+            is_pure: 1,
+            call: *call,
+            release: Some(release),
+            data: null_mut(),
+        };
+        if (registrar.register)(registrar.host, &function) != C0I_OK {
+            return C0I_ERROR;
+        }
+    }
+    C0I_OK
+}
+
+/// Strings are returned in a box, which `release` takes back.
+fn owned_string(s: String) -> C0iValue {
+    let s = Box::leak(s.into_boxed_str());
+    C0iValue::string(C0iStr::new(s))
+}
+
+unsafe extern "C" fn release(_: *mut c_void, value: *mut C0iValue) {
+    let value = &*value;
+    if value.tag == tag::STR || value.tag == tag::SYM {
+        let s = value.payload.string;
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(s.ptr as *mut u8, s.len)));
+    }
+}
+
+unsafe fn args<'a>(args: *const C0iValue, nargs: usize) -> &'a [C0iValue] {
+    if nargs == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(args, nargs)
+    }
+}
+
+/// `(greet name)`
+unsafe extern "C" fn greet(_: *mut c_void, argv: *const C0iValue, nargs: usize, out: *mut C0iValue) -> i32 {
+    match args(argv, nargs)[0].as_str() {
+        Some(name) => {
+            *out = owned_string(format!("Hello, {}!", name));
+            C0I_OK
+        },
+        None => {
+            *out = owned_string("greet: the name should be a string".to_string());
+            C0I_ERROR
+        },
+    }
+}
+
+/// `(checked-div a b)` of two ints, it fails when `b` is zero.
+unsafe extern "C" fn checked_div(_: *mut c_void, argv: *const C0iValue, nargs: usize, out: *mut C0iValue) -> i32 {
+    let argv = args(argv, nargs);
+    let r = match (argv[0].as_int(), argv[1].as_int()) {
+        (Some(a), Some(b)) => a.checked_div(b).ok_or("checked-div: division by zero"),
+        _ => Err("checked-div: the arguments should be ints"),
+    };
+    match r {
+        Ok(r) => {
+            *out = C0iValue::int(r);
+            C0I_OK
+        },
+        Err(e) => {
+            *out = owned_string(e.to_string());
+            C0I_ERROR
+        },
+    }
+}
+
+/// `(count x ...)` is the number of its arguments.
+unsafe extern "C" fn count(_: *mut c_void, _: *const C0iValue, nargs: usize, out: *mut C0iValue) -> i32 {
+    *out = C0iValue::uint(nargs as u64);
+    C0I_OK
+}
+
+/// `(first x ...)` returns its first argument whatever it is,
+/// strings are copied since `release` frees them.
+unsafe extern "C" fn first(_: *mut c_void, argv: *const C0iValue, nargs: usize, out: *mut C0iValue) -> i32 {
+    let arg = args(argv, nargs)[0];
+    *out = match arg.as_str() {
+        Some(s) => C0iValue { tag: arg.tag, ..owned_string(s.to_string()) },
+        None => arg,
+    };
+    C0I_OK
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Host {
+        name: Option<String>,
+        functions: Vec<(String, C0iFn)>,
+    }
+
+    unsafe extern "C" fn set_name(host: *mut c_void, name: C0iStr) {
+        (*(host as *mut Host)).name = name.as_str().map(str::to_string);
+    }
+
+    unsafe extern "C" fn register(host: *mut c_void, function: *const C0iFunction) -> i32 {
+        let function = &*function;
+        let name = function.name.as_str().unwrap().to_string();
+        (*(host as *mut Host)).functions.push((name, function.call));
+        C0I_OK
+    }
+
+    fn init(abi_version: u32) -> (i32, Host) {
+        let mut host = Host::default();
+        let registrar = C0iRegistrar {
+            abi_version,
+            host: &mut host as *mut Host as *mut c_void,
+            set_name,
+            register,
+        };
+        let status = unsafe { c0i_extension_init(&registrar) };
+        (status, host)
+    }
+
+    /// Calls a function of the extension, returning the status, the result and the string of the result.
+    fn call(name: &str, argv: &[C0iValue]) -> (i32, C0iValue, Option<String>) {
+        let (_, host) = init(C0I_ABI_VERSION);
+        let f = host.functions.iter().find(|(x, _)| x == name).unwrap().1;
+        let mut out = C0iValue::nil();
+        unsafe {
+            let status = f(null_mut(), argv.as_ptr(), argv.len(), &mut out);
+            let s = out.as_str().map(str::to_string);
+            let r = out;
+            release(null_mut(), &mut out);
+            (status, r, s)
+        }
+    }
+
+    #[test]
+    fn registers_its_functions() {
+        assert_eq!(c0i_extension_abi_version(), C0I_ABI_VERSION);
+        let (status, host) = init(C0I_ABI_VERSION);
+        assert_eq!(status, C0I_OK);
+        assert_eq!(host.name.as_deref(), Some("example"));
+        let names: Vec<_> = host.functions.iter().map(|(x, _)| x.as_str()).collect();
+        assert_eq!(names, ["greet", "checked-div", "count", "first"]);
+    }
+
+    #[test]
+    fn rejects_another_abi_version() {
+        let (status, host) = init(C0I_ABI_VERSION + 1);
+        assert_eq!(status, C0I_ERROR);
+        assert!(host.functions.is_empty());
+    }
+
+    #[test]
+    fn greets() {
+        let (status, _, s) = call("greet", &[C0iValue::string(C0iStr::new("world"))]);
+        assert_eq!(status, C0I_OK);
+        assert_eq!(s.as_deref(), Some("Hello, world!"));
+
+        let (status, _, s) = call("greet", &[C0iValue::uint(1)]);
+        assert_eq!(status, C0I_ERROR);
+        assert_eq!(s.as_deref(), Some("greet: the name should be a string"));
+    }
+
+    #[test]
+    fn divides() {
+        let (status, r, _) = call("checked-div", &[C0iValue::int(7), C0iValue::int(2)]);
+        assert_eq!(status, C0I_OK);
+        assert_eq!(r.as_int(), Some(3));
+
+        let (status, _, s) = call("checked-div", &[C0iValue::int(7), C0iValue::int(0)]);
+        assert_eq!(status, C0I_ERROR);
+        assert_eq!(s.as_deref(), Some("checked-div: division by zero"));
+    }
+
+    #[test]
+    fn passes_values_through() {
+        let (_, r, _) = call("count", &[C0iValue::nil(), C0iValue::float(1.5)]);
+        assert_eq!(r.as_uint(), Some(2));
+
+        let (_, r, _) = call("first", &[C0iValue::float(1.5), C0iValue::nil()]);
+        assert_eq!(r.as_float(), Some(1.5));
+
+        let (_, r, s) = call("first", &[C0iValue::symbol(C0iStr::new("a"))]);
+        assert_eq!(r.tag, tag::SYM);
+        assert_eq!(s.as_deref(), Some("a"));
+    }
+}
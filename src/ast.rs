@@ -5,11 +5,36 @@ use crate::value::Value;
 #[derive(Debug, Clone)]
 pub enum ModuleTop {
     Import(Import),
+    Export(Export),
     TopLevel(TopLevel),
 }
 
 #[derive(Debug, Clone)]
-pub struct Import(pub Handle<Symbol>);
+pub struct Import(pub Vec<ImportSet>);
+
+#[derive(Debug, Clone)]
+pub enum ImportSet {
+    Module(Handle<Symbol>),
+    Only(Box<ImportSet>, Vec<Handle<Symbol>>),
+    Except(Box<ImportSet>, Vec<Handle<Symbol>>),
+    Prefix(Box<ImportSet>, Handle<Symbol>),
+    Rename(Box<ImportSet>, Vec<(Handle<Symbol>, Handle<Symbol>)>),
+}
+
+impl ImportSet {
+    pub fn module_name(&self) -> &Handle<Symbol> {
+        match self {
+            ImportSet::Module(name) => name,
+            ImportSet::Only(set, _) |
+            ImportSet::Except(set, _) |
+            ImportSet::Prefix(set, _) |
+            ImportSet::Rename(set, _) => set.module_name(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Export(pub Vec<Handle<Symbol>>);
 
 #[derive(Debug, Clone)]
 pub enum TopLevel {
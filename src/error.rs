@@ -1,30 +1,286 @@
+// the helpers that locate errors are used by the compiler of c0i alone
+#![cfg_attr(not(feature = "c0i"), allow(dead_code))]
+
+use std::fmt::Display;
+use std::fs::read_to_string;
+use std::sync::{Arc, Mutex, Weak};
+
+use sexpr_ir::gast::{GAst, Handle, constant::Constant, list::List, symbol::{Location, Symbol}};
+use sexpr_ir::syntax::sexpr::parse;
+
+use crate::value::result::CError;
+
+
+/// Everything that can go wrong from reading a source to running it.
+#[derive(Debug, Clone)]
+pub enum Error {
+    Parse(String),
+    Compile(Vec<CompilerError>),
+    Runtime(CError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Parse(e) => writeln!(f, "Syntax Error: {}", e),
+            Error::Compile(e) => e.iter().try_for_each(|e| write!(f, "Compile Error: {}", e)),
+            Error::Runtime(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Vec<CompilerError>> for Error {
+    fn from(e: Vec<CompilerError>) -> Self {
+        Error::Compile(e)
+    }
+}
+
+impl From<CError> for Error {
+    fn from(e: CError) -> Self {
+        Error::Runtime(e)
+    }
+}
+
+
+/// Errors are located at the form they are about, if it has a location.
 #[derive(Debug, Clone)]
 pub enum CompilerError {
     // SexprIrParseError,
-    BadSyntax(String),
-    IsNotSymbol(String),
-    IncompleteExpr(String),
-    InvalidPairRight(String),
-    InvalidExprLength(usize, usize, String),
-    InvalidExprType((), String)
+    BadSyntax(Option<SourceLocation>, String),
+    IsNotSymbol(Option<SourceLocation>, String),
+    IncompleteExpr(Option<SourceLocation>, String),
+    InvalidPairRight(Option<SourceLocation>, String),
+    InvalidExprLength(Option<SourceLocation>, usize, usize, String),
+    InvalidExprType(Option<SourceLocation>, ExprType, String),
+    NoMatchingRule(Option<SourceLocation>, String),
+    InvalidTemplate(Option<SourceLocation>, String),
+    MacroError(Option<SourceLocation>, String),
+    Unsupported(Option<SourceLocation>, String),
 }
 
-pub(crate) fn bad_syntax<T: ToString>(i: &T) -> CompilerError {
-    CompilerError::BadSyntax(i.to_string())
+/// A location with its line of source, which is taken from the text the source had when it was parsed,
+/// so that the error still shows it after the file changed or is gone.
+#[derive(Debug, Clone)]
+pub struct SourceLocation {
+    pub location: Location,
+    pub source_line: Option<String>,
 }
 
-pub(crate) fn invalid_list_tail<T: ToString>(i: &T) -> CompilerError {
-    CompilerError::InvalidPairRight(i.to_string())
+impl SourceLocation {
+    pub fn new(location: Location) -> Self {
+        let source_line = SOURCES.lock().unwrap()
+            .iter()
+            .find(|x| std::ptr::eq(x.path.as_ptr(), Handle::as_ptr(&location.path)))
+            .and_then(|x| x.text.lines().nth(location.line.checked_sub(1)?).map(str::to_string));
+        SourceLocation { location, source_line }
+    }
 }
 
-pub(crate) fn incomplete_expr<T: ToString>(i: &T) -> CompilerError {
-    CompilerError::IncompleteExpr(i.to_string())
+/// A parsed text, known by the path its locations share,
+/// so that two sources of the same name, as strings given to `eval_str` are, are kept apart.
+struct Source {
+    path: Weak<String>,
+    text: Arc<str>,
 }
 
-pub(crate) fn invalid_expr_length<T: ToString>(i: &T, takes: usize, give: usize) -> CompilerError {
-    CompilerError::InvalidExprLength(takes, give, i.to_string())
+/// The sources whose forms are still around, errors are made without reading the files again.
+static SOURCES: Mutex<Vec<Source>> = Mutex::new(Vec::new());
+
+/// Parses `source`, which is named `path` in the locations of its forms, and keeps its text
+/// for the errors located in it.
+pub fn parse_source(source: &str, path: &str) -> Result<Vec<GAst>, String> {
+    let path = Handle::new(path.to_string());
+    let mut sources = SOURCES.lock().unwrap();
+    sources.retain(|x| x.path.strong_count() > 0);
+    sources.push(Source { path: Handle::downgrade(&path), text: Arc::from(source) });
+    drop(sources);
+    parse(source, path).map_err(|e| e.to_string())
+}
+
+/// Parses the file at `path` and keeps its text.
+pub(crate) fn file_parse(path: &str) -> Result<Vec<GAst>, String> {
+    let source = read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse_source(&source, path)
+}
+
+/// What a form was expected to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExprType {
+    Symbol,
+    List,
+    SymbolOrList,
+}
+
+impl Display for ExprType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExprType::Symbol => write!(f, "a symbol"),
+            ExprType::List => write!(f, "a list"),
+            ExprType::SymbolOrList => write!(f, "a symbol or a list"),
+        }
+    }
+}
+
+impl CompilerError {
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            CompilerError::BadSyntax(pos, _) |
+            CompilerError::IsNotSymbol(pos, _) |
+            CompilerError::IncompleteExpr(pos, _) |
+            CompilerError::InvalidPairRight(pos, _) |
+            CompilerError::InvalidExprLength(pos, _, _, _) |
+            CompilerError::InvalidExprType(pos, _, _) |
+            CompilerError::NoMatchingRule(pos, _) |
+            CompilerError::InvalidTemplate(pos, _) |
+            CompilerError::MacroError(pos, _) |
+            CompilerError::Unsupported(pos, _) => pos.as_ref(),
+        }
+    }
+
+    fn location_mut(&mut self) -> &mut Option<SourceLocation> {
+        match self {
+            CompilerError::BadSyntax(pos, _) |
+            CompilerError::IsNotSymbol(pos, _) |
+            CompilerError::IncompleteExpr(pos, _) |
+            CompilerError::InvalidPairRight(pos, _) |
+            CompilerError::InvalidExprLength(pos, _, _, _) |
+            CompilerError::InvalidExprType(pos, _, _) |
+            CompilerError::NoMatchingRule(pos, _) |
+            CompilerError::InvalidTemplate(pos, _) |
+            CompilerError::MacroError(pos, _) |
+            CompilerError::Unsupported(pos, _) => pos,
+        }
+    }
+}
+
+impl Display for CompilerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompilerError::BadSyntax(_, e) => writeln!(f, "BadSyntax: {}.", e),
+            CompilerError::IsNotSymbol(_, e) => writeln!(f, "IsNotSymbol: {} is not a symbol.", e),
+            CompilerError::IncompleteExpr(_, e) => writeln!(f, "IncompleteExpr: {}.", e),
+            CompilerError::InvalidPairRight(_, e) => writeln!(f, "InvalidPairRight: {} can not have a pair right.", e),
+            CompilerError::InvalidExprLength(_, a, b, e) =>
+                writeln!(f, "InvalidExprLength: expected {} items but {} were found in {}.", a, b, e),
+            CompilerError::InvalidExprType(_, t, e) => writeln!(f, "InvalidExprType: expected {}, found {}.", t, e),
+            CompilerError::NoMatchingRule(_, e) => writeln!(f, "NoMatchingRule: no syntax rule matches {}.", e),
+            CompilerError::InvalidTemplate(_, e) => writeln!(f, "InvalidTemplate: {}.", e),
+            CompilerError::MacroError(_, e) => writeln!(f, "MacroError: {}.", e.trim_end()),
+            CompilerError::Unsupported(_, e) => writeln!(f, "Unsupported: {}.", e),
+        }?;
+        let (pos, line) = match self.location() {
+            Some(x) => (&x.location, &x.source_line),
+            None => return Ok(()),
+        };
+        writeln!(f, "\tat \"{}:{}:{}\"", pos.path, pos.line, pos.colum)?;
+        // the source line is only shown when the file could be read
+        if let Some(line) = line {
+            let indent: String = line.chars()
+                .take(pos.colum.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            writeln!(f, "\t| {}", line)?;
+            writeln!(f, "\t| {}^", indent)?;
+        }
+        Ok(())
+    }
 }
 
-pub(crate) fn invalid_expr_type<T: ToString>(i: &T, etype: ()) -> CompilerError {
-    CompilerError::InvalidExprType(etype, i.to_string())
-}
\ No newline at end of file
+impl std::error::Error for CompilerError {}
+
+/// Forms do not have a location of their own, they are located at their first symbol.
+pub trait Locate {
+    fn location(&self) -> Option<Location>;
+}
+
+/// Symbols made without a location are at line 0.
+impl Locate for Location {
+    fn location(&self) -> Option<Location> {
+        Some(self.clone()).filter(|x| x.line != 0)
+    }
+}
+
+impl Locate for Symbol {
+    fn location(&self) -> Option<Location> {
+        self.1.location()
+    }
+}
+
+impl Locate for Constant {
+    fn location(&self) -> Option<Location> {
+        self.get_sym()?.location()
+    }
+}
+
+impl Locate for List {
+    fn location(&self) -> Option<Location> {
+        self.0.iter().chain(self.1.iter()).find_map(Locate::location)
+    }
+}
+
+impl Locate for GAst {
+    fn location(&self) -> Option<Location> {
+        match self {
+            GAst::Const(x) => x.location(),
+            GAst::List(x) => x.location(),
+        }
+    }
+}
+
+impl<T: Locate> Locate for Handle<T> {
+    fn location(&self) -> Option<Location> {
+        (**self).location()
+    }
+}
+
+fn location_of<T: Locate>(i: &T) -> Option<SourceLocation> {
+    i.location().map(SourceLocation::new)
+}
+
+/// Errors in forms without any symbol are located at the closest enclosing form that has one.
+pub(crate) fn locate_in<T: Locate>(mut errors: Vec<CompilerError>, i: &T) -> Vec<CompilerError> {
+    if let Some(pos) = location_of(i) {
+        for e in errors.iter_mut().filter(|e| e.location().is_none()) {
+            *e.location_mut() = Some(pos.clone());
+        }
+    }
+    errors
+}
+
+pub(crate) fn bad_syntax<T: ToString + Locate>(i: &T) -> CompilerError {
+    CompilerError::BadSyntax(location_of(i), i.to_string())
+}
+
+pub(crate) fn is_not_symbol<T: ToString + Locate>(i: &T) -> CompilerError {
+    CompilerError::IsNotSymbol(location_of(i), i.to_string())
+}
+
+pub(crate) fn invalid_list_tail<T: ToString + Locate>(i: &T) -> CompilerError {
+    CompilerError::InvalidPairRight(location_of(i), i.to_string())
+}
+
+pub(crate) fn incomplete_expr<T: ToString + Locate>(i: &T) -> CompilerError {
+    CompilerError::IncompleteExpr(location_of(i), i.to_string())
+}
+
+pub(crate) fn invalid_expr_length<T: ToString + Locate>(i: &T, takes: usize, give: usize) -> CompilerError {
+    CompilerError::InvalidExprLength(location_of(i), takes, give, i.to_string())
+}
+
+pub(crate) fn invalid_expr_type<T: ToString + Locate>(i: &T, etype: ExprType) -> CompilerError {
+    CompilerError::InvalidExprType(location_of(i), etype, i.to_string())
+}
+
+pub(crate) fn no_matching_rule<T: ToString>(pos: &Location, i: &T) -> CompilerError {
+    CompilerError::NoMatchingRule(location_of(pos), i.to_string())
+}
+
+pub(crate) fn invalid_template<T: ToString>(pos: &Location, i: &T) -> CompilerError {
+    CompilerError::InvalidTemplate(location_of(pos), i.to_string())
+}
+
+pub(crate) fn macro_error<T: ToString>(pos: &Location, i: &T) -> CompilerError {
+    CompilerError::MacroError(location_of(pos), i.to_string())
+}
@@ -1,4 +1,6 @@
 use std::any::TypeId;
+use std::cmp::Ordering;
+use std::fmt;
 use std::io;
 use std::mem::transmute;
 use pr47::builtins::closure::Closure;
@@ -16,6 +18,8 @@ use xjbutil::boxed_slice;
 use xjbutil::rand::random;
 use xjbutil::unchecked::UnsafeFrom;
 
+use crate::eval47::commons::FFIFunction;
+
 macro_rules! implement_blanket_call_unchecked {
     () => {
         unsafe fn call_unchecked<CTX: VMContext>(
@@ -263,6 +267,202 @@ impl FunctionBase for RandBind {
     implement_blanket_call_unchecked!{}
 }
 
+/// A number of the VM, which has neither the bigints nor the rationals of the prelude.
+#[derive(Clone, Copy)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    unsafe fn from_value(value: Value) -> Option<Number> {
+        if !value.is_value() {
+            return None;
+        }
+        match ValueTypeTag::unsafe_from((value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
+            ValueTypeTag::Int => Some(Number::Int(value.vt_data.inner.int_value)),
+            ValueTypeTag::Float => Some(Number::Float(value.vt_data.inner.float_value)),
+            ValueTypeTag::Bool => None
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        match self {
+            Number::Int(x) => x as f64,
+            Number::Float(x) => x
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Number::Int(x) => Value::new_int(x),
+            Number::Float(x) => Value::new_float(x)
+        }
+    }
+
+    fn compare(self, other: Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => Some(a.cmp(&b)),
+            (a, b) => a.to_f64().partial_cmp(&b.to_f64())
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(x) => write!(f, "{}", x),
+            Number::Float(x) => write!(f, "{}", x)
+        }
+    }
+}
+
+unsafe fn number(what: &str, value: Value) -> Result<Number, String> {
+    Number::from_value(value).ok_or_else(|| format!("{} expects numbers", what))
+}
+
+/// Ints give an int as they do in the prelude, and raise where the prelude would make a bigint of
+/// it, any float makes the result a float.
+unsafe fn arithmetic(
+    op: &str,
+    a: Value,
+    b: Value,
+    int: fn(i64, i64) -> Option<i64>,
+    float: fn(f64, f64) -> f64
+) -> Result<Value, String> {
+    let (a, b) = (number(op, a)?, number(op, b)?);
+    let r = match (a, b) {
+        (Number::Int(x), Number::Int(y)) => Number::Int(
+            int(x, y).ok_or_else(|| format!("{} of {} and {} overflows an int", op, a, b))?
+        ),
+        _ => Number::Float(float(a.to_f64(), b.to_f64()))
+    };
+    Ok(r.into_value())
+}
+
+unsafe fn add(a: Value, b: Value) -> Result<Value, String> {
+    arithmetic("`+`", a, b, i64::checked_add, |a, b| a + b)
+}
+
+unsafe fn sub(a: Value, b: Value) -> Result<Value, String> {
+    arithmetic("`-`", a, b, i64::checked_sub, |a, b| a - b)
+}
+
+unsafe fn mul(a: Value, b: Value) -> Result<Value, String> {
+    arithmetic("`*`", a, b, i64::checked_mul, |a, b| a * b)
+}
+
+/// Ints that do not divide raise, the prelude makes a rational of them.
+unsafe fn div(a: Value, b: Value) -> Result<Value, String> {
+    if let (Number::Int(x), Number::Int(y)) = (number("`/`", a)?, number("`/`", b)?) {
+        if y == 0 {
+            return Err(format!("`/` of {} by zero", x));
+        }
+        if x.checked_rem(y).map_or(false, |r| r != 0) {
+            return Err(format!("`/` of {} by {} is a rational, which Pr47 does not have", x, y));
+        }
+    }
+    arithmetic("`/`", a, b, i64::checked_div, |a, b| a / b)
+}
+
+unsafe fn rem(a: Value, b: Value) -> Result<Value, String> {
+    if let (Number::Int(x), Number::Int(0)) = (number("`%`", a)?, number("`%`", b)?) {
+        return Err(format!("`%` of {} by zero", x));
+    }
+    arithmetic("`%`", a, b, |a, b| Some(a.wrapping_rem(b)), |a, b| a % b)
+}
+
+/// Numbers are equal by their value whatever their type, bools by theirs and the other values if
+/// they are the same one.
+unsafe fn same(a: Value, b: Value) -> Result<Value, String> {
+    let r = match (Number::from_value(a), Number::from_value(b)) {
+        (Some(x), Some(y)) => x.compare(y) == Some(Ordering::Equal),
+        (None, None) if a.is_value() && b.is_value() =>
+            a.vt_data.inner.bool_value == b.vt_data.inner.bool_value,
+        (None, None) if !a.is_value() && !b.is_value() => a.ptr_repr.ptr == b.ptr_repr.ptr,
+        _ => false
+    };
+    Ok(Value::new_bool(r))
+}
+
+unsafe fn lt(a: Value, b: Value) -> Result<Value, String> {
+    let r = number("comparison", a)?.compare(number("comparison", b)?);
+    Ok(Value::new_bool(r == Some(Ordering::Less)))
+}
+
+unsafe fn le(a: Value, b: Value) -> Result<Value, String> {
+    let r = number("comparison", a)?.compare(number("comparison", b)?);
+    Ok(Value::new_bool(matches!(r, Some(Ordering::Less) | Some(Ordering::Equal))))
+}
+
+macro_rules! implement_number_bind {
+    ($bind:ident, $name:ident, $op:ident) => {
+        pub struct $bind;
+        pub const $name: $bind = $bind;
+
+        impl FunctionBase for $bind {
+            fn signature(tyck_info_pool: &mut TyckInfoPool) -> Signature {
+                Signature {
+                    func_type: tyck_info_pool.create_function_type(
+                        &[tyck_info_pool.get_any_type(), tyck_info_pool.get_any_type()],
+                        &[tyck_info_pool.get_any_type()],
+                        &[]
+                    ),
+                    param_options: boxed_slice![DataOption::RawUntyped, DataOption::RawUntyped],
+                    ret_option: boxed_slice![DataOption::Copy]
+                }
+            }
+
+            unsafe fn call_rtlc<CTX: VMContext>(
+                context: &mut CTX,
+                args: &[Value],
+                rets: &[*mut Value]
+            ) -> Result<(), FFIException> {
+                match $op(args[0], args[1]) {
+                    Ok(value) => {
+                        *rets[0] = value;
+                        Ok(())
+                    },
+                    Err(e) => {
+                        let e = Value::new_owned(e);
+                        context.add_heap_managed(e);
+                        Err(FFIException::Checked(e))
+                    }
+                }
+            }
+
+            implement_blanket_call_unchecked!{}
+        }
+    }
+}
+
+implement_number_bind! { AddBind, ADD_BIND, add }
+implement_number_bind! { SubBind, SUB_BIND, sub }
+implement_number_bind! { MulBind, MUL_BIND, mul }
+implement_number_bind! { DivBind, DIV_BIND, div }
+implement_number_bind! { RemBind, REM_BIND, rem }
+implement_number_bind! { SameBind, SAME_BIND, same }
+implement_number_bind! { LtBind, LT_BIND, lt }
+implement_number_bind! { LeBind, LE_BIND, le }
+
+/// The functions that the arithmetic and the comparisons compile to, so that they give what the
+/// prelude gives or raise. Their FFI ids follow the 0 of `display` in every program.
+pub const NUMBER_BINDS: &'static [(&'static str, FFIFunction)] = &[
+    ("+", &ADD_BIND),
+    ("-", &SUB_BIND),
+    ("*", &MUL_BIND),
+    ("/", &DIV_BIND),
+    ("%", &REM_BIND),
+    ("=", &SAME_BIND),
+    ("<", &LT_BIND),
+    ("<=", &LE_BIND),
+];
+
+/// The FFI id of the function of `NUMBER_BINDS` named `op`.
+pub fn number_bind_id(op: &str) -> usize {
+    1 + NUMBER_BINDS.iter().position(|x| x.0 == op).unwrap()
+}
+
 pub struct YieldBind;
 pub const YIELD_BIND: YieldBind = YieldBind;
 
@@ -26,9 +26,25 @@
   (cond ((>= n (list-length list)) (raise "IndexOutOfBounds"))
         (else (list-ref-impl list n))))
 
+(define (reverse list)
+  (define (reverse-impl list acc)
+    (cond ((= nil list) acc)
+          (else (reverse-impl (cdr list) (cons (car list) acc)))))
+  (reverse-impl list nil))
+
+(define (append x y)
+  (define (append-impl x acc)
+    (cond ((= nil x) acc)
+          (else (append-impl (cdr x) (cons (car x) acc)))))
+  (append-impl (reverse x) y))
+
+(define (--pr47-builtin-append x y) (append x y))
+
 (define (map f list)
-  (cond ((= nil list) nil)
-        (else (cons (f (car list)) (map f (cdr list))))))
+  (define (map-impl f list acc)
+    (cond ((= nil list) (reverse acc))
+          (else (map-impl f (cdr list) (cons (f (car list)) acc)))))
+  (map-impl f list nil))
 
 (define (display-list list)
   (define (display-list-impl list)
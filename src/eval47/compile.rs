@@ -1,5 +1,5 @@
 use std::any::TypeId;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryInto;
 use std::ptr::NonNull;
 use pr47::builtins::closure::Closure;
@@ -14,6 +14,7 @@ use xjbutil::boxed_slice;
 use xjbutil::korobka::Korobka;
 use xjbutil::slice_arena::SliceArena;
 use crate::ast::{Call, Cond, Expr, Function, Let, Set, TopLevel};
+use crate::eval47::builtins::number_bind_id;
 use crate::eval47::commons::{CompiledFunction, CompiledProgram, FFIAsyncFunction, FFIFunction};
 use crate::eval47::data_map::GValue;
 use crate::eval47::min_scope_analysis::AnalyseResult;
@@ -21,6 +22,7 @@ use crate::eval47::util::{bitcast_i64_usize, MantisGod};
 use crate::{guard, guard2};
 use crate::eval47::util::Guard;
 use crate::value::Value;
+use crate::value::callable::Callable;
 
 pub struct CompileContext {
     tyck_info_pool: TyckInfoPool,
@@ -38,6 +40,11 @@ pub struct CompileContext {
     compiling_loops: Vec<LoopContext>,
 
     func_queue: VecDeque<(Vec<String>, Handle<Function>)>,
+
+    // the `Jump` of each tail call, and the function it jumps into
+    tail_jumps: Vec<(usize, usize)>,
+    // the functions each function jumps into through its tail calls
+    tail_callees: HashMap<usize, HashSet<usize>>,
 }
 
 pub struct CompileResult {
@@ -80,7 +87,10 @@ impl CompileContext {
             compiling_function_names: Vec::new(),
             compiling_loops: Vec::new(),
 
-            func_queue: VecDeque::new()
+            func_queue: VecDeque::new(),
+
+            tail_jumps: Vec::new(),
+            tail_callees: HashMap::new()
         };
         context
     }
@@ -117,6 +127,7 @@ impl CompileContext {
             self.compiling_function_names = function_names;
             self.compile_function(func_handle, analyse_result);
         }
+        self.link_tail_calls();
 
         let mut functions = self.functions.into_iter().collect::<Vec<_>>();
         functions.sort_by_key(|x| x.0);
@@ -139,6 +150,7 @@ impl CompileContext {
 }
 
 struct CompilingFunction {
+    func_id: usize,
     #[allow(dead_code)]
     start_addr: usize,
     capture_count: usize,
@@ -241,6 +253,7 @@ impl CompileContext {
 
         let start_addr = self.code.len();
         let compiling_function = CompilingFunction {
+            func_id,
             start_addr,
             capture_count: captures.len(),
             arg_count: param_var_ids.len(),
@@ -248,7 +261,7 @@ impl CompileContext {
         };
         self.compiling_function_chain.push(compiling_function);
 
-        let ret_pos = self.compile_stmt_list(&func.body, analyse_result);
+        let ret_pos = self.compile_stmt_list(&func.body, analyse_result, true);
         if let Some(ret_pos) = ret_pos {
             self.code.push(Insc::ReturnOne(ret_pos));
         } else {
@@ -272,14 +285,17 @@ impl CompileContext {
         g.cancel();
     }
 
+    /// `tail` tells whether the value of the last statement is what the function returns.
     fn compile_stmt_list(
         &mut self,
         stmt_list: &[TopLevel],
-        analyse_result: &mut AnalyseResult
+        analyse_result: &mut AnalyseResult,
+        tail: bool
     ) -> Option<usize> {
         let mut ret = None;
-        for stmt in stmt_list {
-            ret = self.compile_stmt(stmt, analyse_result, None);
+        for (idx, stmt) in stmt_list.iter().enumerate() {
+            let is_last = idx == stmt_list.len() - 1;
+            ret = self.compile_stmt(stmt, analyse_result, None, tail && is_last);
         }
         ret
     }
@@ -288,7 +304,8 @@ impl CompileContext {
         &mut self,
         stmt: &TopLevel,
         analyse_result: &mut AnalyseResult,
-        tgt: Option<usize>
+        tgt: Option<usize>,
+        tail: bool
     ) -> Option<usize> {
         match stmt {
             TopLevel::Function(func_handle) => {
@@ -306,10 +323,10 @@ impl CompileContext {
                     .try_into()
                     .unwrap();
                 let var_id = bitcast_i64_usize(var_id);
-                self.compile_expr(expr, analyse_result, Some(var_id));
+                self.compile_expr(expr, analyse_result, Some(var_id), false);
                 None
             },
-            TopLevel::Expr(expr) => Some(self.compile_expr(expr, analyse_result, tgt))
+            TopLevel::Expr(expr) => Some(self.compile_expr(expr, analyse_result, tgt, tail))
         }
     }
 
@@ -317,16 +334,17 @@ impl CompileContext {
         &mut self,
         expr: &Expr,
         analyse_result: &mut AnalyseResult,
-        tgt: Option<usize>
+        tgt: Option<usize>,
+        tail: bool
     ) -> usize {
         match expr {
             Expr::Value(value) => self.compile_value(value, analyse_result, tgt),
             Expr::Variable(var) => self.compile_var(var.clone(), analyse_result, tgt),
             Expr::Lambda(lambda) => self.compile_lambda(lambda.clone(), analyse_result, tgt),
-            Expr::Let(let_item) => self.compile_let(let_item.clone(), analyse_result, tgt),
+            Expr::Let(let_item) => self.compile_let(let_item.clone(), analyse_result, tgt, tail),
             Expr::Set(set) => self.compile_set(set.clone(), analyse_result, tgt),
-            Expr::Cond(cond) => self.compile_cond(cond.clone(), analyse_result, tgt),
-            Expr::FunctionCall(call) => self.compile_call(call.clone(), analyse_result, tgt)
+            Expr::Cond(cond) => self.compile_cond(cond.clone(), analyse_result, tgt, tail),
+            Expr::FunctionCall(call) => self.compile_call(call.clone(), analyse_result, tgt, tail)
         }
     }
 
@@ -337,7 +355,20 @@ impl CompileContext {
     ) -> MantisGod<usize, usize, (bool, usize)> {
         match expr {
             Expr::Variable(var) => self.compile_var_for_fn_call(var.clone(), analyse_result),
-            _ => MantisGod::Left(self.compile_expr(expr, analyse_result, None))
+            Expr::Value(Value::Callable(Callable::Native(native))) => {
+                let key = match native.name {
+                    "cons" => "BuiltinConsFuncID",
+                    "append" => "BuiltinAppendFuncID",
+                    name => panic!("native function `{}` is not supported by Pr47", name),
+                };
+                let func_id: i64 = analyse_result.global_data_map.get(key)
+                    .unwrap_or_else(|| panic!("the `{}` function should be built into the program", native.name))
+                    .clone()
+                    .try_into()
+                    .unwrap();
+                MantisGod::Middle(bitcast_i64_usize(func_id))
+            },
+            _ => MantisGod::Left(self.compile_expr(expr, analyse_result, None, false))
         }
     }
 
@@ -540,7 +571,8 @@ impl CompileContext {
         &mut self,
         let_item: Handle<Let>,
         analyse_result: &mut AnalyseResult,
-        tgt: Option<usize>
+        tgt: Option<usize>,
+        tail: bool
     ) -> usize {
         let mut g = guard2!(
             let_item.pos,
@@ -561,11 +593,11 @@ impl CompileContext {
             let var_id = bitcast_i64_usize(var_id);
             let var_id = self.compiling_function_chain.last().unwrap()
                 .translate_local_id_to_address(var_id);
-            self.compile_expr(&bind.1, analyse_result, Some(var_id));
+            self.compile_expr(&bind.1, analyse_result, Some(var_id), false);
             g.cancel();
         }
 
-        let body_ret = self.compile_stmt_list(&let_item.body, analyse_result);
+        let body_ret = self.compile_stmt_list(&let_item.body, analyse_result, tail);
         let tgt = if let Some(tgt) = tgt {
             tgt
         } else {
@@ -600,7 +632,7 @@ impl CompileContext {
         let var_id = bitcast_i64_usize(var_id);
         let var_id = self.compiling_function_chain.last().unwrap()
             .translate_local_id_to_address(var_id);
-        self.compile_expr(&set.value, analyse_result, Some(var_id));
+        self.compile_expr(&set.value, analyse_result, Some(var_id), false);
 
         g.cancel();
         if let Some(tgt) = tgt {
@@ -615,7 +647,8 @@ impl CompileContext {
         &mut self,
         cond: Handle<Cond>,
         analyse_result: &mut AnalyseResult,
-        tgt: Option<usize>
+        tgt: Option<usize>,
+        tail: bool
     ) -> usize {
         let mut g = guard2!(
             cond.pos,
@@ -639,12 +672,12 @@ impl CompileContext {
                 }
             }
 
-            let condition = self.compile_expr(&pair.0, analyse_result, None);
+            let condition = self.compile_expr(&pair.0, analyse_result, None, false);
             let code_idx = self.code.len();
             last_condition_fail_jump_idx = Some(code_idx);
 
             self.code.push(Insc::JumpIfFalse(condition, 0));
-            self.compile_expr(&pair.1, analyse_result, Some(tgt));
+            self.compile_expr(&pair.1, analyse_result, Some(tgt), tail);
             let code_idx = self.code.len();
             jump_to_end_idx.push(code_idx);
             self.code.push(Insc::Jump(0));
@@ -660,7 +693,7 @@ impl CompileContext {
         }
 
         if let Some(else_branch) = cond.other.as_ref() {
-            self.compile_expr(else_branch, analyse_result, Some(tgt));
+            self.compile_expr(else_branch, analyse_result, Some(tgt), tail);
         } else {
             self.code.push(Insc::CreateObject(0));
             self.code.push(Insc::Raise(0));
@@ -681,7 +714,8 @@ impl CompileContext {
         &mut self,
         call: Handle<Call>,
         analyse_result: &mut AnalyseResult,
-        tgt: Option<usize>
+        tgt: Option<usize>,
+        tail: bool
     ) -> usize {
         let mut g = if let Some(pos) = call.0[0].location() {
             guard2!(
@@ -702,7 +736,8 @@ impl CompileContext {
                 sym.0.as_str(),
                 &call.0[1..],
                 analyse_result,
-                Some(tgt)
+                Some(tgt),
+                tail
             ) {
                 g.cancel();
                 return result;
@@ -711,7 +746,7 @@ impl CompileContext {
 
         let mut args = Vec::new();
         for arg in call.as_ref().0.iter().skip(1) {
-            args.push(self.compile_expr(arg, analyse_result, None));
+            args.push(self.compile_expr(arg, analyse_result, None, false));
         }
 
         if let Expr::Variable(sym) = &call.0[0] {
@@ -768,11 +803,15 @@ impl CompileContext {
                            "expected {} args, got {}",
                            arg_count, call.0.len() - 1);
 
-                self.code.push(Insc::Call(func_id, unsafe {
-                    self.slice_arena.unsafe_make(&args)
-                }, unsafe {
-                    self.slice_arena.unsafe_make(&[tgt])
-                }));
+                if tail {
+                    self.compile_tail_call(func_id, &args, analyse_result);
+                } else {
+                    self.code.push(Insc::Call(func_id, unsafe {
+                        self.slice_arena.unsafe_make(&args)
+                    }, unsafe {
+                        self.slice_arena.unsafe_make(&[tgt])
+                    }));
+                }
                 g.cancel();
             },
             MantisGod::Right((is_async, ffi_func_id)) => {
@@ -839,6 +878,128 @@ impl CompileContext {
         tgt
     }
 
+    /// A call in tail position to a function known at compile time re-uses the current frame:
+    /// the arguments are moved into the parameter slots of the callee and execution jumps to its start.
+    /// Such a callee has no captures, so its parameters are the first slots of the frame.
+    fn compile_tail_call(&mut self, func_id: usize, args: &[usize], analyse_result: &AnalyseResult) {
+        let param_var_ids: Vec<GValue> = analyse_result.functions.get_raw_key(func_id, "ParamVarIDs")
+            .unwrap()
+            .clone()
+            .try_into()
+            .unwrap();
+        let param_addrs = param_var_ids.into_iter()
+            .map(|var_id| bitcast_i64_usize(var_id.try_into().unwrap()))
+            .collect::<Vec<_>>();
+
+        // an argument may itself be a parameter slot that gets overwritten before it is read,
+        // the arguments are then staged in temporaries above every parameter slot
+        let mut staged_args = Vec::new();
+        if args.iter().any(|arg| param_addrs.contains(arg)) {
+            let compiling_function = self.compiling_function_chain.last_mut().unwrap();
+            let params_end = param_addrs.iter().map(|x| x + 1).max().unwrap_or(0);
+            compiling_function.local_count = compiling_function.local_count.max(params_end);
+            for arg in args {
+                let tmp = self.compiling_function_chain.last_mut().unwrap().allocate_temp();
+                self.code.push(Insc::Move(*arg, tmp));
+                staged_args.push(tmp);
+            }
+        } else {
+            staged_args.extend_from_slice(args);
+        }
+
+        for (arg, param) in staged_args.iter().zip(param_addrs.iter()) {
+            if arg != param {
+                self.code.push(Insc::Move(*arg, *param));
+            }
+        }
+
+        // the start of the callee is only known once it is compiled, see `link_tail_calls`
+        let caller = self.compiling_function_chain.last().unwrap().func_id;
+        self.tail_callees.entry(caller).or_default().insert(func_id);
+        self.tail_jumps.push((self.code.len(), func_id));
+        self.code.push(Insc::Jump(0));
+    }
+
+    /// Points the jumps of the tail calls at their callees, and makes the frame of every function
+    /// large enough for the functions it may jump into, directly or through their own tail calls.
+    fn link_tail_calls(&mut self) {
+        for (code_idx, func_id) in self.tail_jumps.iter() {
+            self.code[*code_idx] = Insc::Jump(self.functions[func_id].start_addr);
+        }
+
+        let functions = &mut self.functions;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (caller, callees) in self.tail_callees.iter() {
+                let stack_size = callees.iter()
+                    .map(|x| functions[x].stack_size)
+                    .max()
+                    .unwrap_or(0);
+                let caller = functions.get_mut(caller).unwrap();
+                if caller.stack_size < stack_size {
+                    caller.stack_size = stack_size;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    /// Calls the function of `NUMBER_BINDS` named `op`, which promotes and raises as the c0i
+    /// prelude does, on `a` and `b`.
+    fn compile_number_call(&mut self, op: &str, a: usize, b: usize, tgt: usize) {
+        self.code.push(Insc::FFICallRtlc(
+            number_bind_id(op),
+            unsafe { self.slice_arena.unsafe_make(&[a, b]) },
+            unsafe { self.slice_arena.unsafe_make(&[tgt]) }
+        ));
+    }
+
+    /// Folds `args` with `op` starting from `unit`, as the c0i prelude does,
+    /// so that `(+)` is 0 and `(- x)` negates `x`.
+    fn compile_builtin_fold(&mut self, args: &[usize], tgt: usize, unit: i64, op: &str) -> usize {
+        match args {
+            [] => self.code.push(Insc::MakeIntConst(unit, tgt)),
+            [x] => {
+                let unit_pos = self.compiling_function_chain.last_mut().unwrap().allocate_temp();
+                self.code.push(Insc::MakeIntConst(unit, unit_pos));
+                self.compile_number_call(op, unit_pos, *x, tgt);
+            },
+            [first, rest @ ..] => {
+                self.compile_number_call(op, *first, rest[0], tgt);
+                for x in &rest[1..] {
+                    self.compile_number_call(op, tgt, *x, tgt);
+                }
+            }
+        }
+        tgt
+    }
+
+    /// `(< a b c)` is `(and (< a b) (< b c))`, `swap` compares `b` to `a` instead.
+    fn compile_builtin_chain(&mut self, args: &[usize], tgt: usize, op: &str, swap: bool) -> usize {
+        let mut jump_to_end_idx = Vec::new();
+        for (i, pair) in args.windows(2).enumerate() {
+            if swap {
+                self.compile_number_call(op, pair[1], pair[0], tgt);
+            } else {
+                self.compile_number_call(op, pair[0], pair[1], tgt);
+            }
+            if i + 2 < args.len() {
+                jump_to_end_idx.push(self.code.len());
+                self.code.push(Insc::JumpIfFalse(tgt, 0));
+            }
+        }
+        let code_len = self.code.len();
+        for idx in jump_to_end_idx {
+            if let Insc::JumpIfFalse(_, dest) = &mut self.code[idx] {
+                *dest = code_len;
+            } else {
+                unreachable!()
+            }
+        }
+        tgt
+    }
+
     fn try_compile_builtin(
         &mut self,
         op: &str,
@@ -860,22 +1021,10 @@ impl CompileContext {
                 tgt
             },
             "=" => {
-                assert_eq!(args.len(), 2, "`=` expects 2 arguments");
-                self.code.push(Insc::EqAny(args[0], args[1], tgt));
-                tgt
-            },
-            "+" => {
-                assert!(args.len() >= 2, "`+` expects at least 2 arguments");
-                if args.len() == 2 {
-                    self.code.push(Insc::AddAny(args[0], args[1], tgt));
-                } else {
-                    self.code.push(Insc::Move(args[0], tgt));
-                    for i in 1..args.len() {
-                        self.code.push(Insc::AddAny(tgt, args[i], tgt));
-                    }
-                }
-                tgt
+                assert!(args.len() >= 2, "`=` expects at least 2 arguments");
+                self.compile_builtin_chain(args, tgt, "=", false)
             },
+            "+" => self.compile_builtin_fold(args, tgt, 0, "+"),
             "~" => {
                 assert!(args.len() >= 2, "`~` expects at least 2 arguments");
                 let string_type = self.tyck_info_pool.get_string_type();
@@ -890,55 +1039,39 @@ impl CompileContext {
                 tgt
             },
             "-" => {
-                assert_eq!(args.len(), 2, "`-` expects 2 arguments");
-                self.code.push(Insc::SubAny(args[0], args[1], tgt));
-                tgt
-            },
-            "*" => {
-                assert!(args.len() >= 2, "`*` expects at least 2 arguments");
-                if args.len() == 2 {
-                    self.code.push(Insc::MulAny(args[0], args[1], tgt));
-                } else {
-                    self.code.push(Insc::Move(args[0], tgt));
-                    for i in 1..args.len() {
-                        self.code.push(Insc::MulAny(tgt, args[i], tgt));
-                    }
-                }
-                tgt
+                assert!(!args.is_empty(), "`-` expects at least 1 argument");
+                self.compile_builtin_fold(args, tgt, 0, "-")
             },
+            "*" => self.compile_builtin_fold(args, tgt, 1, "*"),
             "/" => {
-                assert_eq!(args.len(), 2, "`/` expects 2 arguments");
-                self.code.push(Insc::DivAny(args[0], args[1], tgt));
-                tgt
+                assert!(!args.is_empty(), "`/` expects at least 1 argument");
+                self.compile_builtin_fold(args, tgt, 1, "/")
             },
             "%" => {
                 assert_eq!(args.len(), 2, "`%` expects 2 arguments");
-                self.code.push(Insc::ModAny(args[0], args[1], tgt));
+                self.compile_number_call("%", args[0], args[1], tgt);
                 tgt
             },
             ">" => {
-                assert_eq!(args.len(), 2, "`>` expects 2 arguments");
-                self.code.push(Insc::LtAny(args[1], args[0], tgt));
-                tgt
+                assert!(args.len() >= 2, "`>` expects at least 2 arguments");
+                self.compile_builtin_chain(args, tgt, "<", true)
             },
             "<" => {
-                assert_eq!(args.len(), 2, "`<` expects 2 arguments");
-                self.code.push(Insc::LtAny(args[0], args[1], tgt));
-                tgt
+                assert!(args.len() >= 2, "`<` expects at least 2 arguments");
+                self.compile_builtin_chain(args, tgt, "<", false)
             },
             ">=" => {
-                assert_eq!(args.len(), 2, "`>=` expects 2 arguments");
-                self.code.push(Insc::LeAny(args[1], args[0], tgt));
-                tgt
+                assert!(args.len() >= 2, "`>=` expects at least 2 arguments");
+                self.compile_builtin_chain(args, tgt, "<=", true)
             },
             "<=" => {
-                assert_eq!(args.len(), 2, "`<=` expects 2 arguments");
-                self.code.push(Insc::LeAny(args[0], args[1], tgt));
-                tgt
+                assert!(args.len() >= 2, "`<=` expects at least 2 arguments");
+                self.compile_builtin_chain(args, tgt, "<=", false)
             },
             "!=" => {
                 assert_eq!(args.len(), 2, "`!=` expects 2 arguments");
-                self.code.push(Insc::NeAny(args[0], args[1], tgt));
+                self.compile_number_call("=", args[0], args[1], tgt);
+                self.code.push(Insc::NotAny(tgt, tgt));
                 tgt
             },
             "not" => {
@@ -1062,7 +1195,8 @@ impl CompileContext {
         op: &str,
         args: &[Expr],
         analyse_result: &mut AnalyseResult,
-        tgt: Option<usize>
+        tgt: Option<usize>,
+        tail: bool
     ) -> Option<usize> {
         let tgt = if let Some(tgt) = tgt { tgt } else {
             self.compiling_function_chain.last_mut().unwrap().allocate_temp()
@@ -1073,7 +1207,7 @@ impl CompileContext {
                 let loop_start = self.code.len();
                 self.compiling_loops.push(LoopContext::new(loop_start));
                 for arg in args {
-                    self.compile_expr(arg, analyse_result, None);
+                    self.compile_expr(arg, analyse_result, None, false);
                 }
                 self.code.push(Insc::Jump(loop_start));
                 let loop_end = self.code.len();
@@ -1100,15 +1234,15 @@ impl CompileContext {
             },
             "if" => {
                 assert!(args.len() == 3 || args.len() == 2, "`if` expects 2 or 3 arguments");
-                let cond = self.compile_expr(&args[0], analyse_result, None);
+                let cond = self.compile_expr(&args[0], analyse_result, None, false);
                 let then_addr = self.code.len();
                 self.code.push(Insc::JumpIfFalse(0, 0));
-                self.compile_expr(&args[1], analyse_result, Some(tgt));
+                self.compile_expr(&args[1], analyse_result, Some(tgt), tail);
                 let then_done_addr = self.code.len();
                 self.code.push(Insc::Jump(0));
                 let else_addr = self.code.len();
                 if args.len() == 3 {
-                    self.compile_expr(&args[2], analyse_result, Some(tgt));
+                    self.compile_expr(&args[2], analyse_result, Some(tgt), tail);
                 } else {
                     self.code.push(Insc::MakeBoolConst(false, tgt));
                 }
@@ -1132,7 +1266,7 @@ impl CompileContext {
                                "`spawn` expects the same number of arguments as the function it is spawning");
                     let mut spawn_args = Vec::new();
                     for arg in args.iter().skip(1) {
-                        spawn_args.push(self.compile_expr(arg, analyse_result, None));
+                        spawn_args.push(self.compile_expr(arg, analyse_result, None, false));
                     }
 
                     self.code.push(Insc::Spawn(func_id, unsafe { self.slice_arena.unsafe_make(&spawn_args) }));
@@ -1146,7 +1280,7 @@ impl CompileContext {
                 assert!(args.len() >= 2, "`and` requires at least two arguments");
                 let mut jump_to_fail_idx = Vec::new();
                 for arg in args {
-                    let tmp = self.compile_expr(arg, analyse_result, None);
+                    let tmp = self.compile_expr(arg, analyse_result, None, false);
                     jump_to_fail_idx.push(self.code.len());
                     self.code.push(Insc::JumpIfFalse(tmp, 0));
                 }
@@ -1176,7 +1310,7 @@ impl CompileContext {
                 assert!(args.len() >= 2, "`or` requires at least two arguments");
                 let mut jump_to_next_idx = Vec::new();
                 for arg in args {
-                    let tmp = self.compile_expr(arg, analyse_result, None);
+                    let tmp = self.compile_expr(arg, analyse_result, None, false);
                     jump_to_next_idx.push(self.code.len());
                     self.code.push(Insc::JumpIfTrue(tmp, 0));
                 }
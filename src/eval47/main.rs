@@ -11,6 +11,8 @@ use sexpr_ir::syntax::sexpr::parse;
 use xjbutil::std_ext::ResultExt;
 use xjbutil::unchecked::UncheckedSendSync;
 
+use c0i::Error;
+use c0i::error::parse_source;
 use c0i::ast::TopLevel;
 use c0i::eval47::builtins::{
     DISPLAY_BIND,
@@ -25,6 +27,8 @@ use c0i::eval47::compile::CompileContext;
 use c0i::eval47::min_scope_analysis::AnalyseContext;
 use c0i::eval47::util::{bitcast_i64_usize, read_to_string_trim_comments};
 use c0i::sexpr_to_ast::FromSexpr;
+use c0i::prelude;
+use c0i::sexpr_to_ast::macros::Expander;
 
 const BUILTINS: &'static str = include_str!("./builtins.scm");
 
@@ -73,8 +77,11 @@ fn main() {
         eprintln!("Transforming builtins");
         let builtins = parse(BUILTINS, Arc::new("builtins".to_string()))
             .expect("failed parsing builtins");
+        let mut expander = Expander::with_env(prelude::init());
         for piece in builtins {
-            top_levels.push(TopLevel::from_sexpr(&piece).unwrap());
+            if let Some(piece) = expander.expand_top(&piece).unwrap() {
+                top_levels.push(TopLevel::from_sexpr(&piece).unwrap());
+            }
         }
     } else {
         eprintln!("Skipping builtins");
@@ -87,13 +94,18 @@ fn main() {
 
         eprintln!("Transforming source file `{}`", arg);
         let file_content = read_to_string_trim_comments(arg).unwrap();
-        let sexprs = parse(&file_content, Arc::new(arg.to_string()))
+        let sexprs = parse_source(&file_content, arg)
             .expect("failed parsing source file");
+        let mut expander = Expander::with_env(prelude::init());
         for piece in sexprs {
-            top_levels.push(
-                TopLevel::from_sexpr(&piece)
-                    .expect("Failed transforming SExpr to TopLevel AST")
-            );
+            let piece = expander.expand_top(&piece)
+                .expect("Failed expanding macros");
+            if let Some(piece) = piece {
+                top_levels.push(
+                    TopLevel::from_sexpr(&piece)
+                        .expect("Failed transforming SExpr to TopLevel AST")
+                );
+            }
         }
     }
 
@@ -106,7 +118,13 @@ fn main() {
     context.register_ffi("rand", &RAND_BIND);
     context.register_async_ffi("sleep", SLEEP_MS_BIND);
     context.register_async_ffi("yield", &YIELD_BIND);
-    let mut analyse_result = context.min_scope_analyse(&top_levels);
+    let mut analyse_result = match context.min_scope_analyse(&top_levels) {
+        Ok(result) => result,
+        Err(e) => {
+            eprint!("{}", Error::Compile(e));
+            std::process::exit(1);
+        }
+    };
 
     if args.contains(&"--only-analyse".to_string()) {
         let data_collection =
@@ -118,19 +136,7 @@ fn main() {
         return;
     }
 
-    let mut ffi_functions_in_use = analyse_result.ffi_function_in_use.iter()
-        .collect::<Vec<_>>();
-    ffi_functions_in_use.sort_by_key(|x| x.1.2);
-    let ffi_functions_in_use = ffi_functions_in_use.into_iter()
-        .map(|(_, v)| v.0)
-        .collect::<Vec<_>>();
-
-    let mut async_ffi_functions_in_use = analyse_result.async_ffi_function_in_use.iter()
-        .collect::<Vec<_>>();
-    async_ffi_functions_in_use.sort_by_key(|x| x.1.2);
-    let async_ffi_functions_in_use = async_ffi_functions_in_use.into_iter()
-        .map(|(_, v)| v.0)
-        .collect::<Vec<_>>();
+    let (ffi_functions_in_use, async_ffi_functions_in_use) = analyse_result.ffi_functions();
 
     let compile_context = CompileContext::new(
         &ffi_functions_in_use,
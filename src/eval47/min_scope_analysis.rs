@@ -8,6 +8,8 @@ use sexpr_ir::gast::Handle;
 use sexpr_ir::gast::symbol::Symbol;
 
 use crate::ast::{Call, Cond, Expr, Function, Let, Set, TopLevel};
+use crate::error::{CompilerError, SourceLocation};
+use crate::eval47::builtins::{NUMBER_BINDS, number_bind_id};
 use crate::eval47::commons::{FFIAsyncFunction, FFIFunction, Signature};
 use crate::eval47::data_map::{DataCollection, GValue};
 use crate::eval47::util::{
@@ -19,6 +21,7 @@ use crate::eval47::util::{
 };
 use crate::{guard, guard2};
 use crate::value::Value;
+use crate::value::callable::Callable;
 
 pub const BUILTIN_OPS: &'static [&'static str] = &[
     "=",
@@ -60,6 +63,38 @@ pub const BUILTIN_OPS: &'static [&'static str] = &[
     "raise"
 ];
 
+/// The least and the most arguments of the builtins that can not take any number of them.
+const BUILTIN_ARITIES: &'static [(&'static str, usize, Option<usize>)] = &[
+    ("display", 0, Some(32)),
+    ("=", 2, None),
+    ("~", 2, None),
+    ("-", 1, None),
+    ("/", 1, None),
+    ("%", 2, Some(2)),
+    (">", 2, None),
+    ("<", 2, None),
+    (">=", 2, None),
+    ("<=", 2, None),
+    ("!=", 2, Some(2)),
+    ("not", 1, Some(1)),
+    ("raise", 1, Some(1)),
+    ("string-length", 1, Some(1)),
+    ("strlen", 1, Some(1)),
+    ("string-equals?", 2, Some(2)),
+    ("strcmp", 2, Some(2)),
+    ("vector-length", 1, Some(1)),
+    ("vector-ref", 2, Some(2)),
+    ("vector-push!", 2, Some(2)),
+    ("vector-set!", 3, Some(3)),
+    ("object", 0, Some(0)),
+    ("object-get", 2, Some(2)),
+    ("object-set!", 3, Some(3)),
+    ("if", 2, Some(3)),
+    ("spawn", 1, None),
+    ("and", 2, None),
+    ("or", 2, None),
+];
+
 pub struct AnalyseContext {
     tyck_info_pool: TyckInfoPool,
     ffi_functions: HashMap<String, (FFIFunction, Signature)>,
@@ -68,11 +103,15 @@ pub struct AnalyseContext {
 
 impl AnalyseContext {
     pub fn new() -> Self {
-        AnalyseContext {
+        let mut context = AnalyseContext {
             tyck_info_pool: TyckInfoPool::new(),
             ffi_functions: HashMap::new(),
             async_ffi_functions: HashMap::new()
+        };
+        for (name, ffi) in NUMBER_BINDS {
+            context.register_ffi(*name, *ffi);
         }
+        context
     }
 
     pub fn register_ffi(&mut self, name: impl Into<String>, ffi: FFIFunction) {
@@ -85,13 +124,22 @@ impl AnalyseContext {
         self.async_ffi_functions.insert(name.into(), (ffi, signature));
     }
 
-    pub fn min_scope_analyse(&self, ast: &[TopLevel]) -> AnalyseResult {
+    /// Literals that Pr47 can not hold and builtins given the wrong number of arguments are compile
+    /// errors, the other forms it lacks still panic.
+    pub fn min_scope_analyse(&self, ast: &[TopLevel]) -> Result<AnalyseResult, Vec<CompilerError>> {
         let mut result = AnalyseResult::new();
         let display_fn = self.ffi_functions.get("display").unwrap();
         result.ffi_function_in_use.insert(
             "display".into(),
             (display_fn.0.clone(), clone_signature(&display_fn.1), 0),
         );
+        for (name, _) in NUMBER_BINDS {
+            let (ffi, signature) = self.ffi_functions.get(*name).unwrap();
+            result.ffi_function_in_use.insert(
+                name.to_string(),
+                (ffi.clone(), clone_signature(signature), number_bind_id(name)),
+            );
+        }
 
         let mut scope_chain = Some(Box::new(Scope::new(None)));
 
@@ -105,7 +153,11 @@ impl AnalyseContext {
             result.async_ffi_function_map.insert(*func_id, func_name.clone());
         }
 
-        result
+        if result.errors.is_empty() {
+            Ok(result)
+        } else {
+            Err(std::mem::take(&mut result.errors))
+        }
     }
 }
 
@@ -185,6 +237,11 @@ impl AnalyseContext {
                     "BuiltinConsFuncID".into(),
                     GValue::Int(bitcast_usize_i64(func_id)),
                 );
+            } else if name.as_ref().0.as_str() == "--pr47-builtin-append" {
+                result.global_data_map.insert(
+                    "BuiltinAppendFuncID".into(),
+                    GValue::Int(bitcast_usize_i64(func_id)),
+                );
             } else if name.as_ref().0.as_str() == "application-start" {
                 result.global_data_map.insert(
                     "ApplicationStartFuncID".into(),
@@ -333,6 +390,10 @@ impl AnalyseContext {
                 );
             },
             Value::Sym(_) => panic!("Sym value is not supported by Pr47"),
+            Value::BigInt(_) | Value::Rational(_) => result.errors.push(CompilerError::Unsupported(
+                None,
+                format!("the number {} does not fit in an int of Pr47", value)
+            )),
             Value::Pair(pair) => {
                 self.analyse_value(result, scope_chain, &pair.0);
                 self.analyse_value(result, scope_chain, &pair.1);
@@ -517,7 +578,33 @@ impl AnalyseContext {
         call: Handle<Call>
     ) {
         let mut g = guard!("analyse call @{:x}", call.as_ref() as *const _ as usize);
-        for arg in call.0.iter() {
+        // the natives that quasiquotes expand to are compiled as calls to the builtin functions,
+        // those that other special forms expand to have no counterpart in Pr47
+        if let Expr::Value(Value::Callable(Callable::Native(native))) = &call.0[0] {
+            if !matches!(native.name, "cons" | "append") {
+                result.errors.push(CompilerError::Unsupported(
+                    None,
+                    format!("the builtin `{}` is not supported by Pr47", native.name)
+                ));
+            }
+        }
+        if let Expr::Variable(var) = &call.0[0] {
+            let arity = BUILTIN_ARITIES.iter().find(|x| x.0 == var.0.as_str());
+            if let Some((name, least, most)) = arity {
+                let given = call.0.len() - 1;
+                if given < *least || most.map_or(false, |most| given > most) {
+                    let takes = if given < *least { *least } else { most.unwrap() };
+                    result.errors.push(CompilerError::InvalidExprLength(
+                        Some(SourceLocation::new(var.1.clone())),
+                        takes + 1,
+                        call.0.len(),
+                        format!("a call to `{}`", name)
+                    ));
+                }
+            }
+        }
+        let skip = matches!(&call.0[0], Expr::Value(Value::Callable(_))) as usize;
+        for arg in call.0.iter().skip(skip) {
             self.analyse_expr(result, scope_chain, arg);
         }
         g.cancel();
@@ -584,6 +671,7 @@ pub struct AnalyseResult<'a> {
     pub ffi_function_map: HashMap<usize, String>,
     pub async_ffi_function_in_use: HashMap<String, (FFIAsyncFunction, Signature, usize)>,
     pub async_ffi_function_map: HashMap<usize, String>,
+    errors: Vec<CompilerError>,
     _phantom: PhantomData<&'a ()>
 }
 
@@ -598,9 +686,23 @@ impl<'a> AnalyseResult<'a> {
             ffi_function_map: HashMap::new(),
             async_ffi_function_in_use: HashMap::new(),
             async_ffi_function_map: HashMap::new(),
+            errors: Vec::new(),
             _phantom: PhantomData
         }
     }
+
+    /// The FFI functions in use and the async ones, in the order of their ids,
+    /// which is what `CompileContext::new` takes.
+    pub fn ffi_functions(&self) -> (Vec<FFIFunction>, Vec<FFIAsyncFunction>) {
+        let mut ffi_functions = self.ffi_function_in_use.values().collect::<Vec<_>>();
+        ffi_functions.sort_by_key(|x| x.2);
+        let mut async_ffi_functions = self.async_ffi_function_in_use.values().collect::<Vec<_>>();
+        async_ffi_functions.sort_by_key(|x| x.2);
+        (
+            ffi_functions.into_iter().map(|x| x.0).collect(),
+            async_ffi_functions.into_iter().map(|x| x.0).collect()
+        )
+    }
 }
 
 struct FunctionFrame {
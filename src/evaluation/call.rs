@@ -1,7 +1,21 @@
-use crate::value::{Value, callable::{Callable, Closure, NativeFunction}, result::CResult, scope::Scope};
+use std::cell::{Cell, RefCell};
+use std::fmt::Debug;
+use std::marker::PhantomData;
 
-use super::Eval;
+use sexpr_ir::gast::Handle;
 
+use crate::value::{Value, ValueType, callable::{Callable, Closure, NativeFunction}, result::{CError, CResult}, scope::Scope};
+use crate::value::autobind::{FromValue, IntoArgs, IntoValue};
+use crate::value::context::{Context, Host, current_host, with_host};
+
+use super::{Eval, Tail, TailEval};
+
+
+thread_local! {
+    // the environments of the natives being called, the innermost last
+    static CALLERS: RefCell<Vec<Handle<Scope>>> = RefCell::new(vec![]);
+    static DEPTH: Cell<usize> = Cell::new(0);
+}
 
 
 pub trait Call {
@@ -11,15 +25,123 @@ pub trait Call {
 impl Call for Callable {
     fn call(&self, args: &[Value]) -> CResult {
         match self {
-            Callable::Closure(x) => x.call(args),
+            Callable::Closure(x) => enter(|_| x.call(args)),
             Callable::Native(x) => x.call(args),
         }
     }
 }
 
+impl Callable {
+    /// Calls with `env` as the environment of the call, which natives see in their context.
+    pub fn call_in(&self, env: &Handle<Scope>, args: &[Value]) -> CResult {
+        match self {
+            Callable::Closure(x) => enter(|_| x.call(args)),
+            Callable::Native(x) => x.call_in(env, args),
+        }
+    }
+}
+
+impl Callable {
+    /// Calls from Rust, converting the arguments and the result.
+    pub fn invoke<A: IntoArgs, R: FromValue>(&self, args: A) -> Result<R, CError> {
+        let r = self.call(&args.into_args())
+            .map_err(|e| CError::StackBacktrace(self.clone(), Handle::new(e)))?;
+        R::from_value(r)
+    }
+}
+
+/// A callable of a script kept by the host, with the types it is called with.
+///
+/// It is called with the host it was made under,
+/// so the natives it calls write where the script did.
+pub struct ScriptFn<A, R> {
+    callable: Callable,
+    host: Handle<Host>,
+    signature: PhantomData<fn(A) -> R>,
+}
+
+impl<A: IntoArgs, R: FromValue> ScriptFn<A, R> {
+    pub fn new(callable: Callable) -> Self {
+        ScriptFn::with_host(callable, current_host())
+    }
+
+    pub fn with_host(callable: Callable, host: Handle<Host>) -> Self {
+        ScriptFn { callable, host, signature: PhantomData }
+    }
+
+    pub fn call(&self, args: A) -> Result<R, CError> {
+        with_host(&self.host, || self.callable.invoke(args))
+    }
+
+    pub fn callable(&self) -> &Callable {
+        &self.callable
+    }
+}
+
+impl<A, R> Clone for ScriptFn<A, R> {
+    fn clone(&self) -> Self {
+        ScriptFn {
+            callable: self.callable.clone(),
+            host: self.host.clone(),
+            signature: PhantomData,
+        }
+    }
+}
+
+impl<A, R> Debug for ScriptFn<A, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ScriptFn").field(&self.callable).finish()
+    }
+}
+
+/// Natives may take the callbacks of scripts as `ScriptFn`.
+impl<A: IntoArgs, R: FromValue> FromValue for ScriptFn<A, R> {
+    fn from_value(v: Value) -> Result<Self, CError> {
+        match v {
+            Value::Callable(x) => Ok(ScriptFn::new(x)),
+            v => Err(CError::TypeError(ValueType::Callable, v)),
+        }
+    }
+}
+
+impl<A, R> IntoValue for ScriptFn<A, R> {
+    fn into_value(self) -> Value {
+        Value::Callable(self.callable)
+    }
+}
+
+/// Counts the calls in progress, and stops them at the depth limit of the host.
+/// A call that panics is no longer counted either.
+fn enter(f: impl FnOnce(usize) -> CResult) -> CResult {
+    struct Leave;
+
+    impl Drop for Leave {
+        fn drop(&mut self) {
+            DEPTH.with(|x| x.set(x.get() - 1));
+        }
+    }
+
+    let depth = DEPTH.with(|x| {
+        x.set(x.get() + 1);
+        x.get()
+    });
+    let _leave = Leave;
+    match current_host().max_depth {
+        Some(max) if depth > max => Err(CError::RecursionLimit(max)),
+        _ => f(depth),
+    }
+}
+
 
 impl Call for Closure {
     fn call(&self, args: &[Value]) -> CResult {
+        trampoline(self.tail_call(args)?)
+    }
+}
+
+impl Closure {
+    /// Runs the body up to its last form, which is left to the caller if it is a call.
+    pub fn tail_call(&self, args: &[Value]) -> Result<Tail, CError> {
         let Closure(f, env) = self;
         let args_dict = f.match_args(&args)?;
 
@@ -29,23 +151,55 @@ impl Call for Closure {
             Scope::from(args_dict)
         };
 
-        if f.body.is_empty() {
-            Ok(Value::Nil)
-        } else if f.body.len() == 1 {
-            f.body.first().unwrap().eval(&scope)
-        } else {
-            let body_end = f.body.last().unwrap();
-            let bodys = &f.body[..f.body.len()-1];
+        if let Some((body_end, bodys)) = f.body.split_last() {
             for i in bodys {
                 i.eval(&scope)?;
             }
-            body_end.eval(&scope)
+            body_end.tail_eval(&scope)
+        } else {
+            Ok(Tail::Return(Value::Nil))
+        }
+    }
+}
+
+/// Keeps calling closures in place until one of them returns a value,
+/// so that a chain of tail calls takes constant Rust stack.
+pub fn trampoline(mut tail: Tail) -> CResult {
+    loop {
+        tail = match tail {
+            Tail::Return(v) => return Ok(v),
+            Tail::Call(Callable::Closure(c), args) => c.tail_call(&args)
+                .map_err(|e| CError::StackBacktrace(
+                    Callable::Closure(c.clone()),
+                    Handle::new(e)))?,
+            Tail::Call(x, args) => return x.call(&args)
+                .map_err(|e| CError::StackBacktrace(x, Handle::new(e))),
         }
     }
 }
 
 impl Call for NativeFunction {
+    /// Natives called from Rust see the environment of the innermost native call.
     fn call(&self, i: &[Value]) -> CResult {
-        (self.interface)(i.to_vec())
+        let env = CALLERS.with(|x| x.borrow().last().cloned())
+            .unwrap_or_else(Scope::new);
+        self.call_in(&env, i)
+    }
+}
+
+impl NativeFunction {
+    pub fn call_in(&self, env: &Handle<Scope>, i: &[Value]) -> CResult {
+        enter(|depth| {
+            let context = Context {
+                env: env.clone(),
+                depth,
+                host: current_host(),
+            };
+            CALLERS.with(|x| x.borrow_mut().push(env.clone()));
+            let r = (self.interface)(&context, i.to_vec())
+                .map_err(|e| e.in_native(self.name));
+            CALLERS.with(|x| x.borrow_mut().pop());
+            r
+        })
     }
 }
@@ -1,15 +1,12 @@
 pub mod debug_eval;
 pub mod call;
+pub mod module;
 // pub mod partial_eval;
 // pub mod partial_call;
 
 
-use std::process::exit;
-
 use sexpr_ir::gast::Handle;
-use sexpr_ir::syntax::sexpr::file_parse;
 
-use crate::sexpr_to_ast::FromSexpr;
 use crate::value::Value;
 use crate::value::callable::{Callable, Closure};
 use crate::value::result::CError;
@@ -17,44 +14,13 @@ use crate::value::scope::SimpleScope;
 use crate::value::{result::CResult, scope::Scope};
 
 use crate::ast::*;
-use call::Call;
+use call::{Call, trampoline};
 
 
 pub trait Eval {
     fn eval(&self, env: &Handle<Scope>) -> CResult;
 }
 
-/*
-impl Eval for ModuleTop {
-    fn eval(&self, env: &Handle<Scope>) -> CResult {
-        match self {
-            ModuleTop::TopLevel(t) => t.eval(env),
-            ModuleTop::Import(x) => {
-                load_file(todo!(), env);
-                Ok(Value::Nil)
-            },
-        }
-    }
-}
- */
-
-pub fn load_file(path: &str, env: &Handle<Scope>) -> Result<(), CError> {
-    let file = file_parse(path).unwrap();
-    let file: Result<Vec<_>, _> = file
-        .iter()
-        .map(TopLevel::from_sexpr)
-        .collect();
-    if let Err(x) = file.clone() {
-        println!("Compile Error: {:?}", x);
-        exit(-1);
-    }
-    let file = file.unwrap();
-    file
-        .iter()
-        .try_for_each(|x| x.eval(&env).map(|_| ()))?;
-    Ok(())
-}
-
 impl Eval for TopLevel {
     fn eval(&self, env: &Handle<Scope>) -> CResult {
         match self {
@@ -100,6 +66,83 @@ impl Eval for Set {
 
 impl Eval for Let {
     fn eval(&self, env: &Handle<Scope>) -> CResult {
+        self.tail_eval(env)?
+            .finish()
+            .map_err(|x| CError::Positional(
+                self.pos.clone(),
+                Handle::new(x)))
+    }
+}
+
+impl Eval for Cond {
+    fn eval(&self, env: &Handle<Scope>) -> CResult {
+        self.tail_eval(env)?
+            .finish()
+            .map_err(|x| CError::Positional(
+                self.pos.clone(),
+                Handle::new(x)))
+    }
+}
+
+impl Eval for crate::ast::Call {
+    fn eval(&self, env: &Handle<Scope>) -> CResult {
+        match self.tail_eval(env)? {
+            Tail::Call(x, args) => Call::call(&x, &args)
+                .map_err(|e| CError::StackBacktrace(x, Handle::new(e))),
+            Tail::Return(v) => Ok(v),
+        }
+    }
+}
+
+impl Eval for Function {
+    fn eval(&self, env: &Handle<Scope>) -> CResult {
+        let env = env.new_level(SimpleScope::new());
+        let r = Closure(self.clone(), Some(env));
+        Ok(Value::Callable(Callable::Closure(r)))
+    }
+}
+
+
+/// The result of evaluating an expression in tail position:
+/// either a value, or a call that the caller still has to make.
+#[derive(Debug, Clone)]
+pub enum Tail {
+    Return(Value),
+    Call(Callable, Vec<Value>),
+}
+
+impl Tail {
+    pub fn finish(self) -> CResult {
+        trampoline(self)
+    }
+}
+
+pub trait TailEval {
+    fn tail_eval(&self, env: &Handle<Scope>) -> Result<Tail, CError>;
+}
+
+impl TailEval for TopLevel {
+    fn tail_eval(&self, env: &Handle<Scope>) -> Result<Tail, CError> {
+        match self {
+            TopLevel::Expr(v) => v.tail_eval(env),
+            _ => self.eval(env).map(Tail::Return),
+        }
+    }
+}
+
+impl TailEval for Expr {
+    fn tail_eval(&self, env: &Handle<Scope>) -> Result<Tail, CError> {
+        match self {
+            Expr::Let(x) => x.tail_eval(env),
+            Expr::Cond(x) => x.tail_eval(env),
+            Expr::FunctionCall(x) => x.tail_eval(env),
+            _ => self.eval(env).map(Tail::Return),
+        }
+    }
+}
+
+impl TailEval for Let {
+    fn tail_eval(&self, env: &Handle<Scope>) -> Result<Tail, CError> {
         let this_level = SimpleScope::new();
         {
             let mut record = this_level.0.write().unwrap();
@@ -112,32 +155,25 @@ impl Eval for Let {
             }
         }
         let env = env.new_level(this_level);
-        if self.body.is_empty() {
-            Ok(Value::Nil)
-        } else if self.body.len() == 1 {
-            self.body.first().unwrap().eval(&env)
-            .map_err(|x| CError::Positional(
-                self.pos.clone(),
-                Handle::new(x)))
-        } else {
-            let body_end = self.body.last().unwrap();
-            let bodys = &self.body[..self.body.len()-1];
+        if let Some((body_end, bodys)) = self.body.split_last() {
             for i in bodys {
                 i.eval(&env)
                 .map_err(|x| CError::Positional(
                     self.pos.clone(),
                     Handle::new(x)))?;
             }
-            body_end.eval(&env)
+            body_end.tail_eval(&env)
             .map_err(|x| CError::Positional(
                 self.pos.clone(),
                 Handle::new(x)))
+        } else {
+            Ok(Tail::Return(Value::Nil))
         }
     }
 }
 
-impl Eval for Cond {
-    fn eval(&self, env: &Handle<Scope>) -> CResult {
+impl TailEval for Cond {
+    fn tail_eval(&self, env: &Handle<Scope>) -> Result<Tail, CError> {
         for (cond, expr) in &self.pairs {
             let c = cond
             .eval(env)
@@ -146,7 +182,7 @@ impl Eval for Cond {
                 Handle::new(x)))?;
             if let Value::Bool(c) = c {
                 if c {
-                    return expr.eval(env)
+                    return expr.tail_eval(env)
                     .map_err(|x| CError::Positional(
                         self.pos.clone(),
                         Handle::new(x)));
@@ -159,7 +195,7 @@ impl Eval for Cond {
             }
         }
         if let Some(x) = &self.other {
-            x.eval(env)
+            x.tail_eval(env)
         } else {
             // return error "conds is not matching"
             Err(CError::Positional(
@@ -169,27 +205,20 @@ impl Eval for Cond {
     }
 }
 
-impl Eval for crate::ast::Call {
-    fn eval(&self, env: &Handle<Scope>) -> CResult {
+impl TailEval for crate::ast::Call {
+    fn tail_eval(&self, env: &Handle<Scope>) -> Result<Tail, CError> {
         let r: Result<Vec<_>, _> = self.0.iter().map(|x| x.eval(env)).collect();
         let r = r?;
         debug_assert_ne!(r.len(), 0);
         let mut iter = r.into_iter();
         let value = iter.next().unwrap();
-        if let Value::Callable(x) = value {
-            let args: Vec<_> = iter.collect();
-            Call::call(&x, &args)
-            .map_err(|e| CError::StackBacktrace(x, Handle::new(e)))
-        } else {
-            Err(CError::ValueIsNotCallable(value))
+        match value {
+            // natives are called right away, as only here is the environment of the call known
+            Value::Callable(Callable::Native(x)) => x.call_in(env, &iter.collect::<Vec<_>>())
+                .map(Tail::Return)
+                .map_err(|e| CError::StackBacktrace(Callable::Native(x), Handle::new(e))),
+            Value::Callable(x) => Ok(Tail::Call(x, iter.collect())),
+            _ => Err(CError::ValueIsNotCallable(value)),
         }
     }
 }
-
-impl Eval for Function {
-    fn eval(&self, env: &Handle<Scope>) -> CResult {
-        let env = env.new_level(SimpleScope::new());
-        let r = Closure(self.clone(), Some(env));
-        Ok(Value::Callable(Callable::Closure(r)))
-    }
-}
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use sexpr_ir::gast::{GAst, Handle, symbol::Symbol};
+
+use crate::ast::{Export, Import, ImportSet, ModuleTop, TopLevel};
+use crate::error::{Error, file_parse};
+use crate::sexpr_to_ast::FromSexpr;
+use crate::sexpr_to_ast::macros::Expander;
+use crate::value::Value;
+use crate::value::context::{Capability, current_host};
+use crate::value::result::{CError, CResult};
+use crate::value::scope::{Scope, SimpleScope};
+
+use super::Eval;
+
+
+pub const MODULE_EXTENSION: &str = "scm";
+pub const MODULE_PATH_VAR: &str = "C0I_PATH";
+pub const QUALIFIER: &str = "/";
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: Handle<Symbol>,
+    pub scope: Handle<Scope>,
+    pub exports: SimpleScope,
+}
+
+#[derive(Debug)]
+pub struct ModuleLoader {
+    base: Handle<Scope>,
+    search_paths: RwLock<Vec<PathBuf>>,
+    loaded: RwLock<HashMap<PathBuf, Handle<Module>>>,
+    loading: RwLock<Vec<PathBuf>>,
+}
+
+impl ModuleLoader {
+    /// Every module is evaluated in a fresh level on top of `base`,
+    /// which usually holds the builtins.
+    pub fn new(base: Handle<Scope>) -> Handle<ModuleLoader> {
+        let mut search_paths = vec![PathBuf::from(".")];
+        if let Some(paths) = env::var_os(MODULE_PATH_VAR) {
+            search_paths.extend(env::split_paths(&paths));
+        }
+        let r = ModuleLoader {
+            base,
+            search_paths: RwLock::new(search_paths),
+            loaded: RwLock::new(HashMap::new()),
+            loading: RwLock::new(Vec::new()),
+        };
+        Handle::new(r)
+    }
+
+    pub fn add_search_path<P: AsRef<Path>>(&self, path: P) {
+        self.search_paths.write().unwrap().push(path.as_ref().to_path_buf());
+    }
+
+    /// The directory of the importing file is searched first, then the search paths in order.
+    pub fn resolve(&self, name: &str, from: Option<&Path>) -> Option<PathBuf> {
+        let file_name = format!("{}.{}", name, MODULE_EXTENSION);
+        let search_paths = self.search_paths.read().unwrap();
+        from.into_iter()
+            .map(Path::to_path_buf)
+            .chain(search_paths.iter().cloned())
+            .map(|dir| dir.join(&file_name))
+            .find(|path| path.is_file())
+            .map(|path| path.canonicalize().unwrap_or(path))
+    }
+
+    /// Modules are read with the capabilities of the current host.
+    pub fn import(&self, name: &Handle<Symbol>, from: Option<&Path>) -> Result<Handle<Module>, CError> {
+        current_host().permit(Capability::ReadFiles)?;
+        let path = self.resolve(&name.0, from)
+            .ok_or_else(|| CError::ModuleNotFound(name.clone()))?;
+
+        if let Some(module) = self.loaded.read().unwrap().get(&path) {
+            return Ok(module.clone());
+        }
+        if self.loading.read().unwrap().contains(&path) {
+            return Err(CError::CircularImport(name.clone()));
+        }
+
+        self.loading.write().unwrap().push(path.clone());
+        let scope = self.base.new_level(SimpleScope::new());
+        let r = self.load_file(&path, &scope);
+        self.loading.write().unwrap().retain(|x| *x != path);
+        let declarations = match r {
+            Ok(declarations) => declarations,
+            Err(Error::Runtime(e)) => return Err(e),
+            Err(e) => return Err(CError::InvalidModule(name.clone(), Handle::new(e.to_string()))),
+        };
+
+        // a module without any `export` form exports all of its own top-level definitions,
+        // but not what it imported
+        let record = scope.this_level.0.read().unwrap();
+        let exports = if let Some(exports) = declarations.exports {
+            let exports: Result<HashMap<_, _>, _> = exports
+                .into_iter()
+                .map(|k| record.get(&k)
+                    .map(|v| (k.clone(), v.clone()))
+                    .ok_or(CError::SymbolNotFound(k)))
+                .collect();
+            SimpleScope::from(exports?)
+        } else {
+            let exports = record.iter()
+                .filter(|(k, _)| !declarations.imported.contains(*k))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<HashMap<_, _>>();
+            SimpleScope::from(exports)
+        };
+        drop(record);
+
+        let module = Handle::new(Module { name: name.clone(), scope, exports });
+        self.loaded.write().unwrap().insert(path, module.clone());
+        Ok(module)
+    }
+
+    /// Returns the `export` and `import` forms of the file.
+    pub fn load_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        env: &Handle<Scope>
+    ) -> Result<Declarations, Error> {
+        let path = path.as_ref();
+        let file = file_parse(&path.to_string_lossy()).map_err(Error::Parse)?;
+        let expander = Mutex::new(Expander::with_env(env.clone()));
+        let mut declarations = Declarations::default();
+        self.eval_forms(&file, env, path.parent(), &expander, &mut declarations)?;
+        Ok(declarations)
+    }
+
+    /// Returns the value of the last form, the `export` and `import` forms are added to `declarations`.
+    /// `expander` is only locked while a form is expanded.
+    pub fn eval_forms(
+        &self,
+        forms: &[GAst],
+        env: &Handle<Scope>,
+        from: Option<&Path>,
+        expander: &Mutex<Expander>,
+        declarations: &mut Declarations
+    ) -> Result<Value, Error> {
+        // forms are expanded one at a time, so macros can use what the forms before them defined
+        let mut r = Value::Nil;
+        for top in forms.iter() {
+            let top = expander.lock().unwrap().expand_top(top)?;
+            let top = match top {
+                Some(top) => ModuleTop::from_sexpr(&top)?,
+                None => continue,
+            };
+            match top {
+                ModuleTop::Export(Export(names)) => {
+                    declarations.exports.get_or_insert_with(Vec::new).extend(names.iter().cloned());
+                },
+                ModuleTop::Import(Import(sets)) => {
+                    for set in sets.iter() {
+                        declarations.imported.extend(self.import_set(set, env, from)?);
+                    }
+                    r = Value::Nil;
+                },
+                ModuleTop::TopLevel(t) => {
+                    // a definition replaces what was imported under its name
+                    match &t {
+                        TopLevel::Function(x) => { declarations.imported.remove(x.name.as_ref().unwrap()); },
+                        TopLevel::Bind(k, _) => { declarations.imported.remove(k); },
+                        TopLevel::Expr(_) => {},
+                    }
+                    r = t.eval(env)?;
+                },
+            }
+        }
+        Ok(r)
+    }
+
+    pub fn eval_module_top(
+        &self,
+        top: &ModuleTop,
+        env: &Handle<Scope>,
+        from: Option<&Path>
+    ) -> CResult {
+        match top {
+            ModuleTop::TopLevel(t) => t.eval(env),
+            ModuleTop::Import(Import(sets)) => {
+                for set in sets {
+                    self.import_set(set, env, from)?;
+                }
+                Ok(Value::Nil)
+            },
+            // exports only mean something while loading a module file
+            ModuleTop::Export(_) => Ok(Value::Nil),
+        }
+    }
+
+    /// Binds the names selected by `set` into `env`, each also under its qualified name `module/name`,
+    /// and returns every name it bound.
+    pub fn import_set(
+        &self,
+        set: &ImportSet,
+        env: &Handle<Scope>,
+        from: Option<&Path>
+    ) -> Result<Vec<Handle<Symbol>>, CError> {
+        let module = self.import(set.module_name(), from)?;
+        let binds = select_imports(set, &module)?;
+        let mut record = env.this_level.0.write().unwrap();
+        let mut names = Vec::with_capacity(binds.len() * 2);
+        for (k, (original, v)) in binds {
+            let name = format!("{}{}{}", module.name.0, QUALIFIER, original.0);
+            let qualified = renamed(&name, &module.name);
+            record.insert(qualified.clone(), v.clone());
+            record.insert(k.clone(), v);
+            names.push(qualified);
+            names.push(k);
+        }
+        Ok(names)
+    }
+}
+
+/// What the forms of a module file declare besides their definitions.
+#[derive(Debug, Default)]
+pub struct Declarations {
+    /// the names in `export` forms, `None` if there are none
+    pub exports: Option<Vec<Handle<Symbol>>>,
+    /// the names bound by `import` forms and not defined again since
+    pub imported: HashSet<Handle<Symbol>>,
+}
+
+/// The names selected by `set`, with the names they have in the module and their values.
+fn select_imports(
+    set: &ImportSet,
+    module: &Module
+) -> Result<HashMap<Handle<Symbol>, (Handle<Symbol>, Value)>, CError> {
+    match set {
+        ImportSet::Module(_) => Ok(module.exports.0.read().unwrap().iter()
+            .map(|(k, v)| (k.clone(), (k.clone(), v.clone())))
+            .collect()),
+        ImportSet::Only(set, names) => {
+            let binds = select_imports(set, module)?;
+            names.iter()
+                .map(|k| binds.get(k)
+                    .map(|v| (k.clone(), v.clone()))
+                    .ok_or_else(|| CError::SymbolNotFound(k.clone())))
+                .collect()
+        },
+        ImportSet::Except(set, names) => {
+            let mut binds = select_imports(set, module)?;
+            for k in names {
+                binds.remove(k).ok_or_else(|| CError::SymbolNotFound(k.clone()))?;
+            }
+            Ok(binds)
+        },
+        ImportSet::Prefix(set, prefix) => {
+            let binds = select_imports(set, module)?;
+            Ok(binds.into_iter()
+                .map(|(k, v)| (renamed(&format!("{}{}", prefix.0, k.0), prefix), v))
+                .collect())
+        },
+        ImportSet::Rename(set, pairs) => {
+            let mut binds = select_imports(set, module)?;
+            for (from, to) in pairs {
+                let v = binds.remove(from).ok_or_else(|| CError::SymbolNotFound(from.clone()))?;
+                binds.insert(to.clone(), v);
+            }
+            Ok(binds)
+        },
+    }
+}
+
+fn renamed(name: &str, at: &Handle<Symbol>) -> Handle<Symbol> {
+    Handle::new(Symbol(Handle::new(name.to_string()), at.1.clone()))
+}
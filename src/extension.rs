@@ -0,0 +1,212 @@
+use std::collections::BTreeSet;
+use std::ffi::c_void;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use libloading::Library;
+use sexpr_ir::gast::{Handle, symbol::Symbol};
+
+pub use c0i_abi as abi;
+use abi::{C0iFunction, C0iRegistrar, C0iStr, C0iValue, C0I_ABI_VERSION, C0I_OK, tag};
+
+use crate::value::Value;
+use crate::value::callable::{Callable, NativeFunction};
+use crate::value::result::{CError, CResult};
+
+
+/// The functions of a native extension, see `c0i_abi` for the ABI.
+///
+/// The library is unloaded once none of its functions is referenced anymore.
+pub struct Extension {
+    pub name: Handle<String>,
+    pub functions: Vec<NativeFunction>,
+}
+
+impl Extension {
+    /// The functions with their names, ready to be set in a scope.
+    pub fn bindings(&self) -> impl Iterator<Item = (Handle<Symbol>, Value)> + '_ {
+        self.functions.iter().map(|f| (
+            Handle::new(Symbol::new(f.name)),
+            Value::Callable(Callable::Native(f.clone())),
+        ))
+    }
+}
+
+pub fn load_extension(path: &Path) -> Result<Extension, CError> {
+    let fail = |e: String| CError::ExtensionError(Handle::new(path.display().to_string()), Handle::new(e));
+
+    let library = unsafe { Library::new(path) }.map_err(|e| fail(e.to_string()))?;
+    let version = unsafe {
+        let f = library.get::<abi::AbiVersionFn>(abi::C0I_ABI_VERSION_SYMBOL)
+            .map_err(|e| fail(e.to_string()))?;
+        f()
+    };
+    if version != C0I_ABI_VERSION {
+        return Err(fail(format!("built for ABI version {}, expected {}", version, C0I_ABI_VERSION)));
+    }
+
+    let mut registration = Registration {
+        name: None,
+        functions: vec![],
+        error: None,
+    };
+    let registrar = C0iRegistrar {
+        abi_version: C0I_ABI_VERSION,
+        host: &mut registration as *mut Registration as *mut c_void,
+        set_name,
+        register,
+    };
+    let status = unsafe {
+        let init = library.get::<abi::InitFn>(abi::C0I_INIT_SYMBOL)
+            .map_err(|e| fail(e.to_string()))?;
+        init(&registrar)
+    };
+    if let Some(e) = registration.error {
+        return Err(fail(e));
+    }
+    if status != C0I_OK {
+        return Err(fail(format!("initialization failed with status {}", status)));
+    }
+
+    let name = registration.name
+        .unwrap_or_else(|| path.file_stem().map_or(String::new(), |x| x.to_string_lossy().to_string()));
+    let module = intern(&name);
+    let library = Arc::new(library);
+    let functions = registration.functions.into_iter()
+        .map(|(name, f)| {
+            let function = ExtFunction { f, _library: library.clone() };
+            NativeFunction {
+                name: intern(&name),
+                from_module: module,
+                is_pure: function.f.is_pure != 0,
+                interface: Arc::new(move |_, args| function.call(args)),
+            }
+        })
+        .collect();
+    Ok(Extension { name: Handle::new(name), functions })
+}
+
+
+static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+/// Natives are named by static strings, each name is only leaked the first time an extension gives it.
+fn intern(name: &str) -> &'static str {
+    let mut names = NAMES.lock().unwrap();
+    match names.get(name) {
+        Some(x) => x,
+        None => {
+            let x: &'static str = Box::leak(name.to_string().into_boxed_str());
+            names.insert(x);
+            x
+        },
+    }
+}
+
+
+struct Registration {
+    name: Option<String>,
+    functions: Vec<(String, C0iFunction)>,
+    error: Option<String>,
+}
+
+unsafe extern "C" fn set_name(host: *mut c_void, name: C0iStr) {
+    let registration = &mut *(host as *mut Registration);
+    match name.as_str() {
+        Some(name) => registration.name = Some(name.to_string()),
+        None => registration.error = Some("the name of the extension is not valid UTF-8".to_string()),
+    }
+}
+
+unsafe extern "C" fn register(host: *mut c_void, function: *const C0iFunction) -> i32 {
+    let registration = &mut *(host as *mut Registration);
+    let function = &*function;
+    let name = match function.name.as_str() {
+        Some(x) => x.to_string(),
+        None => return abi::C0I_ERROR,
+    };
+    if function.min_args > function.max_args || registration.functions.iter().any(|(x, _)| *x == name) {
+        return abi::C0I_ERROR;
+    }
+    registration.functions.push((name, C0iFunction {
+        name: C0iStr { ptr: std::ptr::null(), len: 0 },
+        ..*function
+    }));
+    C0I_OK
+}
+
+
+struct ExtFunction {
+    f: C0iFunction,
+    // keeps the code of `f` loaded
+    _library: Arc<Library>,
+}
+
+// the extension is responsible for `data` being usable from any thread
+unsafe impl Send for ExtFunction {}
+unsafe impl Sync for ExtFunction {}
+
+impl ExtFunction {
+    fn call(&self, args: Vec<Value>) -> CResult {
+        let len = args.len();
+        if len < self.f.min_args as usize {
+            return Err(CError::ArgsNotMatching(self.f.min_args as usize, len));
+        }
+        if self.f.max_args != u32::MAX && len > self.f.max_args as usize {
+            return Err(CError::ArgsNotMatching(self.f.max_args as usize, len));
+        }
+        let c_args: Vec<C0iValue> = args.iter().map(to_c_value).collect();
+        let mut out = C0iValue::nil();
+        let status = unsafe { (self.f.call)(self.f.data, c_args.as_ptr(), c_args.len(), &mut out) };
+        let r = from_c_value(&out, &args);
+        if let Some(release) = self.f.release {
+            unsafe { release(self.f.data, &mut out) };
+        }
+        let r = r?;
+        if status == C0I_OK {
+            Ok(r)
+        } else {
+            Err(CError::RuntimeError(Some(r), vec![]))
+        }
+    }
+}
+
+fn to_c_value(v: &Value) -> C0iValue {
+    match v {
+        Value::Nil => C0iValue::nil(),
+        Value::Bool(x) => C0iValue::boolean(*x),
+        Value::Char(x) => C0iValue::character(*x),
+        Value::Uint(x) => C0iValue::uint(*x),
+        Value::Int(x) => C0iValue::int(*x),
+        Value::Float(x) => C0iValue::float(*x),
+        Value::Str(x) => C0iValue::string(C0iStr::new(x)),
+        Value::Sym(x) => C0iValue::symbol(C0iStr::new(&x.0)),
+        v => C0iValue {
+            tag: tag::OPAQUE,
+            payload: abi::C0iPayload { opaque: v as *const Value as *const c_void },
+        },
+    }
+}
+
+/// An opaque value must be one of the arguments.
+fn from_c_value(v: &C0iValue, args: &[Value]) -> CResult {
+    let invalid = || CError::RuntimeError(
+        Some(Value::Str(Handle::new("an extension returned an invalid value".to_string()))),
+        vec![]
+    );
+    let string = || unsafe { v.as_str() }.ok_or_else(invalid);
+    Ok(match v.tag {
+        tag::NIL => Value::Nil,
+        tag::BOOL => Value::Bool(v.as_bool().unwrap()),
+        tag::CHAR => Value::Char(v.as_char().ok_or_else(invalid)?),
+        tag::UINT => Value::Uint(v.as_uint().unwrap()),
+        tag::INT => Value::Int(v.as_int().unwrap()),
+        tag::FLOAT => Value::Float(v.as_float().unwrap()),
+        tag::STR => Value::Str(Handle::new(string()?.to_string())),
+        tag::SYM => Value::Sym(Handle::new(Symbol::new(string()?))),
+        tag::OPAQUE => {
+            let ptr = unsafe { v.payload.opaque } as *const Value;
+            args.iter().find(|x| std::ptr::eq(*x, ptr)).cloned().ok_or_else(invalid)?
+        },
+        _ => return Err(invalid()),
+    })
+}
@@ -0,0 +1,175 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use sexpr_ir::gast::{Handle, symbol::Symbol};
+
+use crate::error::{Error, file_parse, parse_source};
+use crate::evaluation::call::ScriptFn;
+use crate::evaluation::module::{Declarations, ModuleLoader};
+use crate::extension::load_extension;
+use crate::prelude;
+use crate::sexpr_to_ast::macros::Expander;
+use crate::value::Value;
+use crate::value::autobind::{FromValue, IntoArgs, IntoValue, NativeDef, RustCallable, bind_closure};
+use crate::value::callable::{Callable, NativeClosure, NativeFunction};
+use crate::value::context::{Context, Host, with_host};
+use crate::value::result::{CError, CResult};
+use crate::value::scope::{Scope, SimpleScope};
+
+
+/// A c0i interpreter for host applications.
+///
+/// Globals live in a level of their own on top of the builtins,
+/// which are shared with the modules the scripts import.
+pub struct Interpreter {
+    builtins: Handle<Scope>,
+    env: Handle<Scope>,
+    loader: Handle<ModuleLoader>,
+    expander: Handle<Mutex<Expander>>,
+    host: Handle<Host>,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::with_builtins(prelude::init())
+    }
+
+    pub fn without_builtins() -> Interpreter {
+        Interpreter::with_builtins(Scope::new())
+    }
+
+    fn with_builtins(builtins: Handle<Scope>) -> Interpreter {
+        let loader = ModuleLoader::new(builtins.clone());
+        let env = builtins.new_level(SimpleScope::new());
+        let expander = Handle::new(Mutex::new(Expander::with_env(env.clone())));
+        let host = Handle::new(Host {
+            expander: Some(expander.clone()),
+            loader: Some(loader.clone()),
+            ..Host::default()
+        });
+        Interpreter { builtins, env, loader, expander, host }
+    }
+
+    /// Sets the output streams, user data, depth limit and capabilities that natives see.
+    /// The macros and the modules of the interpreter are kept unless `host` brings its own.
+    pub fn set_host(&mut self, mut host: Host) {
+        host.expander.get_or_insert_with(|| self.expander.clone());
+        host.loader.get_or_insert_with(|| self.loader.clone());
+        self.host = Handle::new(host);
+    }
+
+    pub fn host(&self) -> &Handle<Host> {
+        &self.host
+    }
+
+    /// Evaluates every form of `source` and returns the value of the last one.
+    pub fn eval_str(&mut self, source: &str) -> Result<Value, Error> {
+        let forms = parse_source(source, "<eval>").map_err(Error::Parse)?;
+        let host = self.host.clone();
+        with_host(&host, || self.loader.eval_forms(&forms, &self.env, None, &self.expander, &mut Declarations::default()))
+    }
+
+    /// Evaluates a file like `eval_str`, its imports are searched from its directory first.
+    pub fn eval_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Value, Error> {
+        let path = path.as_ref();
+        let forms = file_parse(&path.to_string_lossy()).map_err(Error::Parse)?;
+        let host = self.host.clone();
+        with_host(&host, || self.loader.eval_forms(&forms, &self.env, path.parent(), &self.expander, &mut Declarations::default()))
+    }
+
+    /// Adds a directory where imported modules are searched, after those already there.
+    pub fn add_search_path<P: AsRef<Path>>(&self, path: P) {
+        self.loader.add_search_path(path);
+    }
+
+    pub fn define_global<V: IntoValue>(&self, name: &str, value: V) {
+        self.env.set(&Handle::new(Symbol::new(name)), &value.into_value());
+    }
+
+    /// Defines a native which may capture state of the host.
+    pub fn define_native<F>(&self, name: &'static str, f: F)
+        where F: Fn(Vec<Value>) -> CResult + Send + Sync + 'static
+    {
+        self.define_closure(name, Arc::new(move |_, args| f(args)));
+    }
+
+    /// Defines a native which also receives the context of the interpreter.
+    pub fn define_context_native<F>(&self, name: &'static str, f: F)
+        where F: Fn(&Context, Vec<Value>) -> CResult + Send + Sync + 'static
+    {
+        self.define_closure(name, Arc::new(f));
+    }
+
+    /// Defines a native from a closure over Rust values, its arguments are converted as by `autobind`.
+    pub fn define_fn<F, TS, R, E>(&self, name: &'static str, f: F)
+        where F: RustCallable<TS, R, E> + Send + Sync + 'static,
+              TS: 'static,
+              R: 'static,
+              E: 'static
+    {
+        self.define_closure(name, bind_closure(f));
+    }
+
+    /// Defines a native generated by `#[c0i_fn]`.
+    pub fn register(&self, native: NativeDef) {
+        self.define_global(native.name, native.to_callable());
+    }
+
+    /// Loads a native extension and defines its functions as globals, see `c0i_abi`.
+    pub fn load_extension<P: AsRef<Path>>(&self, path: P) -> Result<Handle<String>, Error> {
+        let extension = load_extension(path.as_ref())?;
+        for (name, value) in extension.bindings() {
+            self.env.set(&name, &value);
+        }
+        Ok(extension.name)
+    }
+
+    fn define_closure(&self, name: &'static str, interface: NativeClosure) {
+        let native = NativeFunction {
+            name,
+            from_module: "<host>",
+            is_pure: false,
+            interface,
+        };
+        self.define_global(name, Callable::Native(native));
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.env.find_from_raw(name)
+    }
+
+    pub fn call_function(&self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        let symbol = Handle::new(Symbol::new(name));
+        match self.env.find(&symbol) {
+            Some(Value::Callable(f)) => with_host(&self.host, || f.call_in(&self.env, args))
+                .map_err(|e| CError::StackBacktrace(f, Handle::new(e)).into()),
+            Some(v) => Err(CError::ValueIsNotCallable(v).into()),
+            None => Err(CError::SymbolNotFound(symbol).into()),
+        }
+    }
+
+    /// A callable global as a function of Rust.
+    pub fn get_function<A: IntoArgs, R: FromValue>(&self, name: &str) -> Result<ScriptFn<A, R>, Error> {
+        let symbol = Handle::new(Symbol::new(name));
+        match self.env.find(&symbol) {
+            Some(Value::Callable(f)) => Ok(ScriptFn::with_host(f, self.host.clone())),
+            Some(v) => Err(CError::ValueIsNotCallable(v).into()),
+            None => Err(CError::SymbolNotFound(symbol).into()),
+        }
+    }
+
+    /// The level holding the builtins, natives registered here are also seen by modules.
+    pub fn builtins(&self) -> &Handle<Scope> {
+        &self.builtins
+    }
+
+    pub fn scope(&self) -> &Handle<Scope> {
+        &self.env
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
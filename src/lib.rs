@@ -1,13 +1,22 @@
 extern crate core;
+// lets the code generated by `c0i_macros` name this crate from inside it
+extern crate self as c0i;
 
 pub mod value;
 pub mod ast;
+pub mod error;
 
 #[cfg(feature = "c0i")] pub mod evaluation;
 #[cfg(feature = "c0i")] pub mod sexpr_to_ast;
 #[cfg(feature = "c0i")] pub mod analysis;
-#[cfg(feature = "c0i")] pub mod error;
 #[cfg(feature = "c0i")] pub mod prelude;
+#[cfg(feature = "c0i")] pub mod interpreter;
+#[cfg(feature = "c0i")] pub mod extension;
+
+pub use error::Error;
+#[cfg(feature = "c0i")] pub use interpreter::Interpreter;
+
+pub use c0i_macros::{c0i_fn, FromValue, IntoValue};
 
 #[cfg(feature = "c047")]
 pub mod eval47;
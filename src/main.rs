@@ -4,23 +4,26 @@ mod evaluation;
 mod sexpr_to_ast;
 mod value;
 mod prelude;
+mod extension;
 
-use std::collections::HashMap;
 pub use c0i::value::autobind;
 
 use std::io::{stdin, stdout, Write};
 use std::process::exit;
+use std::sync::Mutex;
 
 use prelude::init;
-use evaluation::{Eval, load_file};
+use evaluation::module::ModuleLoader;
 use sexpr_ir::gast::Handle;
-use sexpr_ir::syntax::sexpr::repl_parse;
 use sexpr_to_ast::FromSexpr;
+use sexpr_to_ast::macros::Expander;
 
-use ast::TopLevel;
-use value::scope::Scope;
+use ast::ModuleTop;
+use error::{Error, parse_source};
+use value::context::{Host, with_host};
+use value::scope::{Scope, SimpleScope};
 
-fn start_repl(env: &Handle<Scope>) -> ! {
+fn start_repl(env: &Handle<Scope>, loader: &ModuleLoader, expander: &Mutex<Expander>) -> ! {
     loop {
         print!(">>> ");
         stdout().flush().unwrap();
@@ -30,23 +33,40 @@ fn start_repl(env: &Handle<Scope>) -> ! {
             continue;
         }
         // parse
-        let r = repl_parse(&buf);
-        if r.is_err() {
-            println!("syntax error: {}", r.unwrap_err());
-            continue;
-        }
-        let r = r.unwrap();
-        // into ast
-        match TopLevel::from_sexpr(&r) {
+        let forms = match parse_source(&buf, "<repl>") {
             Err(e) => {
-                println!("error: {:?}", e);
-                exit(-1)
+                println!("syntax error: {}", e);
+                continue;
             },
-            Ok(v) => {
-                let r = v.eval(&env);
-                match r {
-                    Err(e) => println!("Error:\n{}", e),
-                    Ok(v) => println!("{}", v),
+            Ok(forms) => forms,
+        };
+        for r in forms.iter() {
+            // expand macros
+            let r = expander.lock().unwrap().expand_top(r);
+            let r = match r {
+                Err(e) => {
+                    for e in e.iter() {
+                        print!("error: {}", e);
+                    }
+                    exit(-1)
+                },
+                Ok(None) => continue,
+                Ok(Some(r)) => r,
+            };
+            // into ast
+            match ModuleTop::from_sexpr(&r) {
+                Err(e) => {
+                    for e in e.iter() {
+                        print!("error: {}", e);
+                    }
+                    exit(-1)
+                },
+                Ok(v) => {
+                    let r = loader.eval_module_top(&v, &env, None);
+                    match r {
+                        Err(e) => println!("Error:\n{}", e),
+                        Ok(v) => println!("{}", v),
+                    }
                 }
             }
         }
@@ -54,43 +74,60 @@ fn start_repl(env: &Handle<Scope>) -> ! {
 }
 
 use std::env;
-use libloading::{Library, Symbol};
+use std::path::Path;
 
-type NativeModuleLoadFn = fn(
-    &mut HashMap<Handle<crate::value::Symbol>, crate::value::Value>
-);
+use extension::load_extension;
 
 fn main() {
     let mut args = env::args();
     args.next();
     let args = args.collect::<Vec<_>>();
 
-    let env = if args.iter().any(|x| x == "--no-builtins") {
+    let builtins = if args.iter().any(|x| x == "--no-builtins") {
         Scope::new()
     } else {
         init()
     };
+    let loader = ModuleLoader::new(builtins.clone());
+    let env = builtins.new_level(SimpleScope::new());
+    let expander = Handle::new(Mutex::new(Expander::with_env(env.clone())));
+    let host = Handle::new(Host {
+        expander: Some(expander.clone()),
+        loader: Some(loader.clone()),
+        ..Host::default()
+    });
+    with_host(&host, || run(&args, &builtins, &env, &loader, &expander))
+}
 
-    let mut loaded_libraries = Vec::new();
+fn run(
+    args: &[String],
+    builtins: &Handle<Scope>,
+    env: &Handle<Scope>,
+    loader: &ModuleLoader,
+    expander: &Mutex<Expander>
+) -> ! {
     for arg in args {
         if arg.starts_with("--") {
             continue;
         }
 
-        if arg.ends_with(".so") || arg.ends_with(".dll") {
-            unsafe {
-                let lib = Library::new(arg).unwrap();
-                let sym: Symbol<NativeModuleLoadFn> = lib.get(b"load_module").unwrap();
-                (sym)(&mut env.this_level.0.write().unwrap());
-
-                loaded_libraries.push(lib);
+        if arg.ends_with(".so") || arg.ends_with(".dll") || arg.ends_with(".dylib") {
+            match load_extension(Path::new(arg)) {
+                Ok(extension) => for (name, value) in extension.bindings() {
+                    builtins.set(&name, &value);
+                },
+                Err(e) => println!("error loading extension {}:\n{}", arg, e),
             }
         } else {
-            let r = load_file(&arg, &env);
-            if let Err(e) = r {
-                println!("error loading file {}: {:?}", arg, e);
+            match loader.load_file(arg, env) {
+                Err(Error::Runtime(e)) => println!("error loading file {}:\n{}", arg, e),
+                Err(e) => {
+                    print!("{}", e);
+                    exit(-1);
+                },
+                Ok(_) => {},
             }
         }
     }
-    start_repl(&env);
+    start_repl(env, loader, expander)
 }
@@ -18,9 +18,13 @@ impl_native_is_type!(native_is_bool, is_bool);
 impl_native_is_type!(native_is_int, is_int);
 impl_native_is_type!(native_is_uint, is_uint);
 impl_native_is_type!(native_is_float, is_float);
+impl_native_is_type!(native_is_bigint, is_bigint);
+impl_native_is_type!(native_is_rational, is_rational);
+impl_native_is_type!(native_is_exact, is_exact);
 impl_native_is_type!(native_is_str, is_str);
 impl_native_is_type!(native_is_sym, is_sym);
 impl_native_is_type!(native_is_pair, is_pair);
 impl_native_is_type!(native_is_dict, is_dict);
 impl_native_is_type!(native_is_vec, is_vec);
 impl_native_is_type!(native_is_callable, is_callable);
+impl_native_is_type!(native_is_error, is_error);
@@ -1,16 +1,139 @@
-use crate::value::Value;
+use std::cell::RefCell;
+
+use sexpr_ir::gast::Handle;
+
+use crate::evaluation::call::Call;
+use crate::value::{ErrorObject, Value, ValueType};
+use crate::value::callable::Callable;
 use crate::value::result::{CResult, CError};
 
+thread_local! {
+    // the handlers installed by `with-exception-handler`,
+    // `None` marks a `try-call`, which catches everything raised under it
+    static HANDLERS: RefCell<Vec<Option<Callable>>> = RefCell::new(vec![]);
+}
+
 pub(crate) fn native_error(args: Vec<Value>) -> CResult {
-    let e = args.get(0).map_or_else(
-        || CError::RuntimeError(None),
-        |v| CError::RuntimeError(Some(v.clone())));
-    Err(e)
+    let mut iter = args.into_iter();
+    Err(CError::RuntimeError(iter.next(), iter.collect()))
 }
 
 pub(crate) fn native_unreachable(args: Vec<Value>) -> CResult {
-    let e = args.get(0).map_or_else(
-        || CError::RuntimeError(None),
-        |v| CError::Unreachable(Some(v.clone())));
-    Err(e)
+    Err(CError::Unreachable(args.get(0).cloned()))
+}
+
+pub(crate) fn native_raise(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    match args.get(0).unwrap() {
+        // raising a caught error again keeps its backtrace
+        Value::Error(e) => Err((*e.0).clone()),
+        v => Err(CError::Raised(v.clone())),
+    }
+}
+
+pub(crate) fn native_raise_continuable(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let value = args.get(0).unwrap();
+    let handler = HANDLERS.with(|x| x.borrow_mut().pop());
+    let handler = match handler {
+        Some(Some(handler)) => handler,
+        Some(None) => {
+            HANDLERS.with(|x| x.borrow_mut().push(None));
+            return Err(CError::Raised(value.clone()));
+        },
+        None => return Err(CError::Raised(value.clone())),
+    };
+    // the handler runs with the outer handlers installed
+    let r = handler.call(&[value.clone()])
+        .map_err(|e| CError::StackBacktrace(handler.clone(), Handle::new(e)));
+    HANDLERS.with(|x| x.borrow_mut().push(Some(handler)));
+    r
+}
+
+/// As there are no continuations, the value of a handler which was not
+/// called by `raise-continuable` becomes the value of `with-exception-handler`.
+pub(crate) fn with_exception_handler(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let handler = callable_argument(&args, 0)?;
+    let thunk = callable_argument(&args, 1)?;
+    handle(Some(handler.clone()), thunk, handler)
+}
+
+pub(crate) fn try_call(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let thunk = callable_argument(&args, 0)?;
+    let handler = callable_argument(&args, 1)?;
+    handle(None, thunk, handler)
+}
+
+pub(crate) fn dynamic_wind(args: Vec<Value>) -> CResult {
+    if args.len() != 3 {
+        return Err(CError::ArgsNotMatching(3, args.len()));
+    }
+    let before = callable_argument(&args, 0)?;
+    let thunk = callable_argument(&args, 1)?;
+    let after = callable_argument(&args, 2)?;
+    call_thunk(before)?;
+    let r = call_thunk(thunk);
+    call_thunk(after)?;
+    r
+}
+
+pub(crate) fn error_message(args: Vec<Value>) -> CResult {
+    let e = error_argument(&args)?;
+    Ok(Value::Str(Handle::new(e.message())))
+}
+
+pub(crate) fn error_irritants(args: Vec<Value>) -> CResult {
+    let e = error_argument(&args)?;
+    Ok(Value::from(e.irritants().as_slice()))
+}
+
+fn handle(installed: Option<Callable>, thunk: &Callable, handler: &Callable) -> CResult {
+    HANDLERS.with(|x| x.borrow_mut().push(installed));
+    let r = call_thunk(thunk);
+    HANDLERS.with(|x| x.borrow_mut().pop());
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => handler.call(&[condition(e)])
+            .map_err(|e| CError::StackBacktrace(handler.clone(), Handle::new(e))),
+    }
+}
+
+/// What a handler receives: the payload of `raise`, or the error itself.
+fn condition(e: CError) -> Value {
+    match e.root() {
+        CError::Raised(v) => v.clone(),
+        _ => Value::Error(ErrorObject(Handle::new(e))),
+    }
+}
+
+fn call_thunk(thunk: &Callable) -> CResult {
+    thunk.call(&[])
+        .map_err(|e| CError::StackBacktrace(thunk.clone(), Handle::new(e)))
+}
+
+fn callable_argument(args: &[Value], index: usize) -> Result<&Callable, CError> {
+    match args.get(index).unwrap() {
+        Value::Callable(x) => Ok(x),
+        v => Err(CError::ArgTypeError(index, ValueType::Callable, v.clone())),
+    }
+}
+
+fn error_argument(args: &[Value]) -> Result<&CError, CError> {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    match args.get(0).unwrap() {
+        Value::Error(e) => Ok(&e.0),
+        v => Err(CError::ArgTypeError(0, ValueType::Error, v.clone())),
+    }
 }
@@ -0,0 +1,116 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use sexpr_ir::gast::{GAst, Handle};
+
+use crate::error::{Error, file_parse};
+use crate::evaluation::module::{Declarations, ModuleLoader};
+use crate::extension::load_extension;
+use crate::sexpr_to_ast::macros::Expander;
+use crate::sexpr_to_ast::quote::{sexpr_from_value, value_from_sexpr};
+use crate::value::{Value, ValueType};
+use crate::value::context::{Capability, Context};
+use crate::value::result::{CResult, CError};
+
+
+/// Evaluates a datum in the environment `eval` is called from.
+pub(crate) fn native_eval(ctx: &Context, args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let value = args.get(0).unwrap();
+    let form = sexpr_from_value(value)
+        .ok_or_else(|| CError::CompileError(Handle::new(format!("{} can not be evaluated", value))))?;
+    eval_forms(ctx, &[form], None)
+}
+
+/// `(macroexpand form)` is the datum `form` with its macro uses expanded.
+pub(crate) fn native_macroexpand(ctx: &Context, args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let value = args.get(0).unwrap();
+    let form = sexpr_from_value(value)
+        .ok_or_else(|| CError::CompileError(Handle::new(format!("{} can not be expanded", value))))?;
+    let r = expander(ctx).lock().unwrap().macroexpand(&form)
+        .map_err(|e| CError::CompileError(Handle::new(Error::Compile(e).to_string())))?;
+    Ok(value_from_sexpr(&r))
+}
+
+/// `(apply f a ... args)` calls `f` with `a ...` followed by the items of the list `args`.
+pub(crate) fn native_apply(ctx: &Context, args: Vec<Value>) -> CResult {
+    if args.len() < 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let callable = if let Value::Callable(x) = args.get(0).unwrap() {
+        x
+    } else {
+        return Err(CError::ArgTypeError(0, ValueType::Callable, args.get(0).unwrap().clone()));
+    };
+    let last = args.len() - 1;
+    let mut r = args[1..last].to_vec();
+    let mut this = args.get(last).unwrap();
+    while let Value::Pair(pair) = this {
+        r.push(pair.0.clone());
+        this = &pair.1;
+    }
+    if !this.is_nil() {
+        return Err(CError::ArgTypeError(last, ValueType::List, args.get(last).unwrap().clone()));
+    }
+    callable.call_in(&ctx.env, &r)
+        .map_err(|e| CError::StackBacktrace(callable.clone(), Handle::new(e)))
+}
+
+/// Evaluates the forms of a file in the environment `load` is called from.
+pub(crate) fn native_load(ctx: &Context, args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let path = if let Value::Str(x) = args.get(0).unwrap() {
+        x
+    } else {
+        return Err(CError::ArgTypeError(0, ValueType::Str, args.get(0).unwrap().clone()));
+    };
+    ctx.permit(Capability::ReadFiles)?;
+    let forms = file_parse(path).map_err(|e| CError::CompileError(Handle::new(e)))?;
+    eval_forms(ctx, &forms, Path::new(path.as_str()).parent())
+}
+
+/// Loads a native extension and defines its functions in the environment `load-extension` is called from,
+/// it returns the name of the extension.
+pub(crate) fn native_load_extension(ctx: &Context, args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let path = if let Value::Str(x) = args.get(0).unwrap() {
+        x
+    } else {
+        return Err(CError::ArgTypeError(0, ValueType::Str, args.get(0).unwrap().clone()));
+    };
+    ctx.permit(Capability::LoadExtensions)?;
+    let extension = load_extension(Path::new(path.as_str()))?;
+    for (name, value) in extension.bindings() {
+        ctx.env.set(&name, &value);
+    }
+    Ok(Value::Str(extension.name))
+}
+
+fn eval_forms(ctx: &Context, forms: &[GAst], from: Option<&Path>) -> CResult {
+    let loader = ctx.host.loader.clone()
+        .unwrap_or_else(|| ModuleLoader::new(ctx.env.root()));
+    let expander = expander(ctx);
+    loader.eval_forms(forms, &ctx.env, from, &expander, &mut Declarations::default())
+        .map_err(|e| match e {
+            Error::Runtime(e) => e,
+            e => CError::CompileError(Handle::new(e.to_string())),
+        })
+}
+
+/// The expander of the host, or a new one when there is none
+/// or it is busy expanding the use of a procedural macro that calls back.
+fn expander(ctx: &Context) -> Handle<Mutex<Expander>> {
+    match ctx.host.expander.as_ref() {
+        Some(x) if x.try_lock().is_ok() => x.clone(),
+        _ => Handle::new(Mutex::new(Expander::with_env(ctx.env.clone()))),
+    }
+}
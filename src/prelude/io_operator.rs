@@ -1,65 +1,75 @@
 use std::fs::File;
-use std::io::{stdout, Write, stdin, Read};
+use std::io::{Write, stdin, Read};
 
 use sexpr_ir::gast::Handle;
 
-use crate::value::Value;
+use crate::value::{Value, ValueType};
+use crate::value::context::{Capability, Context};
 use crate::value::result::{CResult, CError};
 
-pub(crate) fn read_stdin(args: Vec<Value>) -> CResult {
-    if args.len() != 0 {
-        return Err(CError::ArgsNotMatching(1, args.len()));
+pub(crate) fn read_stdin(ctx: &Context, args: Vec<Value>) -> CResult {
+    if !args.is_empty() {
+        return Err(CError::ArgsNotMatching(0, args.len()));
     }
+    ctx.permit(Capability::ReadStdin)?;
     let mut r = String::new();
     stdin().read_to_string(&mut r).unwrap();
     Ok(Value::Str(Handle::new(r)))
 }
 
-pub(crate) fn read_line(args: Vec<Value>) -> CResult {
-    if args.len() != 0 {
-        return Err(CError::ArgsNotMatching(1, args.len()));
+pub(crate) fn read_line(ctx: &Context, args: Vec<Value>) -> CResult {
+    if !args.is_empty() {
+        return Err(CError::ArgsNotMatching(0, args.len()));
     }
+    ctx.permit(Capability::ReadStdin)?;
     let mut r = String::new();
     stdin().read_line(&mut r).unwrap();
     Ok(Value::Str(Handle::new(r)))
 }
 
-pub(crate) fn display(args: Vec<Value>) -> CResult {
+pub(crate) fn display(ctx: &Context, args: Vec<Value>) -> CResult {
     if args.len() != 1 {
         return Err(CError::ArgsNotMatching(1, args.len()));
     }
     let r = args.get(0).unwrap().to_string();
-    print!("{}", r);
-    stdout().flush().unwrap();
+    let mut out = ctx.stdout();
+    write!(out, "{}", r)
+        .and_then(|_| out.flush())
+        .map_err(write_error)?;
     Ok(Value::Nil)
 }
 
-pub(crate) fn displayln(args: Vec<Value>) -> CResult {
+pub(crate) fn displayln(ctx: &Context, args: Vec<Value>) -> CResult {
     if args.len() != 1 {
         return Err(CError::ArgsNotMatching(1, args.len()));
     }
     let r = args.get(0).unwrap().to_string();
-    println!("{}", r);
+    writeln!(ctx.stdout(), "{}", r).map_err(write_error)?;
     Ok(Value::Nil)
 }
 
-pub(crate) fn file_to_string(args: Vec<Value>) -> CResult {
+fn write_error(e: std::io::Error) -> CError {
+    CError::RuntimeError(Some(Value::Str(Handle::new(format!("write error: {}", e)))), vec![])
+}
+
+pub(crate) fn file_to_string(ctx: &Context, args: Vec<Value>) -> CResult {
     if args.len() != 1 {
         return Err(CError::ArgsNotMatching(1, args.len()));
     }
+    ctx.permit(Capability::ReadFiles)?;
     let v = if let Value::Str(v) = args.get(0).unwrap() {
         v
     } else {
-        return CResult::Err(CError::TypeError((), args.get(0).unwrap().clone()));
+        return CResult::Err(CError::ArgTypeError(0, ValueType::Str, args.get(0).unwrap().clone()));
     };
     let mut f = if let Ok(f) = File::open(v.as_str()) {
         f
     } else {
-        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file not found: {}", v))))));
+        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file not found: {}", v)))), vec![]));
     };
     let mut r = String::new();
     if let Err(e) = f.read_to_string(&mut r) {
-        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file read error: {}", e))))));
+        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file read error: {}", e)))), vec![]));
     }
     Ok(Value::Str(Handle::new(r)))
 }
@@ -77,37 +87,38 @@ pub(crate) fn file_to_lines(args: Vec<Value>) -> CResult {
     let mut f = if let Ok(f) = File::open(v.as_str()) {
         f
     } else {
-        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file not found: {}", v))))));
+        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file not found: {}", v)))), vec![]));
     };
     let mut r = String::new();
     if let Err(e) = f.read_to_string(&mut r) {
-        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file read error: {}", e))))));
+        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file read error: {}", e)))), vec![]));
     }
     Ok(Value::Str(Handle::new(r)))
 }
  */
 
-pub(crate) fn write_file(args: Vec<Value>) -> CResult {
+pub(crate) fn write_file(ctx: &Context, args: Vec<Value>) -> CResult {
     if args.len() != 2 {
-        return Err(CError::ArgsNotMatching(1, args.len()));
+        return Err(CError::ArgsNotMatching(2, args.len()));
     }
+    ctx.permit(Capability::WriteFiles)?;
     let v = if let Value::Str(v) = args.get(0).unwrap() {
         v
     } else {
-        return CResult::Err(CError::TypeError((), args.get(0).unwrap().clone()));
+        return CResult::Err(CError::ArgTypeError(0, ValueType::Str, args.get(0).unwrap().clone()));
     };
-    let s = if let Value::Str(v) = args.get(0).unwrap() {
+    let s = if let Value::Str(v) = args.get(1).unwrap() {
         v
     } else {
-        return CResult::Err(CError::TypeError((), args.get(0).unwrap().clone()));
+        return CResult::Err(CError::ArgTypeError(1, ValueType::Str, args.get(1).unwrap().clone()));
     };
     let mut f = if let Ok(f) = File::create(v.as_str()) {
         f
     } else {
-        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file not found: {}", v))))));
+        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file not found: {}", v)))), vec![]));
     };
     if let Err(e) = f.write_all(s.as_bytes()) {
-        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file write error: {}", e))))));
+        return CResult::Err(CError::RuntimeError(Some(Value::Str(Handle::new(format!("file write error: {}", e)))), vec![]));
     }
     Ok(Value::Nil)
 }
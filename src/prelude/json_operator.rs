@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use sexpr_ir::gast::Handle;
+
+use crate::value::{Value, ValueType};
+use crate::value::result::{CResult, CError};
+
+
+/// `(json->value str)` reads objects as dicts, arrays as vectors, `null` as nil,
+/// and numbers as uints, ints if they are negative, or floats if they have a fraction or an exponent.
+pub(crate) fn json_to_value(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let s = if let Value::Str(x) = args.get(0).unwrap() {
+        x
+    } else {
+        return Err(CError::ArgTypeError(0, ValueType::Str, args.get(0).unwrap().clone()));
+    };
+    serde_json::from_str(s).map_err(json_error)
+}
+
+/// `(value->json v)` writes dicts as objects, lists and vectors as arrays,
+/// symbols and chars as strings, and nil as `null`.
+/// The keys of dicts must be strings or symbols, and not both of the same name.
+/// Callables, errors, improper lists and vectors or dicts that hold themselves can not be written,
+/// neither can the infinite floats and NaN, which JSON has not.
+pub(crate) fn value_to_json(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    check_floats(args.get(0).unwrap(), &mut HashSet::new())?;
+    serde_json::to_string(args.get(0).unwrap())
+        .map(|x| Value::Str(Handle::new(x)))
+        .map_err(json_error)
+}
+
+/// `(json-pretty v)` is `value->json` with indentation.
+pub(crate) fn json_pretty(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    check_floats(args.get(0).unwrap(), &mut HashSet::new())?;
+    serde_json::to_string_pretty(args.get(0).unwrap())
+        .map(|x| Value::Str(Handle::new(x)))
+        .map_err(json_error)
+}
+
+/// serde_json writes `null` for them.
+/// `visiting` holds the vectors and dicts `v` is in, meeting one again means that it holds itself.
+fn check_floats(v: &Value, visiting: &mut HashSet<usize>) -> Result<(), CError> {
+    match v {
+        Value::Float(x) if !x.is_finite() =>
+            Err(CError::ConversionError(Handle::new(format!("{} can not be written as JSON", x)))),
+        Value::Pair(_) => {
+            let mut this = v;
+            while let Value::Pair(x) = this {
+                check_floats(&x.0, visiting)?;
+                this = &x.1;
+            }
+            check_floats(this, visiting)
+        },
+        Value::Vec(x) => {
+            let key = Arc::as_ptr(&x.0) as usize;
+            enter(key, visiting)?;
+            x.0.read().unwrap().iter().try_for_each(|x| check_floats(x, visiting))?;
+            visiting.remove(&key);
+            Ok(())
+        },
+        Value::Dict(x) => {
+            let key = Arc::as_ptr(&x.0) as usize;
+            enter(key, visiting)?;
+            x.0.read().unwrap().values().try_for_each(|x| check_floats(x, visiting))?;
+            visiting.remove(&key);
+            Ok(())
+        },
+        _ => Ok(()),
+    }
+}
+
+fn enter(key: usize, visiting: &mut HashSet<usize>) -> Result<(), CError> {
+    if visiting.insert(key) {
+        Ok(())
+    } else {
+        Err(CError::ConversionError(Handle::new("a vector or dict that holds itself can not be written as JSON".to_string())))
+    }
+}
+
+fn json_error(e: serde_json::Error) -> CError {
+    if e.line() == 0 {
+        return CError::ConversionError(Handle::new(e.to_string()));
+    }
+    // the message without the position, which is kept apart
+    let message = e.to_string();
+    let suffix = format!(" at line {} column {}", e.line(), e.column());
+    let message = message.strip_suffix(&suffix).unwrap_or(&message);
+    CError::JsonError(Handle::new(message.to_string()), e.line(), e.column())
+}
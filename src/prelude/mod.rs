@@ -7,7 +7,11 @@ pub mod native_math_operator;
 pub mod native_bool_operator;
 pub mod native_string_operator;
 pub mod native_dict_operator;
+pub mod native_list_operator;
+pub mod native_vector_operator;
 pub mod io_operator;
+pub mod eval_operator;
+#[cfg(feature = "json")] pub mod json_operator;
 
 use sexpr_ir::gast::Handle;
 
@@ -20,9 +24,13 @@ use native_math_operator::*;
 use native_string_operator::*;
 use native_bool_operator::*;
 use native_dict_operator::*;
+use native_list_operator::*;
+use native_vector_operator::*;
 use io_operator::*;
+use eval_operator::*;
+#[cfg(feature = "json")] use json_operator::*;
 
-use crate::value::autobind::scope_register_module;
+use crate::value::autobind::{scope_register_context_module, scope_register_module};
 use crate::value::scope::Scope;
 
 pub fn init() -> Handle<Scope> {
@@ -34,18 +42,59 @@ pub fn init() -> Handle<Scope> {
         scope_register_module(&mut rcd, "<builtin>", &[
             ("error", native_error),
             ("unreachable", native_unreachable),
+            ("raise", native_raise),
+            ("raise-continuable", native_raise_continuable),
+            ("with-exception-handler", with_exception_handler),
+            ("try-call", try_call),
+            ("dynamic-wind", dynamic_wind),
+            ("error-message", error_message),
+            ("error-irritants", error_irritants),
             ("literal", literal),
             ("read", read),
             ("car", car),
             ("cdr", cdr),
             ("cons", cons),
+            ("list", list),
+            ("length", length),
+            ("append", append),
+            ("reverse", reverse),
+            ("list-ref", list_ref),
+            ("list-tail", list_tail),
+            ("map", map),
+            ("for-each", for_each),
+            ("filter", filter),
+            ("fold-left", fold_left),
+            ("fold-right", fold_right),
+            ("reduce", reduce),
+            ("assoc", assoc),
+            ("assq", assq),
+            ("member", member),
+            ("last", last),
+            ("iota", iota),
+            ("list->vector", list_to_vector),
+            ("vector->list", vector_to_list),
             ("make-vector", vector),
+            ("vec-ref", vector_ref),
+            ("vec-length", vector_length),
+            ("vec-set!", vector_set),
+            ("set-vec!", vector_set),
+            ("vec-push!", vector_push),
+            ("vec-pop!", vector_pop),
+            ("vec-map", vector_map),
+            ("vec-for-each", vector_for_each),
+            ("vec-filter", vector_filter),
             ("vec-reduce", vector_reduce),
-            ("set-vec!", set_vector),
+            ("vec-slice", vector_slice),
+            ("vec-fill!", vector_fill),
+            ("vec-copy", vector_copy),
+            ("vec-sort!", vector_sort),
+            ("vec-binary-search", vector_binary_search),
             ("id", id),
             ("ignore", ignore),
             ("eq?", eq),
             ("ne?", ne),
+            ("eqv?", eqv),
+            ("equal?", equal),
             ("lt?", lt),
             ("gt?", gt),
             ("le?", le),
@@ -56,12 +105,16 @@ pub fn init() -> Handle<Scope> {
             ("int?", native_is_int),
             ("uint?", native_is_uint),
             ("float?", native_is_float),
+            ("bigint?", native_is_bigint),
+            ("rational?", native_is_rational),
+            ("exact?", native_is_exact),
             ("str?", native_is_str),
             ("sym?", native_is_sym),
             ("pair?", native_is_pair),
             ("dict?", native_is_dict),
             ("vec?", native_is_vec),
             ("callable?", native_is_callable),
+            ("error?", native_is_error),
             ("+i", native_add_int),
             ("+u", native_add_uint),
             ("+f", native_add_float),
@@ -74,19 +127,59 @@ pub fn init() -> Handle<Scope> {
             ("/i", native_div_int),
             ("/u", native_div_uint),
             ("/f", native_div_float),
+            ("+", native_add),
+            ("-", native_sub),
+            ("*", native_mul),
+            ("/", native_div),
+            ("%", native_rem),
+            ("exact->inexact", exact_to_inexact),
+            ("inexact->exact", inexact_to_exact),
+            ("string->number", string_to_number),
+            ("=", num_eq),
+            ("!=", num_ne),
+            ("<", num_lt),
+            (">", num_gt),
+            ("<=", num_le),
+            (">=", num_ge),
             ("+s", native_add_str),
             ("->string", to_str),
             ("not", native_bool_not),
             ("and", native_bool_and),
             ("or", native_bool_or),
             ("make-dict", make_dict),
+            ("dict-ref", dict_ref),
+            ("dict-set!", dict_set),
+            ("dict-remove!", dict_remove),
+            ("dict-has?", dict_has),
+            ("dict-count", dict_count),
+            ("dict-keys", dict_keys),
+            ("dict-values", dict_values),
+            ("dict->alist", dict_to_alist),
+            ("alist->dict", alist_to_dict),
+            ("dict-update!", dict_update),
+            ("dict-merge", dict_merge),
+            ("dict-for-each", dict_for_each),
+            ("dict-fold", dict_fold),
+        ]);
+        scope_register_context_module(&mut rcd, "<builtin>", &[
+            ("eval", native_eval),
+            ("macroexpand", native_macroexpand),
+            ("apply", native_apply),
+            ("load", native_load),
+            ("load-extension", native_load_extension),
             ("read-stdin", read_stdin),
             ("read-line", read_line),
             ("display", display),
             ("displayln", displayln),
             ("file->string", file_to_string),
-            ("write-file", write_file)
-        ])
+            ("write-file", write_file),
+        ]);
+        #[cfg(feature = "json")]
+        scope_register_module(&mut rcd, "<builtin>", &[
+            ("json->value", json_to_value),
+            ("value->json", value_to_json),
+            ("json-pretty", json_pretty),
+        ]);
     }
     record
 }
@@ -1,10 +1,201 @@
-use crate::value::{Dict, Value};
+use std::collections::HashMap;
+
+use sexpr_ir::gast::Handle;
+
+use crate::value::{Dict, Key, Pair, Value, ValueType};
 use crate::value::result::{CResult, CError};
 
+use super::native_list_operator::{call_back, callable_arg};
+
 
 pub(crate) fn make_dict(args: Vec<Value>) -> CResult {
-    if args.len() != 0 {
-        return Err(CError::ArgsNotMatching(1, args.len()));
+    if !args.is_empty() {
+        return Err(CError::ArgsNotMatching(0, args.len()));
     }
     Ok(Value::Dict(Dict::default()))
 }
+
+fn dict_arg(args: &[Value], index: usize) -> Result<&Dict, CError> {
+    match args.get(index).unwrap() {
+        Value::Dict(x) => Ok(x),
+        v => Err(CError::ArgTypeError(index, ValueType::Dict, v.clone())),
+    }
+}
+
+fn key_arg(args: &[Value], index: usize) -> Result<Key, CError> {
+    let v = args.get(index).unwrap();
+    Key::new(v.clone()).map_err(|_| CError::ArgTypeError(index, ValueType::Key, v.clone()))
+}
+
+/// `(dict-ref d key)` fails if `key` is missing, `(dict-ref d key default)` gives `default`.
+pub(crate) fn dict_ref(args: Vec<Value>) -> CResult {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let dict = dict_arg(&args, 0)?;
+    let key = key_arg(&args, 1)?;
+    let r = dict.0.read().unwrap().get(&key).cloned();
+    match (r, args.get(2)) {
+        (Some(r), _) => Ok(r),
+        (None, Some(default)) => Ok(default.clone()),
+        (None, None) => Err(CError::KeyNotFound(key.into_value())),
+    }
+}
+
+pub(crate) fn dict_set(args: Vec<Value>) -> CResult {
+    if args.len() != 3 {
+        return Err(CError::ArgsNotMatching(3, args.len()));
+    }
+    let dict = dict_arg(&args, 0)?;
+    let key = key_arg(&args, 1)?;
+    dict.0.write().unwrap().insert(key, args.get(2).unwrap().clone());
+    Ok(Value::Nil)
+}
+
+/// `(dict-remove! d key)` gives the value that was removed, or nil.
+pub(crate) fn dict_remove(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let dict = dict_arg(&args, 0)?;
+    let key = key_arg(&args, 1)?;
+    let r = dict.0.write().unwrap().remove(&key);
+    Ok(r.unwrap_or(Value::Nil))
+}
+
+pub(crate) fn dict_has(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let dict = dict_arg(&args, 0)?;
+    let key = key_arg(&args, 1)?;
+    let r = dict.0.read().unwrap().contains_key(&key);
+    Ok(Value::Bool(r))
+}
+
+pub(crate) fn dict_count(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let r = dict_arg(&args, 0)?.0.read().unwrap().len();
+    Ok(Value::Uint(r as u64))
+}
+
+/// The keys in no particular order, as a list.
+pub(crate) fn dict_keys(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let keys: Vec<_> = dict_arg(&args, 0)?.0.read().unwrap().keys()
+        .map(|k| k.clone().into_value())
+        .collect();
+    Ok(Value::from(&keys[..]))
+}
+
+/// The values in the order of `dict-keys`, as a list.
+pub(crate) fn dict_values(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let values: Vec<_> = dict_arg(&args, 0)?.0.read().unwrap().values().cloned().collect();
+    Ok(Value::from(&values[..]))
+}
+
+/// `(dict->alist d)` is a list of the pairs `(key . value)`.
+pub(crate) fn dict_to_alist(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let entries: Vec<_> = dict_arg(&args, 0)?.0.read().unwrap().iter()
+        .map(|(k, v)| Value::Pair(Handle::new(Pair(k.clone().into_value(), v.clone()))))
+        .collect();
+    Ok(Value::from(&entries[..]))
+}
+
+/// `(alist->dict alist)`, a later pair of the same key wins.
+pub(crate) fn alist_to_dict(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let not_alist = || CError::ArgTypeError(0, ValueType::List, args.get(0).unwrap().clone());
+    let mut record = HashMap::new();
+    let mut this = args.get(0).unwrap();
+    while let Value::Pair(pair) = this {
+        let entry = if let Value::Pair(x) = &pair.0 {
+            x
+        } else {
+            return Err(not_alist());
+        };
+        let key = Key::new(entry.0.clone()).map_err(|_| not_alist())?;
+        record.insert(key, entry.1.clone());
+        this = &pair.1;
+    }
+    if !this.is_nil() {
+        return Err(not_alist());
+    }
+    Ok(Value::from(record))
+}
+
+/// `(dict-update! d key f)` sets `key` to `(f value)`, failing if `key` is missing,
+/// `(dict-update! d key f default)` calls `(f default)` then.
+/// `f` may use the dict, which is not locked during the call.
+pub(crate) fn dict_update(args: Vec<Value>) -> CResult {
+    if args.len() != 3 && args.len() != 4 {
+        return Err(CError::ArgsNotMatching(3, args.len()));
+    }
+    let dict = dict_arg(&args, 0)?;
+    let key = key_arg(&args, 1)?;
+    let callable = callable_arg(&args, 2)?;
+    let value = dict.0.read().unwrap().get(&key).cloned();
+    let value = match (value, args.get(3)) {
+        (Some(r), _) => r,
+        (None, Some(default)) => default.clone(),
+        (None, None) => return Err(CError::KeyNotFound(key.into_value())),
+    };
+    let r = call_back(callable, &[value])?;
+    dict.0.write().unwrap().insert(key, r.clone());
+    Ok(r)
+}
+
+/// `(dict-merge d ...)` is a new dict of the entries of all of them,
+/// the last dict with a key gives its value.
+pub(crate) fn dict_merge(args: Vec<Value>) -> CResult {
+    let mut record = HashMap::new();
+    for i in 0..args.len() {
+        let dict = dict_arg(&args, i)?;
+        let entries = dict.0.read().unwrap();
+        record.extend(entries.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    Ok(Value::from(record))
+}
+
+/// `(dict-for-each d f)` calls `(f key value)` on each entry, in no particular order.
+pub(crate) fn dict_for_each(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let dict = dict_arg(&args, 0)?;
+    let callable = callable_arg(&args, 1)?;
+    // a copy, so that `f` may change the dict
+    let entries: Vec<_> = dict.0.read().unwrap().iter()
+        .map(|(k, v)| (k.clone().into_value(), v.clone()))
+        .collect();
+    for (k, v) in entries {
+        call_back(callable, &[k, v])?;
+    }
+    Ok(Value::Nil)
+}
+
+/// `(dict-fold d f init)` is `(f key value acc)` over the entries, in no particular order.
+pub(crate) fn dict_fold(args: Vec<Value>) -> CResult {
+    if args.len() != 3 {
+        return Err(CError::ArgsNotMatching(3, args.len()));
+    }
+    let dict = dict_arg(&args, 0)?;
+    let callable = callable_arg(&args, 1)?;
+    let entries: Vec<_> = dict.0.read().unwrap().iter()
+        .map(|(k, v)| (k.clone().into_value(), v.clone()))
+        .collect();
+    entries.into_iter()
+        .try_fold(args.get(2).unwrap().clone(), |acc, (k, v)| call_back(callable, &[k, v, acc]))
+}
@@ -1,6 +1,10 @@
+use std::cmp::Ordering;
+
 use crate::value::Value;
 use crate::value::result::{CResult, CError};
 
+use super::native_math_operator::Number;
+
 
 pub(crate) fn eq(args: Vec<Value>) -> CResult {
     if args.len() != 2 {
@@ -8,7 +12,7 @@ pub(crate) fn eq(args: Vec<Value>) -> CResult {
     }
     let a = args.get(0).unwrap();
     let b = args.get(1).unwrap();
-    Ok(Value::Bool(a == b))
+    Ok(Value::Bool(a.is_eq(b)))
 }
 
 pub(crate) fn ne(args: Vec<Value>) -> CResult {
@@ -17,7 +21,25 @@ pub(crate) fn ne(args: Vec<Value>) -> CResult {
     }
     let a = args.get(0).unwrap();
     let b = args.get(1).unwrap();
-    Ok(Value::Bool(a != b))
+    Ok(Value::Bool(!a.is_eq(b)))
+}
+
+pub(crate) fn eqv(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let a = args.get(0).unwrap();
+    let b = args.get(1).unwrap();
+    Ok(Value::Bool(a.is_eqv(b)))
+}
+
+pub(crate) fn equal(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let a = args.get(0).unwrap();
+    let b = args.get(1).unwrap();
+    Ok(Value::Bool(a.is_equal(b)))
 }
 
 pub(crate) fn lt(args: Vec<Value>) -> CResult {
@@ -55,3 +77,50 @@ pub(crate) fn ge(args: Vec<Value>) -> CResult {
     let b = args.get(1).unwrap();
     Ok(Value::Bool(a >= b))
 }
+
+
+macro_rules! impl_native_num_compare {
+    ($fn_name:ident, $pred:expr) => {
+        pub(crate) fn $fn_name(args: Vec<Value>) -> CResult {
+            if args.len() < 2 {
+                return Err(CError::ArgsNotMatching(2, args.len()));
+            }
+            let numbers = args.iter().enumerate()
+                .map(|(i, x)| Number::from_value(i, x))
+                .collect::<Result<Vec<_>, _>>()?;
+            let r = numbers.windows(2).all(|x| x[0].compare(&x[1]).map_or(false, $pred));
+            Ok(Value::Bool(r))
+        }
+    }
+}
+
+// `(< a b c)` is `(and (< a b) (< b c))`, NaN compares false
+impl_native_num_compare! { num_lt, |x| x == Ordering::Less }
+impl_native_num_compare! { num_gt, |x| x == Ordering::Greater }
+impl_native_num_compare! { num_le, |x| x != Ordering::Greater }
+impl_native_num_compare! { num_ge, |x| x != Ordering::Less }
+
+/// Numbers are compared by their value, whatever their type,
+/// the other values as `eqv?` does, as eval47 compares any two values.
+fn num_same(a: &Value, b: &Value) -> bool {
+    match (Number::from_value(0, a), Number::from_value(1, b)) {
+        (Ok(a), Ok(b)) => a.compare(&b) == Some(Ordering::Equal),
+        _ => a.is_eqv(b),
+    }
+}
+
+/// `(= a b c)` is `(and (= a b) (= b c))`.
+pub(crate) fn num_eq(args: Vec<Value>) -> CResult {
+    if args.len() < 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    Ok(Value::Bool(args.windows(2).all(|x| num_same(&x[0], &x[1]))))
+}
+
+/// `(!= a b)` is `(not (= a b))`.
+pub(crate) fn num_ne(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    Ok(Value::Bool(!num_same(args.get(0).unwrap(), args.get(1).unwrap())))
+}
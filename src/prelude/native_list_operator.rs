@@ -0,0 +1,292 @@
+use sexpr_ir::gast::Handle;
+
+use crate::evaluation::call::Call;
+use crate::value::{Pair, Value, ValueType};
+use crate::value::callable::Callable;
+use crate::value::result::{CResult, CError};
+
+use super::native_math_operator::{Number, native_add, native_mul};
+
+
+pub(crate) fn list_arg(args: &[Value], index: usize) -> Result<Vec<Value>, CError> {
+    let v = args.get(index).unwrap();
+    v.list_items().ok_or_else(|| CError::ArgTypeError(index, ValueType::List, v.clone()))
+}
+
+pub(crate) fn callable_arg(args: &[Value], index: usize) -> Result<&Callable, CError> {
+    match args.get(index).unwrap() {
+        Value::Callable(x) => Ok(x),
+        v => Err(CError::ArgTypeError(index, ValueType::Callable, v.clone())),
+    }
+}
+
+/// Counts are uints, or ints that are not negative, which the generic arithmetic gives
+/// whenever an int takes part.
+pub(crate) fn count_arg(args: &[Value], index: usize) -> Result<usize, CError> {
+    match args.get(index).unwrap() {
+        Value::Uint(x) => Ok(*x as usize),
+        Value::Int(x) if *x >= 0 => Ok(*x as usize),
+        v => Err(CError::ArgTypeError(index, ValueType::Uint, v.clone())),
+    }
+}
+
+/// An index is a count, a negative int is out of the range of the sequence of length `len()`.
+pub(crate) fn index_arg(args: &[Value], index: usize, len: impl FnOnce() -> usize) -> Result<usize, CError> {
+    match args.get(index).unwrap() {
+        Value::Int(x) if *x < 0 => Err(CError::IndexOutOfRange(*x as i128, len())),
+        _ => count_arg(args, index),
+    }
+}
+
+/// Errors of a callback must not be taken for errors in the arguments of the builtin.
+pub(crate) fn call_back(callable: &Callable, args: &[Value]) -> CResult {
+    callable.call(args)
+        .map_err(|e| CError::StackBacktrace(callable.clone(), Handle::new(e)))
+}
+
+pub(crate) fn predicate(callable: &Callable, args: &[Value]) -> Result<bool, CError> {
+    match call_back(callable, args)? {
+        Value::Bool(x) => Ok(x),
+        v => Err(CError::TypeError(ValueType::Bool, v)),
+    }
+}
+
+/// The lists from `args[start..]`, and the length of the shortest.
+fn lists_arg(args: &[Value], start: usize) -> Result<(Vec<Vec<Value>>, usize), CError> {
+    let lists = (start..args.len())
+        .map(|i| list_arg(args, i))
+        .collect::<Result<Vec<_>, _>>()?;
+    let len = lists.iter().map(Vec::len).min().unwrap_or(0);
+    Ok((lists, len))
+}
+
+pub(crate) fn column(lists: &[Vec<Value>], index: usize) -> Vec<Value> {
+    lists.iter().map(|x| x[index].clone()).collect()
+}
+
+pub(crate) fn list(args: Vec<Value>) -> CResult {
+    Ok(Value::from(&args[..]))
+}
+
+pub(crate) fn length(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    Ok(Value::Uint(list_arg(&args, 0)?.len() as u64))
+}
+
+/// `(append l ... tail)`, `tail` may be any value, it ends the result.
+pub(crate) fn append(args: Vec<Value>) -> CResult {
+    let (tail, lists) = match args.split_last() {
+        Some(x) => x,
+        None => return Ok(Value::Nil),
+    };
+    let mut items = vec![];
+    for i in 0..lists.len() {
+        items.extend(list_arg(&args, i)?);
+    }
+    Ok(items.into_iter().rev().fold(tail.clone(), |prev, i| Value::Pair(Handle::new(Pair(i, prev)))))
+}
+
+pub(crate) fn reverse(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let mut items = list_arg(&args, 0)?;
+    items.reverse();
+    Ok(Value::from(&items[..]))
+}
+
+pub(crate) fn list_ref(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let items = list_arg(&args, 0)?;
+    let index = index_arg(&args, 1, || items.len())?;
+    items.get(index).cloned().ok_or(CError::IndexOutOfRange(index as i128, items.len()))
+}
+
+/// `(list-tail l k)` is `l` without its first `k` items.
+pub(crate) fn list_tail(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let index = index_arg(&args, 1, || args.get(0).unwrap().list_items().map_or(0, |x| x.len()))?;
+    let mut this = args.get(0).unwrap();
+    for i in 0..index {
+        match this {
+            Value::Pair(pair) => this = &pair.1,
+            Value::Nil => return Err(CError::IndexOutOfRange(index as i128, i)),
+            _ => return Err(CError::ArgTypeError(0, ValueType::List, args.get(0).unwrap().clone())),
+        }
+    }
+    Ok(this.clone())
+}
+
+/// `(map f l ...)` stops at the end of the shortest list.
+pub(crate) fn map(args: Vec<Value>) -> CResult {
+    if args.len() < 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let callable = callable_arg(&args, 0)?;
+    let (lists, len) = lists_arg(&args, 1)?;
+    let r = (0..len)
+        .map(|i| call_back(callable, &column(&lists, i)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::from(&r[..]))
+}
+
+pub(crate) fn for_each(args: Vec<Value>) -> CResult {
+    if args.len() < 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let callable = callable_arg(&args, 0)?;
+    let (lists, len) = lists_arg(&args, 1)?;
+    for i in 0..len {
+        call_back(callable, &column(&lists, i))?;
+    }
+    Ok(Value::Nil)
+}
+
+/// `(filter pred l)`, `pred` should give a bool.
+pub(crate) fn filter(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let callable = callable_arg(&args, 0)?;
+    let mut r = vec![];
+    for i in list_arg(&args, 1)? {
+        if predicate(callable, std::slice::from_ref(&i))? {
+            r.push(i);
+        }
+    }
+    Ok(Value::from(&r[..]))
+}
+
+/// `(fold-left f init l ...)` is `(f (f init a0 b0 ...) a1 b1 ...)`...
+pub(crate) fn fold_left(args: Vec<Value>) -> CResult {
+    if args.len() < 3 {
+        return Err(CError::ArgsNotMatching(3, args.len()));
+    }
+    let callable = callable_arg(&args, 0)?;
+    let (lists, len) = lists_arg(&args, 2)?;
+    (0..len).try_fold(args.get(1).unwrap().clone(), |acc, i| {
+        let mut call_args = vec![acc];
+        call_args.extend(column(&lists, i));
+        call_back(callable, &call_args)
+    })
+}
+
+/// `(fold-right f init l ...)` is `(f a0 b0 ... (f a1 b1 ... init))`...
+pub(crate) fn fold_right(args: Vec<Value>) -> CResult {
+    if args.len() < 3 {
+        return Err(CError::ArgsNotMatching(3, args.len()));
+    }
+    let callable = callable_arg(&args, 0)?;
+    let (lists, len) = lists_arg(&args, 2)?;
+    (0..len).rev().try_fold(args.get(1).unwrap().clone(), |acc, i| {
+        let mut call_args = column(&lists, i);
+        call_args.push(acc);
+        call_back(callable, &call_args)
+    })
+}
+
+/// `(reduce f default l)` is `(f a2 (f a1 a0))`..., or `default` if `l` is empty.
+pub(crate) fn reduce(args: Vec<Value>) -> CResult {
+    if args.len() != 3 {
+        return Err(CError::ArgsNotMatching(3, args.len()));
+    }
+    let callable = callable_arg(&args, 0)?;
+    let mut items = list_arg(&args, 2)?.into_iter();
+    match items.next() {
+        Some(first) => items.try_fold(first, |acc, i| call_back(callable, &[i, acc])),
+        None => Ok(args.get(1).unwrap().clone()),
+    }
+}
+
+fn find_entry(args: &[Value], same: fn(&Value, &Value) -> bool) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let key = args.get(0).unwrap();
+    for i in list_arg(args, 1)? {
+        match &i {
+            Value::Pair(entry) if same(&entry.0, key) => return Ok(i),
+            Value::Pair(_) => {},
+            _ => return Err(CError::ArgTypeError(1, ValueType::List, args.get(1).unwrap().clone())),
+        }
+    }
+    Ok(Value::Nil)
+}
+
+/// `(assoc key alist)` is the first pair of `alist` whose car is `equal?` to `key`, or nil.
+pub(crate) fn assoc(args: Vec<Value>) -> CResult {
+    find_entry(&args, Value::is_equal)
+}
+
+/// `assoc` with `eq?`.
+pub(crate) fn assq(args: Vec<Value>) -> CResult {
+    find_entry(&args, Value::is_eq)
+}
+
+/// `(member x l)` is the tail of `l` from the first item `equal?` to `x`, or nil.
+pub(crate) fn member(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let x = args.get(0).unwrap();
+    let mut this = args.get(1).unwrap();
+    while let Value::Pair(pair) = this {
+        if pair.0.is_equal(x) {
+            return Ok(this.clone());
+        }
+        this = &pair.1;
+    }
+    if !this.is_nil() {
+        return Err(CError::ArgTypeError(1, ValueType::List, args.get(1).unwrap().clone()));
+    }
+    Ok(Value::Nil)
+}
+
+pub(crate) fn last(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    list_arg(&args, 0)?.pop()
+        .ok_or_else(|| CError::ArgTypeError(0, ValueType::Pair, args.get(0).unwrap().clone()))
+}
+
+/// `(iota n start step)` is the list of `n` numbers `start`, `start + step`...,
+/// `start` is 0 and `step` 1 if they are left out.
+pub(crate) fn iota(args: Vec<Value>) -> CResult {
+    if args.is_empty() || args.len() > 3 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let count = count_arg(&args, 0)?;
+    let start = args.get(1).cloned().unwrap_or(Value::Uint(0));
+    let step = args.get(2).cloned().unwrap_or(Value::Uint(1));
+    Number::from_value(1, &start)?;
+    Number::from_value(2, &step)?;
+    // the numbers are computed apart, adding up floats would add up their errors
+    let r = (0..count)
+        .map(|i| native_add(vec![start.clone(), native_mul(vec![Value::Uint(i as u64), step.clone()])?]))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::from(&r[..]))
+}
+
+pub(crate) fn list_to_vector(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    Ok(Value::from(list_arg(&args, 0)?))
+}
+
+pub(crate) fn vector_to_list(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    match args.get(0).unwrap() {
+        Value::Vec(x) => Ok(Value::from(&x.0.read().unwrap()[..])),
+        v => Err(CError::ArgTypeError(0, ValueType::Vec, v.clone())),
+    }
+}
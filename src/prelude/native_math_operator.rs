@@ -1,16 +1,23 @@
-use crate::value::Value;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::value::{Value, ValueType};
 use crate::value::result::{CResult, CError};
 
 
-macro_rules! impl_native_num_add {
-    ($fn_name:ident, $ctor:ident, $init:expr) => (
+macro_rules! impl_native_num_fold {
+    ($fn_name:ident, $ctor:ident, $init:expr, $op:expr) => (
         pub(crate) fn $fn_name(args: Vec<Value>) -> CResult {
             let mut ret = $init;
-            for arg in args.into_iter() {
+            for (i, arg) in args.into_iter().enumerate() {
                 if let Value::$ctor(v) = arg {
-                    ret += v;
+                    ret = $op(ret, v)?;
                 } else {
-                    return CResult::Err(CError::TypeError((), arg));
+                    return CResult::Err(CError::ArgTypeError(i, ValueType::$ctor, arg));
                 }
             }
             CResult::Ok(Value::$ctor(ret))
@@ -18,95 +25,267 @@ macro_rules! impl_native_num_add {
     )
 }
 
-impl_native_num_add! { native_add_int, Int, 0 }
-impl_native_num_add! { native_add_uint, Uint, 0 }
-impl_native_num_add! { native_add_float, Float, 0.0 }
-
-macro_rules! impl_native_num_sub {
-    ($fn_name:ident, $ctor:ident) => {
+/// Like `impl_native_num_fold!`, starting from the first argument.
+macro_rules! impl_native_num_reduce {
+    ($fn_name:ident, $ctor:ident, $op:expr) => {
         pub(crate) fn $fn_name(args: Vec<Value>) -> CResult {
-            let mut ret = if let Value::$ctor(v) = &args[0] {
-                *v
-            } else {
-                panic!()
+            let mut ret = match args.get(0) {
+                Some(Value::$ctor(v)) => *v,
+                Some(v) => return CResult::Err(CError::ArgTypeError(0, ValueType::$ctor, v.clone())),
+                None => return CResult::Err(CError::ArgsNotMatching(1, 0)),
             };
 
-            for arg in args.into_iter().skip(1) {
+            for (i, arg) in args.into_iter().enumerate().skip(1) {
                 if let Value::$ctor(v) = arg {
-                    ret -= v;
+                    ret = $op(ret, v)?;
                 } else {
-                    return CResult::Err(CError::TypeError((), arg));
+                    return CResult::Err(CError::ArgTypeError(i, ValueType::$ctor, arg));
                 }
             }
 
-            return CResult::Ok(Value::$ctor(ret));
+            Ok(Value::$ctor(ret))
         }
     }
 }
 
-impl_native_num_sub! { native_sub_int, Int }
-impl_native_num_sub! { native_sub_uint, Uint }
-impl_native_num_sub! { native_sub_float, Float }
+fn checked<T>(r: Option<T>) -> Result<T, CError> {
+    r.ok_or(CError::OverflowError)
+}
 
-macro_rules! impl_native_num_mul {
-    ($fn_name:ident, $ctor:ident, $init:expr) => (
-        pub(crate) fn $fn_name(args: Vec<Value>) -> CResult {
-            let mut ret = $init;
-            for arg in args.into_iter() {
-                if let Value::$ctor(v) = arg {
-                    ret *= v;
-                } else {
-                    return CResult::Err(CError::TypeError((), arg));
-                }
-            }
-            CResult::Ok(Value::$ctor(ret))
-        }
-    )
+fn checked_div<T: Default + PartialEq>(a: T, b: T, div: fn(T, T) -> Option<T>) -> Result<T, CError> {
+    if b == T::default() {
+        Err(CError::ZeroDivisionError)
+    } else {
+        checked(div(a, b))
+    }
 }
 
-impl_native_num_mul! { native_mul_int, Int, 1 }
-impl_native_num_mul! { native_mul_uint, Uint, 1 }
-impl_native_num_mul! { native_mul_float, Float, 1.0 }
+fn float<T>(r: T) -> Result<T, CError> {
+    Ok(r)
+}
 
-macro_rules! impl_native_num_div {
-    ($fn_name:ident, $ctor:ident) => {
-        pub(crate) fn $fn_name(args: Vec<Value>) -> CResult {
-            let mut ret = if let Value::$ctor(v) = &args[0] {
-                *v
-            } else {
-                panic!()
-            };
+// the typed operators keep the type of their arguments and report an overflow,
+// only the generic ones below promote to bigints
+impl_native_num_fold! { native_add_int, Int, 0, |a: i64, b| checked(a.checked_add(b)) }
+impl_native_num_fold! { native_add_uint, Uint, 0, |a: u64, b| checked(a.checked_add(b)) }
+impl_native_num_fold! { native_add_float, Float, 0.0, |a: f64, b| float(a + b) }
 
-            for arg in args.into_iter().skip(1) {
-                if let Value::$ctor(v) = arg {
-                    ret = ret.checked_div(v).ok_or(CError::ZeroDivisionError)?;
-                } else {
-                    return CResult::Err(CError::TypeError((), arg));
-                }
-            }
+impl_native_num_reduce! { native_sub_int, Int, |a: i64, b| checked(a.checked_sub(b)) }
+impl_native_num_reduce! { native_sub_uint, Uint, |a: u64, b| checked(a.checked_sub(b)) }
+impl_native_num_reduce! { native_sub_float, Float, |a: f64, b| float(a - b) }
 
-            Ok(Value::$ctor(ret))
+impl_native_num_fold! { native_mul_int, Int, 1, |a: i64, b| checked(a.checked_mul(b)) }
+impl_native_num_fold! { native_mul_uint, Uint, 1, |a: u64, b| checked(a.checked_mul(b)) }
+impl_native_num_fold! { native_mul_float, Float, 1.0, |a: f64, b| float(a * b) }
+
+impl_native_num_reduce! { native_div_int, Int, |a, b| checked_div(a, b, i64::checked_div) }
+impl_native_num_reduce! { native_div_uint, Uint, |a, b| checked_div(a, b, u64::checked_div) }
+impl_native_num_reduce! { native_div_float, Float, |a: f64, b| float(a / b) }
+
+
+/// An argument of the generic operators.
+///
+/// Integers are widened so that uints and ints mix, the result is an uint
+/// if every integer was and it is not negative, an int otherwise.
+/// Integers that overflow become bigints, and dividing integers gives a rational
+/// unless the division is exact. Any float makes the result a float.
+/// The builtin operators of eval47 promote the same way, but its VM has neither bigints
+/// nor rationals, so it raises where these would be made, as `(/ 7 2)` does there.
+#[derive(Debug, Clone)]
+pub(crate) enum Number {
+    // the value, and whether an int took part in it
+    Integer(i128, bool),
+    // the integers and rationals that do not fit in an `Integer`
+    Exact(BigRational),
+    Float(f64),
+}
+
+impl Number {
+    pub(crate) fn from_value(index: usize, v: &Value) -> Result<Number, CError> {
+        match v {
+            Value::Uint(x) => Ok(Number::Integer(*x as i128, false)),
+            Value::Int(x) => Ok(Number::Integer(*x as i128, true)),
+            Value::Float(x) => Ok(Number::Float(*x)),
+            Value::BigInt(x) => Ok(Number::Exact(BigRational::from_integer((**x).clone()))),
+            Value::Rational(x) => Ok(Number::Exact((**x).clone())),
+            v => Err(CError::ArgTypeError(index, ValueType::Number, v.clone())),
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        match self {
+            Number::Integer(x, _) => *x as f64,
+            Number::Exact(x) => x.to_f64().unwrap_or(f64::NAN),
+            Number::Float(x) => *x,
+        }
+    }
+
+    fn to_rational(&self) -> BigRational {
+        match self {
+            Number::Integer(x, _) => BigRational::from_integer(BigInt::from(*x)),
+            Number::Exact(x) => x.clone(),
+            Number::Float(_) => unreachable!(),
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Number::Integer(x, false) if x >= 0 => match u64::try_from(x) {
+                Ok(x) => Value::Uint(x),
+                Err(_) => Value::from_bigint(BigInt::from(x)),
+            },
+            Number::Integer(x, _) => match i64::try_from(x) {
+                Ok(x) => Value::Int(x),
+                Err(_) => Value::from_bigint(BigInt::from(x)),
+            },
+            Number::Exact(x) => Value::from_rational(x),
+            Number::Float(x) => Value::Float(x),
+        }
+    }
+
+    /// `integer` gives `None` when it overflows, or when its result is not an integer,
+    /// `exact` is tried then.
+    fn apply(
+        self,
+        other: Number,
+        integer: impl Fn(i128, i128) -> Option<i128>,
+        exact: impl Fn(BigRational, BigRational) -> Result<BigRational, CError>,
+        float: impl Fn(f64, f64) -> f64
+    ) -> Result<Number, CError> {
+        match (self, other) {
+            (Number::Integer(a, sa), Number::Integer(b, sb)) => match integer(a, b) {
+                Some(r) => Ok(Number::Integer(r, sa || sb)),
+                None => Ok(Number::Exact(exact(BigRational::from_integer(a.into()), BigRational::from_integer(b.into()))?)),
+            },
+            (a @ Number::Float(_), b) | (a, b @ Number::Float(_)) => Ok(Number::Float(float(a.to_f64(), b.to_f64()))),
+            (a, b) => Ok(Number::Exact(exact(a.to_rational(), b.to_rational())?)),
+        }
+    }
+
+    pub(crate) fn compare(&self, other: &Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Integer(a, _), Number::Integer(b, _)) => Some(a.cmp(b)),
+            (Number::Float(_), _) | (_, Number::Float(_)) => self.to_f64().partial_cmp(&other.to_f64()),
+            (a, b) => Some(a.to_rational().cmp(&b.to_rational())),
         }
     }
 }
 
-impl_native_num_div! { native_div_int, Int }
-impl_native_num_div! { native_div_uint, Uint }
+fn numbers(args: &[Value]) -> Result<Vec<Number>, CError> {
+    args.iter().enumerate().map(|(i, x)| Number::from_value(i, x)).collect()
+}
 
-pub(crate) fn native_div_float(args: Vec<Value>) -> CResult {
-    let mut ret = if let Value::Float(v) = &args[0] {
-        *v
+/// Folds the arguments with `op`, `unary` applies to a single argument.
+fn reduce(
+    args: Vec<Value>,
+    unary: impl Fn(Number) -> Result<Number, CError>,
+    op: impl Fn(Number, Number) -> Result<Number, CError>
+) -> CResult {
+    let mut numbers = numbers(&args)?.into_iter();
+    let first = numbers.next().ok_or(CError::ArgsNotMatching(1, 0))?;
+    let r = if numbers.len() == 0 {
+        unary(first)?
     } else {
-        panic!()
+        numbers.try_fold(first, op)?
     };
+    Ok(r.into_value())
+}
 
-    for arg in args.into_iter().skip(1) {
-        if let Value::Float(v) = arg {
-            ret /= v;
-        } else {
-            return CResult::Err(CError::TypeError((), arg));
-        }
+fn non_zero(x: BigRational) -> Result<BigRational, CError> {
+    if x.is_zero() {
+        Err(CError::ZeroDivisionError)
+    } else {
+        Ok(x)
     }
+}
 
-    Ok(Value::Float(ret))
+fn add(a: Number, b: Number) -> Result<Number, CError> {
+    a.apply(b, i128::checked_add, |a, b| Ok(a + b), |a, b| a + b)
+}
+
+fn sub(a: Number, b: Number) -> Result<Number, CError> {
+    a.apply(b, i128::checked_sub, |a, b| Ok(a - b), |a, b| a - b)
+}
+
+fn mul(a: Number, b: Number) -> Result<Number, CError> {
+    a.apply(b, i128::checked_mul, |a, b| Ok(a * b), |a, b| a * b)
+}
+
+fn div(a: Number, b: Number) -> Result<Number, CError> {
+    let integer = |a: i128, b: i128| a.checked_rem(b).filter(|x| *x == 0).and_then(|_| a.checked_div(b));
+    a.apply(b, integer, |a, b| Ok(a / non_zero(b)?), |a, b| a / b)
+}
+
+fn rem(a: Number, b: Number) -> Result<Number, CError> {
+    a.apply(b, i128::checked_rem, |a, b| Ok(a % non_zero(b)?), |a, b| a % b)
+}
+
+/// `(+ x ...)`, `(+)` is 0.
+pub(crate) fn native_add(args: Vec<Value>) -> CResult {
+    let r = numbers(&args)?.into_iter().try_fold(Number::Integer(0, false), add)?;
+    Ok(r.into_value())
+}
+
+/// `(- x)` negates `x`, `(- x y ...)` subtracts the others from `x`.
+pub(crate) fn native_sub(args: Vec<Value>) -> CResult {
+    reduce(args, |x| sub(Number::Integer(0, true), x), sub)
+}
+
+/// `(* x ...)`, `(*)` is 1.
+pub(crate) fn native_mul(args: Vec<Value>) -> CResult {
+    let r = numbers(&args)?.into_iter().try_fold(Number::Integer(1, false), mul)?;
+    Ok(r.into_value())
+}
+
+/// `(/ x y ...)`, integers that do not divide give a rational, `(/ x)` is `(/ 1 x)`.
+pub(crate) fn native_div(args: Vec<Value>) -> CResult {
+    reduce(args, |x| div(Number::Integer(1, false), x), div)
+}
+
+/// `(% x y)`, the remainder has the sign of `x`.
+pub(crate) fn native_rem(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    reduce(args, Ok, rem)
+}
+
+/// `(exact->inexact x)` is the float nearest to `x`.
+pub(crate) fn exact_to_inexact(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    Ok(Value::Float(Number::from_value(0, args.get(0).unwrap())?.to_f64()))
+}
+
+/// `(inexact->exact x)` is the integer or rational that `x` stands for exactly.
+pub(crate) fn inexact_to_exact(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    match args.get(0).unwrap() {
+        Value::Float(x) => Value::exact_float(*x)
+            .ok_or_else(|| CError::InvalidValue("finite float", Value::Float(*x))),
+        v => Number::from_value(0, v).map(Number::into_value),
+    }
+}
+
+/// `(string->number s)` reads the numbers of the literals, or gives nil.
+pub(crate) fn string_to_number(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let s = if let Value::Str(x) = args.get(0).unwrap() {
+        x.trim()
+    } else {
+        return Err(CError::ArgTypeError(0, ValueType::Str, args.get(0).unwrap().clone()));
+    };
+    if let Some(r) = Value::parse_exact(s) {
+        return Ok(r);
+    }
+    let is_float = s.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.');
+    match s.parse::<f64>() {
+        Ok(r) if is_float => Ok(Value::Float(r)),
+        _ => Ok(Value::Nil),
+    }
 }
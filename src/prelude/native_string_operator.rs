@@ -1,16 +1,16 @@
 use sexpr_ir::gast::Handle;
 
-use crate::value::Value;
+use crate::value::{Value, ValueType};
 use crate::value::result::{CResult, CError};
 
 
 pub(crate) fn native_add_str(args: Vec<Value>) -> CResult {
     let mut ret = String::new();
-    for arg in args.into_iter() {
+    for (i, arg) in args.into_iter().enumerate() {
         if let Value::Str(v) = arg {
             ret += &*v;
         } else {
-            return CResult::Err(CError::TypeError((), arg));
+            return CResult::Err(CError::ArgTypeError(i, ValueType::Str, arg));
         }
     }
     CResult::Ok(Value::Str(Handle::new(ret)))
@@ -0,0 +1,235 @@
+use crate::value::{Value, ValueType, Vector};
+use crate::value::result::{CResult, CError};
+
+use super::native_list_operator::{call_back, callable_arg, column, index_arg, predicate};
+
+
+fn vector_arg(args: &[Value], index: usize) -> Result<&Vector, CError> {
+    match args.get(index).unwrap() {
+        Value::Vec(x) => Ok(x),
+        v => Err(CError::ArgTypeError(index, ValueType::Vec, v.clone())),
+    }
+}
+
+/// A copy of the items, callbacks are called on it so that they may change the vector.
+fn vector_items(args: &[Value], index: usize) -> Result<Vec<Value>, CError> {
+    Ok(vector_arg(args, index)?.0.read().unwrap().clone())
+}
+
+/// The vectors from `args[start..]`, and the length of the shortest.
+fn vectors_arg(args: &[Value], start: usize) -> Result<(Vec<Vec<Value>>, usize), CError> {
+    let vectors = (start..args.len())
+        .map(|i| vector_items(args, i))
+        .collect::<Result<Vec<_>, _>>()?;
+    let len = vectors.iter().map(Vec::len).min().unwrap_or(0);
+    Ok((vectors, len))
+}
+
+pub(crate) fn vector_ref(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let vector = vector_arg(&args, 0)?;
+    let items = vector.0.read().unwrap();
+    let index = index_arg(&args, 1, || items.len())?;
+    items.get(index).cloned().ok_or(CError::IndexOutOfRange(index as i128, items.len()))
+}
+
+pub(crate) fn vector_length(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let r = vector_arg(&args, 0)?.0.read().unwrap().len();
+    Ok(Value::Uint(r as u64))
+}
+
+/// `(vec-set! v i x)` replaces the item at `i` with `x`.
+pub(crate) fn vector_set(args: Vec<Value>) -> CResult {
+    if args.len() != 3 {
+        return Err(CError::ArgsNotMatching(3, args.len()));
+    }
+    let vector = vector_arg(&args, 0)?;
+    let mut items = vector.0.write().unwrap();
+    let len = items.len();
+    let index = index_arg(&args, 1, || len)?;
+    let item = items.get_mut(index).ok_or(CError::IndexOutOfRange(index as i128, len))?;
+    *item = args.get(2).unwrap().clone();
+    Ok(Value::Nil)
+}
+
+pub(crate) fn vector_push(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    vector_arg(&args, 0)?.0.write().unwrap().push(args.get(1).unwrap().clone());
+    Ok(Value::Nil)
+}
+
+/// `(vec-pop! v)` removes the last item and gives it, or nil if `v` is empty.
+pub(crate) fn vector_pop(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    let r = vector_arg(&args, 0)?.0.write().unwrap().pop();
+    Ok(r.unwrap_or(Value::Nil))
+}
+
+/// `(vec-map f v ...)` is a new vector, it stops at the end of the shortest vector.
+pub(crate) fn vector_map(args: Vec<Value>) -> CResult {
+    if args.len() < 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let callable = callable_arg(&args, 0)?;
+    let (vectors, len) = vectors_arg(&args, 1)?;
+    let r = (0..len)
+        .map(|i| call_back(callable, &column(&vectors, i)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::from(r))
+}
+
+pub(crate) fn vector_for_each(args: Vec<Value>) -> CResult {
+    if args.len() < 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let callable = callable_arg(&args, 0)?;
+    let (vectors, len) = vectors_arg(&args, 1)?;
+    for i in 0..len {
+        call_back(callable, &column(&vectors, i))?;
+    }
+    Ok(Value::Nil)
+}
+
+/// `(vec-filter pred v)` is a new vector, `pred` should give a bool.
+pub(crate) fn vector_filter(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let callable = callable_arg(&args, 0)?;
+    let mut r = vec![];
+    for i in vector_items(&args, 1)? {
+        if predicate(callable, std::slice::from_ref(&i))? {
+            r.push(i);
+        }
+    }
+    Ok(Value::from(r))
+}
+
+/// `(vec-reduce f v)` is `(f (f a0 a1) a2)`..., or nil if `v` is empty.
+pub(crate) fn vector_reduce(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let callable = callable_arg(&args, 0)?;
+    let mut items = vector_items(&args, 1)?.into_iter();
+    let init = items.next().unwrap_or(Value::Nil);
+    items.try_fold(init, |x, y| call_back(callable, &[x, y]))
+}
+
+/// `(vec-slice v start end)` is a new vector of the items from `start` to before `end`,
+/// `end` is the length of `v` if it is left out.
+pub(crate) fn vector_slice(args: Vec<Value>) -> CResult {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let vector = vector_arg(&args, 0)?;
+    let items = vector.0.read().unwrap();
+    let start = index_arg(&args, 1, || items.len())?;
+    let end = if args.len() == 3 {
+        index_arg(&args, 2, || items.len())?
+    } else {
+        items.len()
+    };
+    if end > items.len() {
+        return Err(CError::IndexOutOfRange(end as i128, items.len()));
+    }
+    if start > end {
+        return Err(CError::IndexOutOfRange(start as i128, end));
+    }
+    Ok(Value::from(items[start..end].to_vec()))
+}
+
+/// `(vec-fill! v x)` replaces every item of `v` with `x`.
+pub(crate) fn vector_fill(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let x = args.get(1).unwrap();
+    vector_arg(&args, 0)?.0.write().unwrap().fill(x.clone());
+    Ok(Value::Nil)
+}
+
+/// `(vec-copy v)` is a new vector of the same items, which are not copied.
+pub(crate) fn vector_copy(args: Vec<Value>) -> CResult {
+    if args.len() != 1 {
+        return Err(CError::ArgsNotMatching(1, args.len()));
+    }
+    Ok(Value::from(vector_items(&args, 0)?))
+}
+
+/// A stable merge sort, `std` can not give up on the errors of `less`.
+fn merge_sort(
+    mut items: Vec<Value>,
+    less: &impl Fn(&Value, &Value) -> Result<bool, CError>
+) -> Result<Vec<Value>, CError> {
+    if items.len() < 2 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, less)?;
+    let right = merge_sort(right, less)?;
+    let mut r = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // an item of `right` goes first only if it is strictly less, to keep the order of equal items
+        if less(b, a)? {
+            r.extend(right.next());
+        } else {
+            r.extend(left.next());
+        }
+    }
+    r.extend(left);
+    r.extend(right);
+    Ok(r)
+}
+
+/// `(vec-sort! v less)` sorts `v` in place, keeping the order of equal items.
+/// `(less a b)` should give whether `a` goes before `b`.
+pub(crate) fn vector_sort(args: Vec<Value>) -> CResult {
+    if args.len() != 2 {
+        return Err(CError::ArgsNotMatching(2, args.len()));
+    }
+    let vector = vector_arg(&args, 0)?;
+    let callable = callable_arg(&args, 1)?;
+    let items = vector_items(&args, 0)?;
+    let r = merge_sort(items, &|a, b| predicate(callable, &[a.clone(), b.clone()]))?;
+    *vector.0.write().unwrap() = r;
+    Ok(Value::Nil)
+}
+
+/// `(vec-binary-search v x less)` is the index of an item of `v` equal to `x`, or nil,
+/// `v` should be sorted by `less`, as `vec-sort!` does.
+/// The first of the equal items is found.
+pub(crate) fn vector_binary_search(args: Vec<Value>) -> CResult {
+    if args.len() != 3 {
+        return Err(CError::ArgsNotMatching(3, args.len()));
+    }
+    let items = vector_items(&args, 0)?;
+    let x = args.get(1).unwrap();
+    let callable = callable_arg(&args, 2)?;
+    let less = |a: &Value, b: &Value| predicate(callable, &[a.clone(), b.clone()]);
+    let (mut low, mut high) = (0, items.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if less(&items[mid], x)? {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    if low < items.len() && !less(x, &items[low])? {
+        Ok(Value::Uint(low as u64))
+    } else {
+        Ok(Value::Nil)
+    }
+}
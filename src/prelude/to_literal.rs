@@ -14,6 +14,8 @@ impl Value {
             Value::Int(v) => if *v > 0 {write!(f, "+{}", v)} else {write!(f, "{}", v)},
             Value::Uint(v) => write!(f, "{}", v),
             Value::Float(v) => write!(f, "{}", v),
+            Value::BigInt(v) => write!(f, "{}", v),
+            Value::Rational(v) => write!(f, "{}", v),
             Value::Sym(v) => write!(f, "{}", v.0),
             Value::Str(v) => write!(f, "\"{}\"", v), // todo: escape
             Value::Char(v) => write!(f, "(char \"{}\")", v), // todo: escape
@@ -21,6 +23,7 @@ impl Value {
             Value::Dict(_) => panic!("error: to_literal is not supported for dict"),
             Value::Vec(_) => panic!("error: to_literal is not supported for vec"),
             Value::Callable(_) => panic!("error: to_literal is not supported for callable"),
+            Value::Error(_) => panic!("error: to_literal is not supported for error"),
         }
     }
 }
@@ -1,6 +1,6 @@
 use sexpr_ir::gast::{GAst, Handle, constant::Constant, list::List, symbol::Symbol};
 
-use crate::{ast::{Expr, Let, Set, TopLevel}, error::{CompilerError, incomplete_expr, invalid_expr_length, invalid_expr_type, invalid_list_tail}, sexpr_to_ast::symbol_from_sexpr};
+use crate::{ast::{Expr, Let, Set, TopLevel}, error::{CompilerError, incomplete_expr, invalid_expr_length, invalid_expr_type, invalid_list_tail, ExprType}, sexpr_to_ast::symbol_from_sexpr};
 
 use super::FromSexpr;
 
@@ -18,7 +18,7 @@ impl FromSexpr<List, Set> for Set {
         let pos = if let GAst::Const(Constant::Sym(l)) = label.unwrap() {
             l.1.clone()
         } else {
-            error_buffer.push(invalid_expr_type(&*i, ()));
+            error_buffer.push(invalid_expr_type(&*i, ExprType::Symbol));
             return Err(error_buffer);
         };
 
@@ -37,7 +37,7 @@ impl FromSexpr<List, Set> for Set {
         let name = if let GAst::Const(Constant::Sym(l)) = name {
             l.clone()
         } else {
-            error_buffer.push(invalid_expr_type(&*i, ()));
+            error_buffer.push(invalid_expr_type(&*i, ExprType::Symbol));
             return Err(error_buffer);
         };
         let value = Expr::from_sexpr(value)?;
@@ -65,7 +65,7 @@ impl FromSexpr<List, Let> for Let {
         let pos = if let GAst::Const(Constant::Sym(l)) = label.unwrap() {
             l.1.clone()
         } else {
-            error_buffer.push(invalid_expr_type(&*i, ()));
+            error_buffer.push(invalid_expr_type(&*i, ExprType::Symbol));
             return Err(error_buffer);
         };
 
@@ -74,7 +74,7 @@ impl FromSexpr<List, Let> for Let {
             .next()
             .ok_or_else(|| vec![incomplete_expr(&*i)])?
             .get_list()
-            .ok_or_else(|| vec![invalid_expr_type(&*i, ())])?;
+            .ok_or_else(|| vec![invalid_expr_type(&*i, ExprType::List)])?;
 
         if binds.1.is_some() {
             error_buffer.push(invalid_list_tail(&*i));
@@ -138,6 +138,6 @@ fn bind_from_sexpr(i: &GAst) -> Result<(Handle<Symbol>, Expr), Vec<CompilerError
             Err(error_buffer)
         }
     } else {
-        Err(vec![invalid_expr_type(&*i, ())])
+        Err(vec![invalid_expr_type(&*i, ExprType::List)])
     }
 }
@@ -1,6 +1,6 @@
 use sexpr_ir::gast::{GAst, list::List};
 
-use crate::{ast::{Call, Expr}, error::{CompilerError, invalid_expr_type}};
+use crate::{ast::{Call, Expr}, error::{CompilerError, ExprType, bad_syntax, invalid_expr_type}};
 
 use super::FromSexpr;
 
@@ -12,7 +12,7 @@ impl FromSexpr<GAst, Call> for Call {
         if let GAst::List(x) = i {
             call_process(x)
         } else {
-            Err(vec![invalid_expr_type(i, ())])
+            Err(vec![invalid_expr_type(i, ExprType::List)])
         }
     }
 }
@@ -21,7 +21,7 @@ impl FromSexpr<GAst, Call> for Call {
 pub(crate) fn call_process(x: &List) -> Result<Call, Vec<CompilerError>> {
     let mut error_buffer = vec![];
     if x.1.is_some() {
-        error_buffer.push(CompilerError::BadSyntax(x.to_string()));
+        error_buffer.push(bad_syntax(x));
     }
     let r =
         x.0.iter()
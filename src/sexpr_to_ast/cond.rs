@@ -1,6 +1,6 @@
 use sexpr_ir::gast::{GAst, constant::Constant, list::List};
 
-use crate::{ast::{Cond, Expr}, error::{CompilerError, incomplete_expr, invalid_expr_length, invalid_expr_type, invalid_list_tail}};
+use crate::{ast::{Cond, Expr}, error::{CompilerError, incomplete_expr, invalid_expr_length, invalid_expr_type, invalid_list_tail, ExprType}};
 
 use super::FromSexpr;
 
@@ -26,7 +26,7 @@ impl FromSexpr<List, Cond> for Cond {
         let pos = if let GAst::Const(Constant::Sym(l)) = label.unwrap() {
             l.1.clone()
         } else {
-            error_buffer.push(invalid_expr_type(&*i, ()));
+            error_buffer.push(invalid_expr_type(&*i, ExprType::Symbol));
             return Err(error_buffer);
         };
 
@@ -67,6 +67,7 @@ fn pair_from_sexpr(i: &GAst) -> Result<(Expr, Expr), Vec<CompilerError>> {
         }
         if i.0.len() != 2 {
             error_buffer.push(invalid_expr_length(i, 2, i.0.len()));
+            return Err(error_buffer);
         }
         let cond = i.0.get(0).unwrap();
         let expr = i.0.get(1).unwrap();
@@ -85,6 +86,6 @@ fn pair_from_sexpr(i: &GAst) -> Result<(Expr, Expr), Vec<CompilerError>> {
             Err(error_buffer)
         }
     } else {
-        Err(vec![invalid_expr_type(i, ())])
+        Err(vec![invalid_expr_type(i, ExprType::List)])
     }
 }
\ No newline at end of file
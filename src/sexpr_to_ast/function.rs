@@ -1,6 +1,6 @@
 use sexpr_ir::gast::{GAst, constant::Constant, list::List};
 
-use crate::{ast::{Function, TopLevel}, error::{CompilerError, bad_syntax, incomplete_expr, invalid_expr_type, invalid_list_tail}, sexpr_to_ast::symbol_from_sexpr};
+use crate::{ast::{Function, TopLevel}, error::{CompilerError, bad_syntax, incomplete_expr, invalid_expr_type, invalid_list_tail, ExprType}, sexpr_to_ast::symbol_from_sexpr};
 
 use super::FromSexpr;
 
@@ -20,7 +20,7 @@ impl FromSexpr<List, Function> for Function {
         let (def_headle, pos) = if let GAst::Const(Constant::Sym(sym)) = def_headle {
             (sym.0.clone(), sym.1.clone())
         } else {
-            error_buffer.push(invalid_expr_type(i, ()));
+            error_buffer.push(invalid_expr_type(i, ExprType::Symbol));
             return Err(error_buffer);
         };
 
@@ -29,7 +29,7 @@ impl FromSexpr<List, Function> for Function {
             .next()
             .ok_or_else(|| vec![incomplete_expr(&*i)])?
             .get_list()
-            .ok_or_else(|| vec![invalid_expr_type(i, ())])?;
+            .ok_or_else(|| vec![invalid_expr_type(i, ExprType::List)])?;
 
         let List(prarms, extend_prarms) = (*prarms).clone();
         let mut prarms = prarms.iter();
@@ -68,7 +68,7 @@ impl FromSexpr<List, Function> for Function {
         let bodys: Vec<_> = iter.collect();
         /*
         if bodys.is_empty() {
-            error_buffer.push(CompilerError::IncompleteExpr(i.to_string()));
+            error_buffer.push(incomplete_expr(i));
             return Err(error_buffer);
         }
         // */
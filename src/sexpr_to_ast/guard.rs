@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use sexpr_ir::gast::{GAst, Handle, constant::Constant, list::List, symbol::{Location, Symbol}};
+
+use crate::{ast::{Call, Cond, Expr, Function, TopLevel}, error::{CompilerError, bad_syntax, invalid_list_tail}};
+use crate::prelude::error::{dynamic_wind, native_raise, try_call};
+use crate::prelude::raw_operator::ignore;
+
+use super::{FromSexpr, builtin, call_builtin};
+
+
+/// `(guard (e clause ...) body ...)` catches what is raised in the body
+/// and hands it to `e` in the `cond` clauses, it is raised again if none of them matches.
+pub fn guard_from_sexpr(i: &List) -> Result<Expr, Vec<CompilerError>> {
+    if i.1.is_some() {
+        return Err(vec![invalid_list_tail(i)]);
+    }
+    let pos = head_location(i)?;
+    let (var, clauses) = match i.0.get(1) {
+        Some(GAst::List(spec)) if spec.1.is_none() => match spec.0.split_first() {
+            Some((GAst::Const(Constant::Sym(var)), clauses)) => (var.clone(), clauses.to_vec()),
+            _ => return Err(vec![bad_syntax(&**spec)]),
+        },
+        _ => return Err(vec![bad_syntax(i)]),
+    };
+    let mut cond = vec![symbol("cond", &pos)];
+    cond.extend(clauses);
+    let mut cond = Cond::from_sexpr(&List(cond, None))?;
+    if cond.other.is_none() {
+        let var = Expr::Variable(var.clone());
+        cond.other = Some(call_builtin("raise", Arc::new(|_, args| native_raise(args)), vec![var]));
+    }
+    let handler = Function {
+        name: None,
+        params: vec![var],
+        extend_params: None,
+        body: vec![TopLevel::Expr(Expr::Cond(Handle::new(cond)))],
+        pos: pos.clone(),
+    };
+    let thunk = Expr::from_sexpr(&lambda(vec![], i.0[2..].to_vec(), &pos))?;
+    let handler = Expr::Lambda(Handle::new(handler));
+    Ok(call_builtin("try-call", Arc::new(|_, args| try_call(args)), vec![thunk, handler]))
+}
+
+/// `(try body ... (catch (e) handler ...) (finally cleanup ...))`,
+/// either of `catch` and `finally` may be left out.
+pub fn try_from_sexpr(i: &List) -> Result<Expr, Vec<CompilerError>> {
+    if i.1.is_some() {
+        return Err(vec![invalid_list_tail(i)]);
+    }
+    let pos = head_location(i)?;
+    let mut body = i.0[1..].to_vec();
+    let finally = match body.last().and_then(|x| clause(x, "finally")) {
+        Some(x) => {
+            body.pop();
+            Some(x.0[1..].to_vec())
+        },
+        None => None,
+    };
+    let catch = match body.last().and_then(|x| clause(x, "catch")) {
+        Some(x) => {
+            body.pop();
+            match x.0.get(1) {
+                Some(GAst::List(params)) if params.1.is_none() && params.0.len() == 1 =>
+                    Some((params.0.clone(), x.0[2..].to_vec())),
+                _ => return Err(vec![bad_syntax(&*x)]),
+            }
+        },
+        None => None,
+    };
+    if catch.is_none() && finally.is_none() {
+        return Err(vec![bad_syntax(i)]);
+    }
+    let thunk = Expr::from_sexpr(&lambda(vec![], body, &pos))?;
+    let r = match catch {
+        Some((params, handler)) => {
+            let handler = Expr::from_sexpr(&lambda(params, handler, &pos))?;
+            call_builtin("try-call", Arc::new(|_, args| try_call(args)), vec![thunk, handler])
+        },
+        None => Expr::FunctionCall(Handle::new(Call(vec![thunk]))),
+    };
+    let r = match finally {
+        Some(cleanup) => {
+            let cleanup = Expr::from_sexpr(&lambda(vec![], cleanup, &pos))?;
+            let body = Function {
+                name: None,
+                params: vec![],
+                extend_params: None,
+                body: vec![TopLevel::Expr(r)],
+                pos,
+            };
+            let before = builtin("ignore", Arc::new(|_, args| ignore(args)));
+            let args = vec![before, Expr::Lambda(Handle::new(body)), cleanup];
+            call_builtin("dynamic-wind", Arc::new(|_, args| dynamic_wind(args)), args)
+        },
+        None => r,
+    };
+    Ok(r)
+}
+
+fn head_location(i: &List) -> Result<Location, Vec<CompilerError>> {
+    match i.0.first() {
+        Some(GAst::Const(Constant::Sym(x))) => Ok(x.1.clone()),
+        _ => Err(vec![bad_syntax(i)]),
+    }
+}
+
+/// Returns `(name ...)`.
+fn clause(i: &GAst, name: &str) -> Option<Handle<List>> {
+    let list = i.get_list()?;
+    match list.0.first() {
+        Some(GAst::Const(Constant::Sym(x))) if *x.0 == name && list.1.is_none() => Some(list.clone()),
+        _ => None,
+    }
+}
+
+fn lambda(params: Vec<GAst>, body: Vec<GAst>, pos: &Location) -> GAst {
+    let mut r = vec![symbol("lambda", pos), make_list(params)];
+    r.extend(body);
+    make_list(r)
+}
+
+fn symbol(name: &str, pos: &Location) -> GAst {
+    GAst::Const(Constant::Sym(Handle::new(Symbol(Handle::new(name.to_string()), pos.clone()))))
+}
+
+fn make_list(items: Vec<GAst>) -> GAst {
+    GAst::List(Handle::new(List(items, None)))
+}
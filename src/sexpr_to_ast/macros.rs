@@ -0,0 +1,985 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+
+use sexpr_ir::gast::{GAst, Handle, constant::Constant, list::List, symbol::Symbol};
+
+use crate::ast::Expr;
+use crate::error::{CompilerError, bad_syntax, invalid_list_tail, invalid_template, macro_error, no_matching_rule};
+use crate::evaluation::Eval;
+use crate::evaluation::call::Call;
+use crate::value::Value;
+use crate::value::callable::Callable;
+use crate::value::scope::Scope;
+
+use super::{FromSexpr, symbol_from_sexpr};
+use super::quote::{sexpr_from_value, value_from_sexpr};
+
+
+pub const ELLIPSIS: &str = "...";
+pub const WILDCARD: &str = "_";
+
+#[derive(Debug, Clone)]
+pub struct SyntaxRules {
+    pub literals: Vec<Handle<Symbol>>,
+    pub rules: Vec<(GAst, GAst)>,
+}
+
+/// The user binders in scope where a macro is defined, by the address of their symbol.
+pub type Visible = Handle<HashSet<usize>>;
+
+/// A procedural macro is an ordinary callable,
+/// it receives the operands of the macro use unevaluated and returns the new form.
+#[derive(Debug, Clone)]
+pub enum Macro {
+    Rules(Handle<SyntaxRules>, Visible),
+    Procedure(Callable),
+}
+
+#[derive(Debug, Clone)]
+enum Binding {
+    One(GAst),
+    Many(Vec<Binding>),
+}
+
+type Bindings = HashMap<Handle<String>, Binding>;
+
+/// Expands macro uses on the `GAst` before it is turned into an ast.
+///
+/// Hygiene works by renaming: every symbol a template introduces gets a fresh alias,
+/// aliases bound inside the expansion stay renamed so they can not capture user variables,
+/// and the free ones are turned back into the original symbol.
+/// A free one refers to what it did where the macro is defined,
+/// the user binders around the macro use that it would see instead are renamed.
+/// Procedural macros are not hygienic.
+#[derive(Debug, Clone, Default)]
+pub struct Expander {
+    frames: Vec<HashMap<Handle<String>, Macro>>,
+    /// the original symbol of an alias, and the mark of the macro use that made it
+    aliases: HashMap<Handle<String>, (Handle<Symbol>, usize)>,
+    /// the user binders in scope where the macro of a mark is defined
+    definitions: HashMap<usize, Visible>,
+    /// the user binders in scope of the form being expanded
+    locals: Vec<Handle<Symbol>>,
+    counter: usize,
+    env: Option<Handle<Scope>>,
+}
+
+impl Expander {
+    pub fn new() -> Self {
+        Expander {
+            frames: vec![HashMap::new()],
+            ..Default::default()
+        }
+    }
+
+    /// `define-macro` bodies are evaluated in `env` while expanding.
+    pub fn with_env(env: Handle<Scope>) -> Self {
+        Expander {
+            env: Some(env),
+            ..Expander::new()
+        }
+    }
+
+    /// Returns `None` for a `define-syntax` or `define-macro` form, which is only recorded.
+    pub fn expand_top(&mut self, i: &GAst) -> Result<Option<GAst>, Vec<CompilerError>> {
+        if self.frames.is_empty() {
+            self.frames.push(HashMap::new());
+        }
+        let i = self.expand_head(i)?;
+        if self.try_define(&i)? {
+            return Ok(None);
+        }
+        let r = self.expand(&i)?;
+        Ok(Some(self.resolve(&r)))
+    }
+
+    /// Expands the macro uses in `i`, for `macroexpand`.
+    pub fn macroexpand(&mut self, i: &GAst) -> Result<GAst, Vec<CompilerError>> {
+        let r = self.expand(i)?;
+        Ok(self.resolve(&r))
+    }
+
+    /// Records `i` if it defines a macro.
+    fn try_define(&mut self, i: &GAst) -> Result<bool, Vec<CompilerError>> {
+        if self.is_form(i, "define-syntax") {
+            self.define_syntax(i)?;
+            Ok(true)
+        } else if self.is_form(i, "define-macro") {
+            self.define_macro(i)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    pub fn define_syntax(&mut self, i: &GAst) -> Result<(), Vec<CompilerError>> {
+        let (name, rules) = self.syntax_binding(i)?;
+        let visible = self.visible();
+        self.frames.last_mut().unwrap().insert(name.0.clone(), Macro::Rules(Handle::new(rules), visible));
+        Ok(())
+    }
+
+    /// Both `(define-macro (name . params) body ...)` and `(define-macro name procedure)`.
+    pub fn define_macro(&mut self, i: &GAst) -> Result<(), Vec<CompilerError>> {
+        let list = match i {
+            GAst::List(x) if x.1.is_none() => x,
+            _ => return Err(vec![bad_syntax(i)]),
+        };
+        let head = match list.0.first() {
+            Some(GAst::Const(Constant::Sym(x))) => x.clone(),
+            _ => return Err(vec![bad_syntax(i)]),
+        };
+        let (name, procedure) = match list.0.get(1) {
+            Some(GAst::List(sign)) if !sign.0.is_empty() => {
+                let name = symbol_from_sexpr(&sign.0[0]).map_err(|e| vec![e])?;
+                let lambda = Symbol(Handle::new("lambda".to_string()), head.1.clone());
+                let params = make_list(sign.0[1..].to_vec(), sign.1.clone());
+                let mut r = vec![GAst::Const(Constant::Sym(Handle::new(lambda))), params];
+                r.extend(list.0[2..].iter().cloned());
+                (name, make_list(r, None))
+            },
+            Some(GAst::Const(Constant::Sym(name))) if list.0.len() == 3 => (name.clone(), list.0[2].clone()),
+            _ => return Err(vec![bad_syntax(i)]),
+        };
+        let env = self.env.clone()
+            .ok_or_else(|| vec![macro_error(&head.1, &"there is no environment to evaluate macros in")])?;
+        let procedure = self.expand(&procedure)?;
+        let procedure = Expr::from_sexpr(&self.resolve(&procedure))?;
+        match procedure.eval(&env).map_err(|e| vec![macro_error(&name.1, &e)])? {
+            Value::Callable(f) => {
+                self.frames.last_mut().unwrap().insert(name.0.clone(), Macro::Procedure(f));
+                Ok(())
+            },
+            v => Err(vec![macro_error(&name.1, &format!("{} is not callable", v))]),
+        }
+    }
+
+    fn expand(&mut self, i: &GAst) -> Result<GAst, Vec<CompilerError>> {
+        let i = self.expand_head(i)?;
+        let list = if let GAst::List(x) = &i {
+            x.clone()
+        } else {
+            return Ok(i);
+        };
+        if let Some(GAst::Const(Constant::Sym(head))) = list.0.first() {
+            match self.base_name(head).as_str() {
+                "quote" => return Ok(i),
+                "quasiquote" if list.0.len() == 2 && list.1.is_none() => {
+                    let r = self.expand_quasiquote(&list.0[1], 1)?;
+                    return Ok(make_list(vec![list.0[0].clone(), r], None));
+                },
+                "let-syntax" | "letrec-syntax" => return self.expand_let_syntax(&list),
+                _ => {},
+            }
+        }
+        let locals = self.locals.len();
+        let binders = self.binders(&list);
+        self.locals.extend(binders);
+        self.frames.push(HashMap::new());
+        let r = self.expand_body(&list);
+        self.frames.pop();
+        self.locals.truncate(locals);
+        r
+    }
+
+    /// Only what is unquoted at depth 1 is expanded, the rest of a quasiquote is data.
+    fn expand_quasiquote(&mut self, i: &GAst, depth: usize) -> Result<GAst, Vec<CompilerError>> {
+        let list = if let GAst::List(x) = i {
+            x
+        } else {
+            return Ok(i.clone());
+        };
+        let (items, tail) = split_list(list);
+        if let (Some(depth), None) = (self.quasiquote_depth(&items, depth), &tail) {
+            let r = if depth == 0 {
+                self.expand(&items[1])?
+            } else {
+                self.expand_quasiquote(&items[1], depth)?
+            };
+            return Ok(make_list(vec![items[0].clone(), r], None));
+        }
+        let split = self.unquoted_tail(&items, &tail);
+        let mut r = vec![];
+        for x in items[..split].iter() {
+            r.push(self.expand_quasiquote(x, depth)?);
+        }
+        let tail = if split < items.len() {
+            Some(self.expand_quasiquote(&make_list(items[split..].to_vec(), None), depth)?)
+        } else {
+            match tail {
+                Some(x) => Some(self.expand_quasiquote(&x, depth)?),
+                None => None,
+            }
+        };
+        Ok(make_list(r, tail))
+    }
+
+    /// The depth of `x` in `(quasiquote x)`, `(unquote x)` or `(unquote-splicing x)`
+    /// when the form is at `depth`.
+    fn quasiquote_depth(&self, items: &[GAst], depth: usize) -> Option<usize> {
+        match items {
+            [GAst::Const(Constant::Sym(x)), _] => match self.base_name(x).as_str() {
+                "quasiquote" => Some(depth + 1),
+                "unquote" | "unquote-splicing" => Some(depth - 1),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// `(a . ,x)` is read as `(a unquote x)`, this is where its tail starts.
+    fn unquoted_tail(&self, items: &[GAst], tail: &Option<GAst>) -> usize {
+        let n = items.len();
+        if tail.is_none() && n > 2 && self.quasiquote_depth(&items[n - 2..], 1).is_some() {
+            n - 2
+        } else {
+            n
+        }
+    }
+
+    /// The user binders that `i` brings into scope for its body.
+    fn binders(&self, i: &List) -> Vec<Handle<Symbol>> {
+        let head = match i.0.first() {
+            Some(GAst::Const(Constant::Sym(x))) => self.base_name(x),
+            _ => return vec![],
+        };
+        let mut r: Vec<_> = match (head.as_str(), i.0.get(1)) {
+            ("lambda", Some(params)) | ("catch", Some(params)) => template_symbols(params),
+            ("define", Some(GAst::List(sign))) => sign.0.iter()
+                .skip(1)
+                .chain(sign.1.iter())
+                .flat_map(template_symbols)
+                .collect(),
+            ("let", Some(GAst::List(binds))) => binds.0.iter()
+                .filter_map(|x| x.get_list()?.0.first().cloned())
+                .flat_map(|x| template_symbols(&x))
+                .collect(),
+            ("guard", Some(GAst::List(spec))) => spec.0.iter().take(1).flat_map(template_symbols).collect(),
+            _ => return vec![],
+        };
+        if matches!(head.as_str(), "lambda" | "define" | "let") {
+            r.extend(i.0.iter().skip(2).filter_map(|x| self.defined_symbol(x)));
+        }
+        r
+    }
+
+    fn visible(&self) -> Visible {
+        Handle::new(self.locals.iter().map(address).collect())
+    }
+
+    /// Expands the macro uses at the head of `i` until it is no longer one.
+    fn expand_head(&mut self, i: &GAst) -> Result<GAst, Vec<CompilerError>> {
+        let mut i = i.clone();
+        loop {
+            let head = match &i {
+                GAst::List(x) => match x.0.first() {
+                    Some(GAst::Const(Constant::Sym(head))) => head.clone(),
+                    _ => return Ok(i),
+                },
+                _ => return Ok(i),
+            };
+            match self.find_macro(&head) {
+                Some(Macro::Rules(rules, visible)) => i = self.expand_use(&rules, &visible, &head, &i)?,
+                Some(Macro::Procedure(f)) => i = self.expand_procedure(&f, &head, &i)?,
+                None => return Ok(i),
+            }
+        }
+    }
+
+    fn expand_body(&mut self, i: &List) -> Result<GAst, Vec<CompilerError>> {
+        let mut error_buffer = vec![];
+        let mut items = vec![];
+        for x in i.0.iter() {
+            let r = self.expand_head(x).and_then(|x| {
+                if self.try_define(&x)? {
+                    Ok(None)
+                } else {
+                    self.expand(&x).map(Some)
+                }
+            });
+            match r {
+                Ok(Some(x)) => items.push(x),
+                Ok(None) => {},
+                Err(mut e) => error_buffer.append(&mut e),
+            }
+        }
+        let tail = match &i.1 {
+            Some(x) => self.expand(x).map(Some).unwrap_or_else(|mut e| {
+                error_buffer.append(&mut e);
+                None
+            }),
+            None => None,
+        };
+        if error_buffer.is_empty() {
+            Ok(make_list(items, tail))
+        } else {
+            Err(error_buffer)
+        }
+    }
+
+    /// `(let-syntax ((name rules) ...) body ...)` becomes `(let () body ...)`.
+    fn expand_let_syntax(&mut self, i: &List) -> Result<GAst, Vec<CompilerError>> {
+        if i.1.is_some() {
+            return Err(vec![invalid_list_tail(i)]);
+        }
+        let binds = match i.0.get(1) {
+            Some(GAst::List(x)) if x.1.is_none() => x.clone(),
+            Some(GAst::Const(Constant::Nil)) => Handle::new(List(vec![], None)),
+            _ => return Err(vec![bad_syntax(i)]),
+        };
+        let mut frame = HashMap::new();
+        let visible = self.visible();
+        for x in binds.0.iter() {
+            let (name, rules) = self.syntax_binding(x)?;
+            frame.insert(name.0.clone(), Macro::Rules(Handle::new(rules), visible.clone()));
+        }
+        let head = if let Some(GAst::Const(Constant::Sym(x))) = i.0.first() {
+            Symbol(Handle::new("let".to_string()), x.1.clone())
+        } else {
+            unreachable!()
+        };
+        let mut body = vec![
+            GAst::Const(Constant::Sym(Handle::new(head))),
+            GAst::List(Handle::new(List(vec![], None))),
+        ];
+        body.extend(i.0.iter().skip(2).cloned());
+        self.frames.push(frame);
+        let r = self.expand_body(&List(body, None));
+        self.frames.pop();
+        r
+    }
+
+    fn syntax_binding(&self, i: &GAst) -> Result<(Handle<Symbol>, SyntaxRules), Vec<CompilerError>> {
+        let i = match i {
+            GAst::List(x) => x,
+            _ => return Err(vec![bad_syntax(i)]),
+        };
+        if i.1.is_some() {
+            return Err(vec![invalid_list_tail(&**i)]);
+        }
+        // both (define-syntax name rules) and the (name rules) of let-syntax
+        let (name, rules) = match i.0.as_slice() {
+            [_, GAst::Const(Constant::Sym(name)), rules] |
+            [GAst::Const(Constant::Sym(name)), rules] => (name.clone(), rules),
+            _ => return Err(vec![bad_syntax(&**i)]),
+        };
+        Ok((name, self.syntax_rules(rules)?))
+    }
+
+    fn syntax_rules(&self, i: &GAst) -> Result<SyntaxRules, Vec<CompilerError>> {
+        let i = match i {
+            GAst::List(x) if x.1.is_none() => x,
+            _ => return Err(vec![bad_syntax(i)]),
+        };
+        match i.0.first() {
+            // a macro that defines macros gives an alias of `syntax-rules`
+            Some(GAst::Const(Constant::Sym(x))) if *self.base_name(x) == "syntax-rules" => {},
+            _ => return Err(vec![bad_syntax(&**i)]),
+        }
+        let literals = match i.0.get(1) {
+            Some(GAst::List(x)) if x.1.is_none() => x.0.iter()
+                .map(|x| match x {
+                    GAst::Const(Constant::Sym(x)) => Ok(x.clone()),
+                    _ => Err(vec![bad_syntax(x)]),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(GAst::Const(Constant::Nil)) => vec![],
+            _ => return Err(vec![bad_syntax(&**i)]),
+        };
+        let rules = i.0.iter()
+            .skip(2)
+            .map(|x| match x {
+                GAst::List(x) if x.1.is_none() && x.0.len() == 2 && x.0[0].get_list().is_some() =>
+                    Ok((x.0[0].clone(), x.0[1].clone())),
+                _ => Err(vec![bad_syntax(x)]),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SyntaxRules { literals, rules })
+    }
+
+    fn find_macro(&self, name: &Handle<Symbol>) -> Option<Macro> {
+        let mut name = name.clone();
+        loop {
+            let r = self.frames.iter().rev().find_map(|x| x.get(&name.0));
+            if let Some(r) = r {
+                return Some(r.clone());
+            }
+            name = self.aliases.get(&name.0)?.0.clone();
+        }
+    }
+
+    fn expand_procedure(
+        &mut self,
+        f: &Callable,
+        head: &Handle<Symbol>,
+        i: &GAst
+    ) -> Result<GAst, Vec<CompilerError>> {
+        let list = match i {
+            GAst::List(x) if x.1.is_none() => x,
+            _ => return Err(vec![bad_syntax(i)]),
+        };
+        let args: Vec<_> = list.0[1..].iter().map(value_from_sexpr).collect();
+        let r = f.call(&args).map_err(|e| vec![macro_error(&head.1, &e)])?;
+        sexpr_from_value(&r)
+            .ok_or_else(|| vec![macro_error(&head.1, &format!("{} is not a form", r))])
+    }
+
+    fn expand_use(
+        &mut self,
+        rules: &SyntaxRules,
+        visible: &Visible,
+        head: &Handle<Symbol>,
+        i: &GAst
+    ) -> Result<GAst, Vec<CompilerError>> {
+        for (pattern, template) in rules.rules.iter() {
+            let mut binds = HashMap::new();
+            if self.match_use(rules, pattern, i, &mut binds) {
+                self.counter += 1;
+                let mark = self.counter;
+                self.definitions.insert(mark, visible.clone());
+                return self.instantiate(template, &binds, mark, false)
+                    .map_err(|e| vec![invalid_template(&head.1, &e)]);
+            }
+        }
+        Err(vec![no_matching_rule(&head.1, i)])
+    }
+
+    /// The keyword position of the pattern is ignored.
+    fn match_use(&self, rules: &SyntaxRules, pattern: &GAst, i: &GAst, binds: &mut Bindings) -> bool {
+        match (pattern, i) {
+            (GAst::List(p), GAst::List(x)) => {
+                let (p_items, p_tail) = split_list(p);
+                let (items, tail) = split_list(x);
+                !p_items.is_empty() && !items.is_empty() &&
+                    self.match_seq(rules, &p_items[1..], &p_tail, &items[1..], &tail, binds)
+            },
+            _ => false,
+        }
+    }
+
+    fn match_pattern(&self, rules: &SyntaxRules, pattern: &GAst, i: &GAst, binds: &mut Bindings) -> bool {
+        match pattern {
+            GAst::Const(Constant::Sym(p)) if *p.0 == WILDCARD => true,
+            GAst::Const(Constant::Sym(p)) if self.is_literal(rules, p) => match i {
+                GAst::Const(Constant::Sym(x)) => self.base_name(x) == self.base_name(p),
+                _ => false,
+            },
+            GAst::Const(Constant::Sym(p)) => {
+                binds.insert(p.0.clone(), Binding::One(i.clone()));
+                true
+            },
+            GAst::Const(p) => match i {
+                GAst::Const(x) => const_eq(p, x),
+                _ => false,
+            },
+            GAst::List(p) => {
+                let (p_items, p_tail) = split_list(p);
+                match i {
+                    GAst::List(x) => {
+                        let (items, tail) = split_list(x);
+                        self.match_seq(rules, &p_items, &p_tail, &items, &tail, binds)
+                    },
+                    GAst::Const(Constant::Nil) => p_items.is_empty() && p_tail.is_none(),
+                    _ => false,
+                }
+            },
+        }
+    }
+
+    fn match_seq(
+        &self,
+        rules: &SyntaxRules,
+        p_items: &[GAst],
+        p_tail: &Option<GAst>,
+        items: &[GAst],
+        tail: &Option<GAst>,
+        binds: &mut Bindings
+    ) -> bool {
+        let ellipsis = p_items.iter().position(is_ellipsis);
+        let (before, repeat, after) = match ellipsis {
+            Some(e) if e > 0 => (&p_items[..e - 1], Some(&p_items[e - 1]), &p_items[e + 1..]),
+            Some(_) => return false,
+            None => (p_items, None, &p_items[p_items.len()..]),
+        };
+        let min = before.len() + after.len();
+        if items.len() < min || (repeat.is_none() && p_tail.is_none() && items.len() != min) {
+            return false;
+        }
+        if p_tail.is_none() && tail.is_some() {
+            return false;
+        }
+        let matched = before.iter().zip(items.iter())
+            .all(|(p, x)| self.match_pattern(rules, p, x, binds));
+        if !matched {
+            return false;
+        }
+        let rest = &items[before.len()..];
+        let rest = if let Some(repeat) = repeat {
+            let count = rest.len() - after.len();
+            let mut matches = vec![];
+            for x in rest[..count].iter() {
+                let mut b = HashMap::new();
+                if !self.match_pattern(rules, repeat, x, &mut b) {
+                    return false;
+                }
+                matches.push(b);
+            }
+            for var in self.pattern_vars(rules, repeat) {
+                let r = matches.iter_mut()
+                    .map(|b| b.remove(&var).unwrap())
+                    .collect();
+                binds.insert(var, Binding::Many(r));
+            }
+            &rest[count..]
+        } else {
+            rest
+        };
+        let matched = after.iter().zip(rest.iter())
+            .all(|(p, x)| self.match_pattern(rules, p, x, binds));
+        if !matched {
+            return false;
+        }
+        match p_tail {
+            Some(p) => {
+                let rest = make_list(rest[after.len().min(rest.len())..].to_vec(), tail.clone());
+                self.match_pattern(rules, p, &rest, binds)
+            },
+            None => true,
+        }
+    }
+
+    fn pattern_vars(&self, rules: &SyntaxRules, pattern: &GAst) -> Vec<Handle<String>> {
+        match pattern {
+            GAst::Const(Constant::Sym(p)) => {
+                if *p.0 == WILDCARD || *p.0 == ELLIPSIS || self.is_literal(rules, p) {
+                    vec![]
+                } else {
+                    vec![p.0.clone()]
+                }
+            },
+            GAst::Const(_) => vec![],
+            GAst::List(p) => p.0.iter()
+                .chain(p.1.iter())
+                .flat_map(|x| self.pattern_vars(rules, x))
+                .collect(),
+        }
+    }
+
+    fn is_literal(&self, rules: &SyntaxRules, i: &Handle<Symbol>) -> bool {
+        rules.literals.iter().any(|x| x.0 == i.0)
+    }
+
+    fn instantiate(
+        &mut self,
+        template: &GAst,
+        binds: &Bindings,
+        mark: usize,
+        escaped: bool
+    ) -> Result<GAst, String> {
+        match template {
+            GAst::Const(Constant::Sym(x)) => match binds.get(&x.0) {
+                Some(Binding::One(v)) => Ok(v.clone()),
+                Some(Binding::Many(_)) => Err(format!("pattern variable {} is used without ellipsis", x)),
+                None => Ok(GAst::Const(Constant::Sym(self.alias(x, mark)))),
+            },
+            GAst::Const(_) => Ok(template.clone()),
+            GAst::List(x) => {
+                let (items, tail) = split_list(x);
+                // (... template) escapes the ellipsis
+                if !escaped && items.len() == 2 && tail.is_none() && is_ellipsis(&items[0]) {
+                    return self.instantiate(&items[1], binds, mark, true);
+                }
+                let mut r = vec![];
+                let mut iter = items.iter().peekable();
+                while let Some(item) = iter.next() {
+                    let mut depth = 0;
+                    while !escaped && iter.peek().is_some_and(|x| is_ellipsis(x)) {
+                        iter.next();
+                        depth += 1;
+                    }
+                    if depth == 0 {
+                        r.push(self.instantiate(item, binds, mark, escaped)?);
+                    } else {
+                        self.instantiate_repeat(item, binds, mark, depth, &mut r)?;
+                    }
+                }
+                let tail = match tail {
+                    Some(x) => Some(self.instantiate(&x, binds, mark, escaped)?),
+                    None => None,
+                };
+                Ok(make_list(r, tail))
+            },
+        }
+    }
+
+    fn instantiate_repeat(
+        &mut self,
+        template: &GAst,
+        binds: &Bindings,
+        mark: usize,
+        depth: usize,
+        r: &mut Vec<GAst>
+    ) -> Result<(), String> {
+        let vars: Vec<_> = template_vars(template)
+            .into_iter()
+            .filter(|x| matches!(binds.get(x), Some(Binding::Many(_))))
+            .collect();
+        if vars.is_empty() {
+            return Err(format!("no pattern variable to repeat in {}", template));
+        }
+        let len = |x: &Handle<String>| match binds.get(x) {
+            Some(Binding::Many(x)) => x.len(),
+            _ => unreachable!(),
+        };
+        let count = len(&vars[0]);
+        if vars.iter().any(|x| len(x) != count) {
+            return Err(format!("pattern variables repeat different times in {}", template));
+        }
+        for n in 0..count {
+            let mut binds = binds.clone();
+            for var in vars.iter() {
+                if let Some(Binding::Many(x)) = binds.get(var) {
+                    let v = x[n].clone();
+                    binds.insert(var.clone(), v);
+                }
+            }
+            if depth > 1 {
+                self.instantiate_repeat(template, &binds, mark, depth - 1, r)?;
+            } else {
+                r.push(self.instantiate(template, &binds, mark, false)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn alias(&mut self, i: &Handle<Symbol>, mark: usize) -> Handle<Symbol> {
+        let name = Handle::new(format!("{}#{}", i.0, mark));
+        self.aliases.entry(name.clone()).or_insert_with(|| (i.clone(), mark));
+        Handle::new(Symbol(name, i.1.clone()))
+    }
+
+    fn base_name(&self, i: &Handle<Symbol>) -> Handle<String> {
+        let mut name = i.0.clone();
+        while let Some((x, _)) = self.aliases.get(&name) {
+            name = x.0.clone();
+        }
+        name
+    }
+
+    fn is_form(&self, i: &GAst, name: &str) -> bool {
+        match i {
+            GAst::List(x) => match x.0.first() {
+                Some(GAst::Const(Constant::Sym(head))) => *self.base_name(head) == name,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Keeps the aliases bound inside the expansion and turns the free ones back into their original symbol.
+    fn resolve(&mut self, i: &GAst) -> GAst {
+        let resolver = Resolver {
+            expander: self,
+            captured: RefCell::default(),
+            renamed: RefCell::default(),
+            counter: Cell::new(self.counter),
+        };
+        let mut r = resolver.resolve(i, &Lexical::default());
+        // the first pass finds the user binders to rename
+        if !resolver.captured.borrow().is_empty() {
+            r = resolver.resolve(i, &Lexical::default());
+        }
+        self.counter = resolver.counter.get();
+        r
+    }
+
+    /// Turns every alias in quoted data back into its original symbol.
+    fn strip(&self, i: &GAst) -> GAst {
+        match i {
+            GAst::Const(Constant::Sym(x)) => GAst::Const(Constant::Sym(self.original(x))),
+            GAst::Const(_) => i.clone(),
+            GAst::List(x) => make_list(
+                x.0.iter().map(|x| self.strip(x)).collect(),
+                x.1.as_ref().map(|x| self.strip(x))),
+        }
+    }
+
+    fn original(&self, i: &Handle<Symbol>) -> Handle<Symbol> {
+        let mut i = i.clone();
+        while let Some((x, _)) = self.aliases.get(&i.0) {
+            i = x.clone();
+        }
+        i
+    }
+
+    /// The name bound by an internal `define`.
+    fn defined_symbol(&self, i: &GAst) -> Option<Handle<Symbol>> {
+        if !self.is_form(i, "define") {
+            return None;
+        }
+        match i.get_list()?.0.get(1)? {
+            GAst::Const(Constant::Sym(x)) => Some(x.clone()),
+            GAst::List(x) => match x.0.first()? {
+                GAst::Const(Constant::Sym(x)) => Some(x.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// What `Resolver` knows of the scope of a form.
+#[derive(Debug, Clone, Default)]
+struct Lexical {
+    /// the aliases bound inside the expansion
+    bound: HashSet<Handle<String>>,
+    /// the user binders, the innermost last
+    locals: Vec<Handle<Symbol>>,
+}
+
+struct Resolver<'a> {
+    expander: &'a Expander,
+    /// the user binders that a free symbol of a template would see, by address
+    captured: RefCell<HashSet<usize>>,
+    /// the new names of the captured binders, by address
+    renamed: RefCell<HashMap<usize, Handle<Symbol>>>,
+    counter: Cell<usize>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&self, i: &GAst, scope: &Lexical) -> GAst {
+        let list = match i {
+            GAst::Const(Constant::Sym(x)) => return GAst::Const(Constant::Sym(self.resolve_symbol(x, scope))),
+            GAst::Const(_) => return i.clone(),
+            GAst::List(x) => x,
+        };
+        let head = match list.0.first() {
+            Some(GAst::Const(Constant::Sym(x))) => self.expander.base_name(x).to_string(),
+            _ => String::new(),
+        };
+        let items = &list.0;
+        let resolve_all = |x: &[GAst], scope: &Lexical| -> Vec<GAst> {
+            x.iter().map(|x| self.resolve(x, scope)).collect()
+        };
+        let mut inner = scope.clone();
+        let mut r = vec![];
+        match (head.as_str(), items.get(1)) {
+            ("quote", _) => return self.expander.strip(i),
+            ("quasiquote", Some(x)) if items.len() == 2 && list.1.is_none() => {
+                let head = self.expander.strip(&items[0]);
+                return make_list(vec![head, self.resolve_quasiquote(x, 1, scope)], None);
+            },
+            ("lambda", Some(params)) => {
+                self.bind_all(&mut inner, params);
+                self.bind_defined(&mut inner, &items[2..]);
+                r.extend(resolve_all(&items[..2], &inner));
+            },
+            ("define", Some(GAst::List(sign))) => {
+                for x in sign.0.iter().skip(1).chain(sign.1.iter()) {
+                    self.bind_all(&mut inner, x);
+                }
+                self.bind_defined(&mut inner, &items[2..]);
+                let name = resolve_all(&sign.0[..1.min(sign.0.len())], scope);
+                let params = resolve_all(&sign.0[1.min(sign.0.len())..], &inner);
+                let tail = sign.1.as_ref().map(|x| self.resolve(x, &inner));
+                r.push(self.resolve(&items[0], scope));
+                r.push(make_list(name.into_iter().chain(params).collect(), tail));
+            },
+            ("let", Some(GAst::List(binds))) => {
+                for x in binds.0.iter() {
+                    if let Some(x) = x.get_list().and_then(|x| x.0.first().cloned()) {
+                        self.bind_all(&mut inner, &x);
+                    }
+                }
+                self.bind_defined(&mut inner, &items[2..]);
+                let r_binds = binds.0.iter().map(|x| match x {
+                    GAst::List(x) if !x.0.is_empty() => {
+                        let name = self.resolve(&x.0[0], &inner);
+                        let value = resolve_all(&x.0[1..], scope);
+                        make_list(Some(name).into_iter().chain(value).collect(), x.1.clone())
+                    },
+                    _ => self.resolve(x, scope),
+                }).collect();
+                r.push(self.resolve(&items[0], scope));
+                r.push(make_list(r_binds, binds.1.clone()));
+            },
+            // the variable of guard is only bound in its clauses
+            ("guard", Some(GAst::List(spec))) => {
+                let mut clauses = scope.clone();
+                if let Some(x) = spec.0.first() {
+                    self.bind_all(&mut clauses, x);
+                }
+                r.push(self.resolve(&items[0], scope));
+                r.push(make_list(resolve_all(&spec.0, &clauses), spec.1.clone()));
+            },
+            ("try", _) => {
+                r.push(self.resolve(&items[0], scope));
+                for x in &items[1..] {
+                    match x {
+                        GAst::List(c) if self.expander.is_form(x, "catch") && c.0.len() > 1 => {
+                            let mut handler = scope.clone();
+                            self.bind_all(&mut handler, &c.0[1]);
+                            let head = self.resolve(&c.0[0], scope);
+                            let rest = resolve_all(&c.0[1..], &handler);
+                            r.push(make_list(Some(head).into_iter().chain(rest).collect(), c.1.clone()));
+                        },
+                        _ => r.push(self.resolve(x, scope)),
+                    }
+                }
+            },
+            _ => r.extend(resolve_all(&items[..1.min(items.len())], scope)),
+        }
+        r.extend(resolve_all(&items[r.len()..], &inner));
+        let tail = list.1.as_ref().map(|x| self.resolve(x, &inner));
+        make_list(r, tail)
+    }
+
+    /// Quasiquoted data is stripped like quoted data, except what is unquoted at depth 1.
+    fn resolve_quasiquote(&self, i: &GAst, depth: usize, scope: &Lexical) -> GAst {
+        let list = if let GAst::List(x) = i {
+            x
+        } else {
+            return self.expander.strip(i);
+        };
+        let (items, tail) = split_list(list);
+        if let (Some(depth), None) = (self.expander.quasiquote_depth(&items, depth), &tail) {
+            let r = if depth == 0 {
+                self.resolve(&items[1], scope)
+            } else {
+                self.resolve_quasiquote(&items[1], depth, scope)
+            };
+            return make_list(vec![self.expander.strip(&items[0]), r], None);
+        }
+        let split = self.expander.unquoted_tail(&items, &tail);
+        let r = items[..split].iter().map(|x| self.resolve_quasiquote(x, depth, scope)).collect();
+        let tail = if split < items.len() {
+            Some(self.resolve_quasiquote(&make_list(items[split..].to_vec(), None), depth, scope))
+        } else {
+            tail.map(|x| self.resolve_quasiquote(&x, depth, scope))
+        };
+        make_list(r, tail)
+    }
+
+    fn bind_all(&self, scope: &mut Lexical, i: &GAst) {
+        for x in template_symbols(i) {
+            self.bind(scope, x);
+        }
+    }
+
+    /// Internal `define`s bind their names in the whole body.
+    fn bind_defined(&self, scope: &mut Lexical, body: &[GAst]) {
+        for x in body.iter().filter_map(|x| self.expander.defined_symbol(x)) {
+            self.bind(scope, x);
+        }
+    }
+
+    fn bind(&self, scope: &mut Lexical, i: Handle<Symbol>) {
+        if self.expander.aliases.contains_key(&i.0) {
+            scope.bound.insert(i.0.clone());
+            return;
+        }
+        if self.captured.borrow().contains(&address(&i)) {
+            self.renamed.borrow_mut().entry(address(&i)).or_insert_with(|| {
+                self.counter.set(self.counter.get() + 1);
+                let name = format!("{}#{}", i.0, self.counter.get());
+                Handle::new(Symbol(Handle::new(name), i.1.clone()))
+            });
+        }
+        scope.locals.push(i);
+    }
+
+    fn resolve_symbol(&self, i: &Handle<Symbol>, scope: &Lexical) -> Handle<Symbol> {
+        let mut i = i.clone();
+        loop {
+            if scope.bound.contains(&i.0) {
+                return i;
+            }
+            match self.expander.aliases.get(&i.0) {
+                Some((x, _)) if self.expander.aliases.contains_key(&x.0) => i = x.clone(),
+                Some((x, mark)) => return self.free_symbol(x, *mark, scope),
+                None => break,
+            }
+        }
+        match scope.locals.iter().rev().find(|x| x.0 == i.0) {
+            Some(x) => self.local_name(x, &i),
+            None => i,
+        }
+    }
+
+    /// A free symbol of a template skips the user binders that are not in scope where its macro is defined.
+    fn free_symbol(&self, i: &Handle<Symbol>, mark: usize, scope: &Lexical) -> Handle<Symbol> {
+        let visible = self.expander.definitions.get(&mark);
+        for x in scope.locals.iter().rev().filter(|x| x.0 == i.0) {
+            if visible.is_some_and(|v| v.contains(&address(x))) {
+                return self.local_name(x, i);
+            }
+            self.captured.borrow_mut().insert(address(x));
+        }
+        i.clone()
+    }
+
+    /// `i` refers to the binder `local`, which may be renamed.
+    fn local_name(&self, local: &Handle<Symbol>, i: &Handle<Symbol>) -> Handle<Symbol> {
+        match self.renamed.borrow().get(&address(local)) {
+            Some(x) => Handle::new(Symbol(x.0.clone(), i.1.clone())),
+            None => i.clone(),
+        }
+    }
+}
+
+fn is_ellipsis(i: &GAst) -> bool {
+    matches!(i, GAst::Const(Constant::Sym(x)) if *x.0 == ELLIPSIS)
+}
+
+fn template_symbols(i: &GAst) -> Vec<Handle<Symbol>> {
+    match i {
+        GAst::Const(Constant::Sym(x)) => vec![x.clone()],
+        GAst::Const(_) => vec![],
+        GAst::List(x) => x.0.iter().chain(x.1.iter()).flat_map(template_symbols).collect(),
+    }
+}
+
+fn address(i: &Handle<Symbol>) -> usize {
+    Handle::as_ptr(i) as usize
+}
+
+fn template_vars(i: &GAst) -> Vec<Handle<String>> {
+    match i {
+        GAst::Const(Constant::Sym(x)) => vec![x.0.clone()],
+        GAst::Const(_) => vec![],
+        GAst::List(x) => x.0.iter().chain(x.1.iter()).flat_map(template_vars).collect(),
+    }
+}
+
+/// Flattens a dotted tail that is itself a list, `(a . (b c))` is `(a b c)`.
+fn split_list(i: &List) -> (Vec<GAst>, Option<GAst>) {
+    let mut items = i.0.clone();
+    let mut tail = i.1.clone();
+    while let Some(GAst::List(x)) = tail.clone() {
+        items.extend(x.0.iter().cloned());
+        tail = x.1.clone();
+    }
+    if let Some(GAst::Const(Constant::Nil)) = tail {
+        tail = None;
+    }
+    (items, tail)
+}
+
+fn make_list(items: Vec<GAst>, tail: Option<GAst>) -> GAst {
+    let (items, tail) = split_list(&List(items, tail));
+    GAst::List(Handle::new(List(items, tail)))
+}
+
+fn const_eq(a: &Constant, b: &Constant) -> bool {
+    match (a, b) {
+        (Constant::Nil, Constant::Nil) => true,
+        (Constant::Bool(a), Constant::Bool(b)) => a == b,
+        (Constant::Char(a), Constant::Char(b)) => a == b,
+        (Constant::Int(a), Constant::Int(b)) => a == b,
+        (Constant::Uint(a), Constant::Uint(b)) => a == b,
+        (Constant::Float(a), Constant::Float(b)) => a == b,
+        (Constant::Str(a), Constant::Str(b)) => a == b,
+        (Constant::Sym(a), Constant::Sym(b)) => a.0 == b.0,
+        _ => false,
+    }
+}
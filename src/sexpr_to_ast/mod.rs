@@ -1,15 +1,18 @@
 pub mod quote;
+pub mod macros;
 mod function;
 mod top_level;
 mod call;
 mod cond;
 mod bind;
+mod guard;
 
 use sexpr_ir::gast::{constant::Constant, list::List, symbol::Symbol, GAst, Handle};
 
-use crate::{ast::{Cond, Expr, Function, Let, Set}, error::CompilerError, value::Value};
+use crate::{ast::{Call, Cond, Expr, Function, Let, Set}, error::{CompilerError, bad_syntax, is_not_symbol, locate_in}, value::Value};
+use crate::value::callable::{Callable, NativeClosure, NativeFunction};
 
-use self::{call::call_process, quote::quote_from_sexpr};
+use self::{call::call_process, guard::{guard_from_sexpr, try_from_sexpr}, quote::{quasiquote_from_sexpr, quote_from_sexpr}};
 
 
 pub trait FromSexpr<I, T> {
@@ -21,7 +24,7 @@ impl FromSexpr<GAst, Expr> for Expr {
     fn from_sexpr(i: &GAst) -> Result<Expr, Vec<CompilerError>> {
         match i {
             GAst::Const(x) => Value::from_sexpr(x),
-            GAst::List(x) => expr_list_process(x),
+            GAst::List(x) => expr_list_process(x).map_err(|e| locate_in(e, x)),
         }
     }
 }
@@ -29,7 +32,7 @@ impl FromSexpr<GAst, Expr> for Expr {
 fn expr_list_process(i: &List) -> Result<Expr, Vec<CompilerError>> {
     // let mut error_buffer = vec![];
     if i.0.is_empty() {
-        return Err(vec![CompilerError::BadSyntax(i.to_string())]);
+        return Err(vec![bad_syntax(i)]);
     }
     match i.0.get(0).unwrap() {
         GAst::Const(Constant::Sym(n)) if *n.0 == "let" =>
@@ -41,6 +44,9 @@ fn expr_list_process(i: &List) -> Result<Expr, Vec<CompilerError>> {
         GAst::Const(Constant::Sym(n)) if *n.0 == "lambda" =>
             Function::from_sexpr(i).map(|f| Expr::Lambda(Handle::new(f))),
         GAst::Const(Constant::Sym(n)) if *n.0 == "quote" => quote_from_sexpr(i).map(Expr::Value),
+        GAst::Const(Constant::Sym(n)) if *n.0 == "quasiquote" => quasiquote_from_sexpr(i),
+        GAst::Const(Constant::Sym(n)) if *n.0 == "guard" => guard_from_sexpr(i),
+        GAst::Const(Constant::Sym(n)) if *n.0 == "try" => try_from_sexpr(i),
         _ => call_process(i).map(|x| Expr::FunctionCall(Handle::new(x))),
     }
 }
@@ -60,6 +66,10 @@ impl FromSexpr<Constant, Expr> for Value {
             return Ok(Expr::Value(Value::Nil));
         }
         if let Constant::Sym(x) = i {
+            // numbers the reader does not know, bigints and rationals
+            if let Some(r) = Value::parse_exact(&x.0) {
+                return Ok(Expr::Value(r));
+            }
             return Ok(Expr::Variable(x.clone()));
         }
         ImplCastItem!(i, Bool);
@@ -74,7 +84,27 @@ impl FromSexpr<Constant, Expr> for Value {
 
 fn symbol_from_sexpr(i: &GAst) -> Result<Handle<Symbol>, CompilerError> {
     i.get_const()
-        .ok_or_else(|| CompilerError::IsNotSymbol(i.to_string()))?
+        .ok_or_else(|| is_not_symbol(i))?
         .get_sym()
-        .ok_or_else(|| CompilerError::IsNotSymbol(i.to_string()))
-}
\ No newline at end of file
+        .ok_or_else(|| is_not_symbol(i))
+}
+
+/// A native of the prelude, for the code that special forms expand to.
+/// The expression holds the native itself, a binding of the same name can not shadow it
+/// and it is there without the builtins.
+pub(crate) fn builtin(name: &'static str, interface: NativeClosure) -> Expr {
+    let native = NativeFunction {
+        name,
+        from_module: "<builtin>",
+        is_pure: true,
+        interface,
+    };
+    Expr::Value(Value::Callable(Callable::Native(native)))
+}
+
+/// A call to a native of the prelude, see `builtin`.
+pub(crate) fn call_builtin(name: &'static str, interface: NativeClosure, args: Vec<Expr>) -> Expr {
+    let mut r = vec![builtin(name, interface)];
+    r.extend(args);
+    Expr::FunctionCall(Handle::new(Call(r)))
+}
@@ -1,27 +1,130 @@
-use sexpr_ir::gast::{GAst, Handle, constant::Constant, list::List};
+use sexpr_ir::gast::{GAst, Handle, constant::Constant, list::List, symbol::Symbol};
 
-use crate::{ast::Expr, error::CompilerError, sexpr_to_ast::FromSexpr, value::{Pair, Value}};
+use std::sync::Arc;
+
+use crate::{ast::Expr, error::{CompilerError, bad_syntax}, sexpr_to_ast::{FromSexpr, call_builtin}, value::{Pair, Value}};
+use crate::prelude::{native_list_operator::append, raw_operator::cons};
 
 
 
 
 pub fn quote_from_sexpr(i: &List) -> Result<Value, Vec<CompilerError>> {
+    if i.1.is_some() || i.0.len() != 2 {
+        return Err(vec![bad_syntax(i)]);
+    }
+    Ok(value_from_sexpr(i.0.get(1).unwrap()))
+}
+
+pub fn quasiquote_from_sexpr(i: &List) -> Result<Expr, Vec<CompilerError>> {
+    if i.1.is_some() || i.0.len() != 2 {
+        return Err(vec![bad_syntax(i)]);
+    }
+    template_from_sexpr(i.0.get(1).unwrap(), 1)
+}
+
+/// Returns the operand of `(name x)`.
+fn special_form<'a>(i: &'a GAst, name: &str) -> Option<&'a GAst> {
+    let i = if let GAst::List(x) = i {
+        x
+    } else {
+        return None;
+    };
+    match i.0.first() {
+        Some(GAst::Const(Constant::Sym(x))) if *x.0 == name => {},
+        _ => return None,
+    }
+    if i.1.is_none() && i.0.len() == 2 {
+        i.0.get(1)
+    } else {
+        None
+    }
+}
+
+/// `depth` counts the quasiquotes enclosing `i`,
+/// only unquotes at depth 1 are evaluated, the deeper ones are kept as data.
+fn template_from_sexpr(i: &GAst, depth: usize) -> Result<Expr, Vec<CompilerError>> {
+    let list = if let GAst::List(x) = i {
+        x
+    } else {
+        return Ok(Expr::Value(value_from_sexpr(i)));
+    };
+    if let Some(x) = special_form(i, "unquote") {
+        return if depth == 1 {
+            Expr::from_sexpr(x)
+        } else {
+            nested_template("unquote", x, depth - 1)
+        };
+    }
+    if let Some(x) = special_form(i, "unquote-splicing") {
+        return if depth == 1 {
+            Err(vec![bad_syntax(i)])
+        } else {
+            nested_template("unquote-splicing", x, depth - 1)
+        };
+    }
+    if let Some(x) = special_form(i, "quasiquote") {
+        return nested_template("quasiquote", x, depth + 1);
+    }
+
+    let (items, pair_right) = split_tail(list);
     let mut error_buffer = vec![];
-    if i.1.is_some() {
-        error_buffer.push(CompilerError::BadSyntax(i.to_string()));
+    let mut r = match &pair_right {
+        Some(x) => template_from_sexpr(x, depth).unwrap_or_else(|mut e| {
+            error_buffer.append(&mut e);
+            Expr::Value(Value::Nil)
+        }),
+        None => Expr::Value(Value::Nil),
+    };
+    for item in items.iter().rev() {
+        let spliced = if depth == 1 {
+            special_form(item, "unquote-splicing")
+        } else {
+            None
+        };
+        let item = match spliced {
+            Some(x) => Expr::from_sexpr(x),
+            None => template_from_sexpr(item, depth),
+        };
+        match item {
+            Ok(x) if spliced.is_some() => r = call_builtin("append", Arc::new(|_, args| append(args)), vec![x, r]),
+            Ok(x) => r = cons_expr(x, r),
+            Err(mut e) => error_buffer.append(&mut e),
+        }
     }
-    if i.0.len() != 2 {
-        error_buffer.push(CompilerError::BadSyntax(i.to_string()));
-        return Err(error_buffer);
+    if error_buffer.is_empty() {
+        Ok(r)
+    } else {
+        Err(error_buffer)
     }
+}
+
+fn nested_template(name: &str, i: &GAst, depth: usize) -> Result<Expr, Vec<CompilerError>> {
+    let name = Value::Sym(Handle::new(Symbol::new(name)));
+    let r = template_from_sexpr(i, depth)?;
+    Ok(cons_expr(Expr::Value(name), cons_expr(r, Expr::Value(Value::Nil))))
+}
 
-    if let GAst::Const(Constant::Sym(x)) = i.0.get(0).unwrap() {
-        if *x.0 == "quote" {
-            error_buffer.push(CompilerError::BadSyntax(x.to_string()));
+/// `(a . ,x)` is read as `(a unquote x)`, the tail `,x` is split back from the items.
+fn split_tail(i: &List) -> (&[GAst], Option<GAst>) {
+    let List(items, pair_right) = i;
+    if pair_right.is_none() && items.len() > 2 {
+        let (items, tail) = items.split_at(items.len() - 2);
+        let keyword = ["unquote", "unquote-splicing", "quasiquote"]
+            .iter()
+            .any(|x| matches!(&tail[0], GAst::Const(Constant::Sym(s)) if *s.0 == *x));
+        if keyword {
+            return (items, Some(GAst::List(Handle::new(List(tail.to_vec(), None)))));
         }
     }
-    let r = value_from_sexpr(i.0.get(1).unwrap());
-    Ok(r)
+    (items, pair_right.clone())
+}
+
+/// Parts of a template without unquotes are folded back into constants.
+fn cons_expr(car: Expr, cdr: Expr) -> Expr {
+    match (car, cdr) {
+        (Expr::Value(car), Expr::Value(cdr)) => Expr::Value(Value::Pair(Handle::new(Pair(car, cdr)))),
+        (car, cdr) => call_builtin("cons", Arc::new(|_, args| cons(args)), vec![car, cdr]),
+    }
 }
 
 pub fn value_from_sexpr(i: &GAst) -> Value {
@@ -54,3 +157,37 @@ pub fn list_from_sexpr(i: &List) -> Value {
         right
     }
 }
+
+/// The inverse of `value_from_sexpr`, values without a literal form give `None`.
+pub fn sexpr_from_value(i: &Value) -> Option<GAst> {
+    let r = match i {
+        Value::Nil => Constant::Nil,
+        Value::Bool(x) => Constant::Bool(*x),
+        Value::Char(x) => Constant::Char(*x),
+        Value::Uint(x) => Constant::Uint(*x),
+        Value::Int(x) => Constant::Int(*x),
+        Value::Float(x) => Constant::Float(*x),
+        // read back by `Value::parse_exact`
+        Value::BigInt(_) | Value::Rational(_) => Constant::Sym(Handle::new(Symbol::new(&i.to_string()))),
+        Value::Str(x) => Constant::Str(x.clone()),
+        Value::Sym(x) => Constant::Sym(x.clone()),
+        Value::Pair(_) => return sexpr_list_from_value(i),
+        _ => return None,
+    };
+    Some(GAst::Const(r))
+}
+
+fn sexpr_list_from_value(i: &Value) -> Option<GAst> {
+    let mut items = vec![];
+    let mut this = i;
+    while let Value::Pair(x) = this {
+        items.push(sexpr_from_value(&x.0)?);
+        this = &x.1;
+    }
+    let right = if this.is_nil() {
+        None
+    } else {
+        Some(sexpr_from_value(this)?)
+    };
+    Some(GAst::List(Handle::new(List(items, right))))
+}
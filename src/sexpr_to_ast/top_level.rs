@@ -1,6 +1,6 @@
-use sexpr_ir::gast::{constant::Constant, list::List, GAst, Handle};
+use sexpr_ir::gast::{constant::Constant, list::List, symbol::Symbol, GAst, Handle};
 
-use crate::{ast::{Expr, Function, TopLevel, ModuleTop, Import}, error::{CompilerError, incomplete_expr, invalid_expr_type}, sexpr_to_ast::symbol_from_sexpr, value::Value};
+use crate::{ast::{Expr, Export, Function, Import, ImportSet, ModuleTop, TopLevel}, error::{CompilerError, bad_syntax, incomplete_expr, invalid_expr_length, invalid_expr_type, invalid_list_tail, locate_in, ExprType}, sexpr_to_ast::symbol_from_sexpr, value::Value};
 
 use super::{FromSexpr, quote_from_sexpr};
 
@@ -9,18 +9,20 @@ impl FromSexpr<GAst, ModuleTop> for ModuleTop {
     fn from_sexpr(i: &GAst) -> Result<ModuleTop, Vec<CompilerError>> {
         match i {
             GAst::Const(x) => Value::from_sexpr(x).map(TopLevel::Expr).map(ModuleTop::TopLevel),
-            GAst::List(x) => module_top_list_process(x),
+            GAst::List(x) => module_top_list_process(x).map_err(|e| locate_in(e, x)),
         }
     }
 }
 
 fn module_top_list_process(i: &List) -> Result<ModuleTop, Vec<CompilerError>> {
     if i.0.is_empty() {
-        return Err(vec![CompilerError::BadSyntax(i.to_string())]);
+        return Err(vec![bad_syntax(i)]);
     }
     match i.0.get(0).unwrap() {
         GAst::Const(Constant::Sym(n)) if *n.0 == "import" =>
             Import::from_sexpr(i).map(ModuleTop::Import),
+        GAst::Const(Constant::Sym(n)) if *n.0 == "export" =>
+            Export::from_sexpr(i).map(ModuleTop::Export),
         _ => TopLevel::from_sexpr(&GAst::List(Handle::new(i.clone()))).map(ModuleTop::TopLevel),
     }
 }
@@ -28,23 +30,111 @@ fn module_top_list_process(i: &List) -> Result<ModuleTop, Vec<CompilerError>> {
 impl FromSexpr<List, Import> for Import {
     fn from_sexpr(i: &List) -> Result<Import, Vec<CompilerError>> {
         let mut error_buffer = vec![];
+
+        // check is not tail
+        if i.1.is_some() {
+            error_buffer.push(invalid_list_tail(&*i));
+        }
+
         let mut iter = i.0.iter();
 
         let _label = iter.next();
 
-        let name = iter.next();
-        if name.is_none() {
+        let sets = iter
+            .map(ImportSet::from_sexpr)
+            .fold(vec![], |mut prev, i| {
+                match i {
+                    Ok(v) => prev.push(v),
+                    Err(mut e) => error_buffer.append(&mut e),
+                }
+                prev
+            });
+
+        if sets.is_empty() && error_buffer.is_empty() {
             error_buffer.push(incomplete_expr(&*i));
-            return Err(error_buffer);
         }
-        let name = name.unwrap();
-        let name = if let GAst::Const(Constant::Sym(l)) = name {
-            l.clone()
+
+        if error_buffer.is_empty() {
+            Ok(Import(sets))
         } else {
-            error_buffer.push(invalid_expr_type(&*i, ()));
-            return Err(error_buffer);
+            Err(error_buffer)
+        }
+    }
+}
+
+impl FromSexpr<GAst, ImportSet> for ImportSet {
+    fn from_sexpr(i: &GAst) -> Result<ImportSet, Vec<CompilerError>> {
+        let i = match i {
+            GAst::Const(Constant::Sym(name)) => return Ok(ImportSet::Module(name.clone())),
+            GAst::Const(_) => return Err(vec![invalid_expr_type(i, ExprType::SymbolOrList)]),
+            GAst::List(i) => i,
         };
-        Ok(Import(name))
+        if i.1.is_some() {
+            return Err(vec![invalid_list_tail(&**i)]);
+        }
+        if i.0.len() < 2 {
+            return Err(vec![incomplete_expr(&**i)]);
+        }
+        let label = symbol_from_sexpr(&i.0[0]).map_err(|e| vec![e])?;
+        let set = Box::new(ImportSet::from_sexpr(&i.0[1])?);
+        let rest = &i.0[2..];
+        match label.0.as_str() {
+            "only" => symbols_from_sexpr(rest).map(|names| ImportSet::Only(set, names)),
+            "except" => symbols_from_sexpr(rest).map(|names| ImportSet::Except(set, names)),
+            "prefix" => if rest.len() == 1 {
+                symbol_from_sexpr(&rest[0])
+                    .map(|prefix| ImportSet::Prefix(set, prefix))
+                    .map_err(|e| vec![e])
+            } else {
+                Err(vec![invalid_expr_length(&**i, 3, i.0.len())])
+            },
+            "rename" => rest
+                .iter()
+                .map(rename_pair_from_sexpr)
+                .collect::<Result<Vec<_>, _>>()
+                .map(|pairs| ImportSet::Rename(set, pairs)),
+            _ => Err(vec![bad_syntax(&**i)]),
+        }
+    }
+}
+
+fn rename_pair_from_sexpr(i: &GAst) -> Result<(Handle<Symbol>, Handle<Symbol>), Vec<CompilerError>> {
+    let pair = i.get_list().ok_or_else(|| vec![invalid_expr_type(i, ExprType::List)])?;
+    if pair.1.is_some() {
+        return Err(vec![invalid_list_tail(&*pair)]);
+    }
+    if pair.0.len() != 2 {
+        return Err(vec![invalid_expr_length(&*pair, 2, pair.0.len())]);
+    }
+    let from = symbol_from_sexpr(&pair.0[0]).map_err(|e| vec![e])?;
+    let to = symbol_from_sexpr(&pair.0[1]).map_err(|e| vec![e])?;
+    Ok((from, to))
+}
+
+fn symbols_from_sexpr(i: &[GAst]) -> Result<Vec<Handle<Symbol>>, Vec<CompilerError>> {
+    let mut error_buffer = vec![];
+    let r = i.iter()
+        .map(symbol_from_sexpr)
+        .fold(vec![], |mut prev, i| {
+            match i {
+                Ok(v) => prev.push(v),
+                Err(e) => error_buffer.push(e),
+            }
+            prev
+        });
+    if error_buffer.is_empty() {
+        Ok(r)
+    } else {
+        Err(error_buffer)
+    }
+}
+
+impl FromSexpr<List, Export> for Export {
+    fn from_sexpr(i: &List) -> Result<Export, Vec<CompilerError>> {
+        if i.1.is_some() {
+            return Err(vec![invalid_list_tail(&*i)]);
+        }
+        symbols_from_sexpr(&i.0[1..]).map(Export)
     }
 }
 
@@ -54,14 +144,14 @@ impl FromSexpr<GAst, TopLevel> for TopLevel {
     fn from_sexpr(i: &GAst) -> Result<TopLevel, Vec<CompilerError>> {
         match i {
             GAst::Const(x) => Value::from_sexpr(x).map(TopLevel::Expr),
-            GAst::List(x) => top_level_list_process(x),
+            GAst::List(x) => top_level_list_process(x).map_err(|e| locate_in(e, x)),
         }
     }
 }
 
 fn top_level_list_process(i: &List) -> Result<TopLevel, Vec<CompilerError>> {
     if i.0.is_empty() {
-        return Err(vec![CompilerError::BadSyntax(i.to_string())]);
+        return Err(vec![bad_syntax(i)]);
     }
     match i.0.get(0).unwrap() {
         /*
@@ -84,10 +174,10 @@ fn top_level_list_process(i: &List) -> Result<TopLevel, Vec<CompilerError>> {
 fn define_from_sexpr(x: &List) -> Result<TopLevel, Vec<CompilerError>> {
     let mut error_buffer = vec![];
     if x.1.is_some() {
-        error_buffer.push(CompilerError::BadSyntax(x.to_string()));
+        error_buffer.push(bad_syntax(x));
     }
     if x.0.len() != 3 {
-        error_buffer.push(CompilerError::BadSyntax(x.to_string()));
+        error_buffer.push(bad_syntax(x));
         return Err(error_buffer);
     }
     let name = x.0.get(1).unwrap();
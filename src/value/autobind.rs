@@ -1,21 +1,445 @@
 use std::collections::HashMap;
-use std::convert::{Infallible, TryInto};
+use std::convert::{Infallible, TryFrom, TryInto};
+use std::hash::Hash;
+use std::sync::Arc;
 
+use num_bigint::BigInt;
+use num_rational::BigRational;
 use sexpr_ir::gast::Handle;
 use sexpr_ir::gast::symbol::{Location, Symbol};
 
-use crate::value::Value;
-use crate::value::callable::{Callable, NativeFunction, NativeInterface};
+use crate::value::{Dict, ErrorObject, Key, Pair, Value, ValueType, Vector};
+use crate::value::callable::{Callable, NativeClosure, NativeContextInterface, NativeFunction, NativeInterface};
 use crate::value::result::{CError, CResult};
 
 
+/// Conversion of an argument from `Value`.
+pub trait FromValue: Sized {
+    fn from_value(v: Value) -> Result<Self, CError>;
+
+    const OPTIONAL: bool = false;
+
+    /// The value of a parameter whose argument is left out, `None` if the argument is required.
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+/// Conversion of a return value into `Value`.
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+macro_rules! impl_from_value {
+    ($($t:ty),*) => {
+        $(
+            impl FromValue for $t {
+                fn from_value(v: Value) -> Result<Self, CError> {
+                    v.try_into()
+                }
+            }
+
+            impl IntoValue for $t {
+                fn into_value(self) -> Value {
+                    Value::from(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value!(
+    bool, char, f64, BigInt, BigRational,
+    Handle<String>, Handle<Symbol>, Handle<Pair>,
+    String, Symbol, Pair,
+    Dict, Vector, Callable, ErrorObject
+);
+
+/// Integers are taken from both `Uint` and `Int`, a value out of the range of the type is invalid.
+macro_rules! impl_from_value_int {
+    ($t:ty, $via:ty, $variant:ident) => {
+        impl FromValue for $t {
+            fn from_value(v: Value) -> Result<Self, CError> {
+                let r = match &v {
+                    Value::Uint(x) => <$t>::try_from(*x).ok(),
+                    Value::Int(x) => <$t>::try_from(*x).ok(),
+                    _ => return Err(CError::TypeError(ValueType::$variant, v)),
+                };
+                r.ok_or(CError::InvalidValue(stringify!($t), v))
+            }
+        }
+
+        impl IntoValue for $t {
+            fn into_value(self) -> Value {
+                Value::$variant(self as $via)
+            }
+        }
+    };
+}
+
+impl_from_value_int!(u64, u64, Uint);
+impl_from_value_int!(usize, u64, Uint);
+impl_from_value_int!(u32, u64, Uint);
+impl_from_value_int!(i64, i64, Int);
+impl_from_value_int!(i32, i64, Int);
+
+impl FromValue for f32 {
+    fn from_value(v: Value) -> Result<Self, CError> {
+        match v {
+            Value::Float(x) => Ok(x as f32),
+            v => Err(CError::TypeError(ValueType::Float, v)),
+        }
+    }
+}
+
+impl IntoValue for f32 {
+    fn into_value(self) -> Value {
+        Value::Float(self as f64)
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(v: Value) -> Result<Self, CError> {
+        Ok(v)
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Nil
+    }
+}
+
+/// Any value is accepted and dropped, for calls whose result is not used.
+impl FromValue for () {
+    fn from_value(_: Value) -> Result<Self, CError> {
+        Ok(())
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::Str(Handle::new(self.to_string()))
+    }
+}
+
+/// Both vectors and lists are accepted.
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(v: Value) -> Result<Self, CError> {
+        let items = match &v {
+            Value::Vec(x) => x.0.read().unwrap().clone(),
+            Value::Nil | Value::Pair(_) => {
+                let mut items = vec![];
+                let mut this = &v;
+                while let Value::Pair(pair) = this {
+                    items.push(pair.0.clone());
+                    this = &pair.1;
+                }
+                if !this.is_nil() {
+                    return Err(CError::TypeError(ValueType::List, v.clone()));
+                }
+                items
+            },
+            _ => return Err(CError::TypeError(ValueType::Vec, v)),
+        };
+        items.into_iter().map(T::from_value).collect()
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::from(self.into_iter().map(IntoValue::into_value).collect::<Vec<_>>())
+    }
+}
+
+/// `nil` is `None`.
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(v: Value) -> Result<Self, CError> {
+        match v {
+            Value::Nil => Ok(None),
+            v => T::from_value(v).map(Some),
+        }
+    }
+
+    const OPTIONAL: bool = true;
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        self.map_or(Value::Nil, IntoValue::into_value)
+    }
+}
+
+impl FromValue for Key {
+    fn from_value(v: Value) -> Result<Self, CError> {
+        Key::new(v)
+    }
+}
+
+impl IntoValue for Key {
+    fn into_value(self) -> Value {
+        Key::into_value(self)
+    }
+}
+
+/// The keys should be strings or symbols.
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(v: Value) -> Result<Self, CError> {
+        let dict = if let Value::Dict(x) = v {
+            x
+        } else {
+            return Err(CError::TypeError(ValueType::Dict, v));
+        };
+        let record = dict.0.read().unwrap();
+        record.iter()
+            .map(|(k, v)| {
+                let k = k.as_str().ok_or_else(|| CError::TypeError(ValueType::Str, k.clone().into_value()))?;
+                Ok((k.to_string(), T::from_value(v.clone())?))
+            })
+            .collect()
+    }
+}
+
+impl<T: FromValue> FromValue for HashMap<Key, T> {
+    fn from_value(v: Value) -> Result<Self, CError> {
+        let dict = if let Value::Dict(x) = v {
+            x
+        } else {
+            return Err(CError::TypeError(ValueType::Dict, v));
+        };
+        let record = dict.0.read().unwrap();
+        record.iter()
+            .map(|(k, v)| Ok((k.clone(), T::from_value(v.clone())?)))
+            .collect()
+    }
+}
+
+impl<K: Into<Key> + Eq + Hash, T: IntoValue> IntoValue for HashMap<K, T> {
+    fn into_value(self) -> Value {
+        let record: HashMap<Key, Value> = self.into_iter()
+            .map(|(k, v)| (k.into(), v.into_value()))
+            .collect();
+        Value::from(record)
+    }
+}
+
+/// A pair `(a . b)`.
+impl<A: FromValue, B: FromValue> FromValue for (A, B) {
+    fn from_value(v: Value) -> Result<Self, CError> {
+        match v {
+            Value::Pair(x) => Ok((A::from_value(x.0.clone())?, B::from_value(x.1.clone())?)),
+            v => Err(CError::TypeError(ValueType::Pair, v)),
+        }
+    }
+}
+
+impl<A: IntoValue, B: IntoValue> IntoValue for (A, B) {
+    fn into_value(self) -> Value {
+        Value::from(Pair(self.0.into_value(), self.1.into_value()))
+    }
+}
+
+/// Used by `#[derive(FromValue)]`: the dict a struct is read from.
+pub fn record_of(v: Value) -> Result<Dict, CError> {
+    match v {
+        Value::Dict(x) => Ok(x),
+        v => Err(CError::TypeError(ValueType::Dict, v)),
+    }
+}
+
+/// Used by `#[derive(FromValue)]`, the field is the string key or else the symbol of that name,
+/// a missing key is left to the field type like a missing argument.
+pub fn record_field<T: FromValue>(record: &Dict, key: &str) -> Result<T, CError> {
+    let value = {
+        let record = record.0.read().unwrap();
+        record.get(&Key::from(key))
+            .or_else(|| record.get(&Key::Sym(Handle::new(Symbol::new(key)))))
+            .cloned()
+    };
+    match value {
+        Some(v) => T::from_value(v),
+        None => T::missing().ok_or_else(|| CError::KeyNotFound(Value::from(key.to_string()))),
+    }
+}
+
+/// Used by `#[derive(FromValue)]`: the items of a vector of `len` items.
+pub fn tuple_items(v: Value, len: usize, type_name: &'static str) -> Result<Vec<Value>, CError> {
+    let items = match &v {
+        Value::Vec(x) => x.0.read().unwrap().clone(),
+        _ => return Err(CError::TypeError(ValueType::Vec, v)),
+    };
+    if items.len() == len {
+        Ok(items)
+    } else {
+        Err(CError::InvalidValue(type_name, v))
+    }
+}
+
+/// Used by `#[derive(FromValue)]`: the name and the fields of an enum variant,
+/// from `name` or `#(name field ...)`.
+pub fn split_variant(v: &Value) -> Option<(String, Vec<Value>)> {
+    match v {
+        Value::Sym(x) => Some((x.0.to_string(), vec![])),
+        Value::Vec(x) => {
+            let items = x.0.read().unwrap();
+            match items.split_first() {
+                Some((Value::Sym(tag), fields)) => Some((tag.0.to_string(), fields.to_vec())),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Used by `#[derive(IntoValue)]`: a dict of the fields of a struct.
+pub fn record_into_value(fields: Vec<(&str, Value)>) -> Value {
+    let record: HashMap<Key, Value> = fields.into_iter()
+        .map(|(k, v)| (Key::from(k), v))
+        .collect();
+    Value::from(record)
+}
+
+/// Used by `#[derive(IntoValue)]`: the symbol of a unit variant,
+/// or a vector of the symbol followed by the fields.
+pub fn tagged_into_value(tag: &str, fields: Option<Vec<Value>>) -> Value {
+    let tag = Value::from(Symbol::new(tag));
+    match fields {
+        Some(fields) => {
+            let mut items = vec![tag];
+            items.extend(fields);
+            Value::from(items)
+        },
+        None => tag,
+    }
+}
+
+
+/// The arguments of a call from Rust, a tuple of values to convert or a list of them.
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Value>;
+}
+
+impl IntoArgs for Vec<Value> {
+    fn into_args(self) -> Vec<Value> {
+        self
+    }
+}
+
+impl IntoArgs for &[Value] {
+    fn into_args(self) -> Vec<Value> {
+        self.to_vec()
+    }
+}
+
+impl IntoArgs for () {
+    fn into_args(self) -> Vec<Value> {
+        vec![]
+    }
+}
+
+macro_rules! impl_into_args {
+    ($($tp:ident),*) => {
+        impl<$($tp: IntoValue),*> IntoArgs for ($($tp,)*) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<Value> {
+                let ($($tp,)*) = self;
+                vec![$($tp.into_value()),*]
+            }
+        }
+    }
+}
+
+impl_into_args!(T1);
+impl_into_args!(T1, T2);
+impl_into_args!(T1, T2, T3);
+impl_into_args!(T1, T2, T3, T4);
+impl_into_args!(T1, T2, T3, T4, T5);
+impl_into_args!(T1, T2, T3, T4, T5, T6);
+impl_into_args!(T1, T2, T3, T4, T5, T6, T7);
+impl_into_args!(T1, T2, T3, T4, T5, T6, T7, T8);
+impl_into_args!(T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_into_args!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+impl_into_args!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+impl_into_args!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
+
+
+/// The rest of the arguments, as the last parameter of a bound function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variadic<T>(pub Vec<T>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Required,
+    Optional,
+    Rest,
+}
+
+/// How a parameter of a bound function takes its arguments.
+pub trait Param: Sized {
+    const ARITY: Arity;
+
+    /// `index` is the index of the next argument.
+    fn take(args: &mut std::vec::IntoIter<Value>, index: &mut usize) -> Result<Self, CError>;
+}
+
+/// Trailing `Option` parameters are optional.
+impl<T: FromValue> Param for T {
+    const ARITY: Arity = if T::OPTIONAL { Arity::Optional } else { Arity::Required };
+
+    fn take(args: &mut std::vec::IntoIter<Value>, index: &mut usize) -> Result<Self, CError> {
+        match args.next() {
+            Some(v) => {
+                *index += 1;
+                T::from_value(v).map_err(|e| e.at_argument(*index - 1))
+            },
+            None => T::missing().ok_or(CError::ArgsNotMatching(*index + 1, *index)),
+        }
+    }
+}
+
+impl<T: FromValue> Param for Variadic<T> {
+    const ARITY: Arity = Arity::Rest;
+
+    fn take(args: &mut std::vec::IntoIter<Value>, index: &mut usize) -> Result<Self, CError> {
+        args.map(|v| {
+            *index += 1;
+            T::from_value(v).map_err(|e| e.at_argument(*index - 1))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Variadic)
+    }
+}
+
+fn check_arity(params: &[Arity], len: usize) -> Result<(), CError> {
+    let required = params.iter().filter(|x| **x == Arity::Required).count();
+    let rest = params.iter().any(|x| *x == Arity::Rest);
+    if len < required {
+        Err(CError::ArgsNotMatching(required, len))
+    } else if !rest && len > params.len() {
+        Err(CError::ArgsNotMatching(params.len(), len))
+    } else {
+        Ok(())
+    }
+}
+
+
 pub trait RustCallable<TS, R, E> {
     fn call(&self, args: Vec<Value>) -> CResult;
 }
 
 impl<F, R> RustCallable<(), R, Infallible> for F
     where F: Fn() -> R,
-          Value: From<R>
+          R: IntoValue
 {
     fn call(&self, args: Vec<Value>) -> CResult {
         if args.len() != 0 {
@@ -23,51 +447,49 @@ impl<F, R> RustCallable<(), R, Infallible> for F
         }
 
         let r: R = (self)();
-        Ok(Value::from(r))
+        Ok(r.into_value())
     }
 }
 
 macro_rules! impl_rust_callable {
-    ($n:expr, $($tp:ident),*) => {
-        #[allow(unused_parens)]
-        impl<F, $($tp),*, R> RustCallable<($($tp),*), R, Infallible> for F
+    ($($tp:ident),*) => {
+        impl<F, $($tp),*, R> RustCallable<($($tp,)*), R, Infallible> for F
             where F: Fn($($tp),*) -> R,
-                  $(Value: TryInto<$tp, Error = CError>,)*
-                  Value: From<R>
+                  $($tp: Param,)*
+                  R: IntoValue
         {
             #[allow(non_snake_case)]
             fn call(&self, args: Vec<Value>) -> CResult {
-                if args.len() != $n {
-                    return Err(CError::ArgsNotMatching($n, args.len()));
-                }
+                check_arity(&[$(<$tp as Param>::ARITY),*], args.len())?;
 
                 let mut iter = args.into_iter();
+                let mut index = 0;
                 $(
-                    let $tp: $tp = iter.next().unwrap().try_into()?;
+                    let $tp = <$tp as Param>::take(&mut iter, &mut index)?;
                 )*
                 let r: R = (self)($($tp),*);
-                Ok(Value::from(r))
+                Ok(r.into_value())
             }
         }
     }
 }
 
-impl_rust_callable!(1, T1);
-impl_rust_callable!(2, T1, T2);
-impl_rust_callable!(3, T1, T2, T3);
-impl_rust_callable!(4, T1, T2, T3, T4);
-impl_rust_callable!(5, T1, T2, T3, T4, T5);
-impl_rust_callable!(6, T1, T2, T3, T4, T5, T6);
-impl_rust_callable!(7, T1, T2, T3, T4, T5, T6, T7);
-impl_rust_callable!(8, T1, T2, T3, T4, T5, T6, T7, T8);
-impl_rust_callable!(9, T1, T2, T3, T4, T5, T6, T7, T8, T9);
-impl_rust_callable!(10, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
-impl_rust_callable!(11, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
-impl_rust_callable!(12, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
+impl_rust_callable!(T1);
+impl_rust_callable!(T1, T2);
+impl_rust_callable!(T1, T2, T3);
+impl_rust_callable!(T1, T2, T3, T4);
+impl_rust_callable!(T1, T2, T3, T4, T5);
+impl_rust_callable!(T1, T2, T3, T4, T5, T6);
+impl_rust_callable!(T1, T2, T3, T4, T5, T6, T7);
+impl_rust_callable!(T1, T2, T3, T4, T5, T6, T7, T8);
+impl_rust_callable!(T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_rust_callable!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+impl_rust_callable!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+impl_rust_callable!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
 
 impl<F, R, E> RustCallable<(), R, E> for F
     where F: Fn() -> Result<R, E>,
-          Value: From<R>,
+          R: IntoValue,
           CError: From<E>
 {
     fn call(&self, args: Vec<Value>) -> CResult {
@@ -77,37 +499,32 @@ impl<F, R, E> RustCallable<(), R, E> for F
 
         let r: Result<R, E> = (self)();
         match r {
-            Ok(r) => Ok(Value::from(r)),
+            Ok(r) => Ok(r.into_value()),
             Err(e) => Err(CError::from(e))
         }
     }
 }
 
 macro_rules! impl_rust_callable_exc {
-    ($n:expr, $($tp:ident),*) => {
-        #[allow(unused_parens)]
-        impl<F, $($tp),*, R, E> RustCallable<($($tp),*), R, E> for F
+    ($($tp:ident),*) => {
+        impl<F, $($tp),*, R, E> RustCallable<($($tp,)*), R, E> for F
             where F: Fn($($tp),*) -> Result<R, E>,
-                  $(Value: TryInto<$tp, Error = CError>,)*
-                  Value: From<R>,
+                  $($tp: Param,)*
+                  R: IntoValue,
                   CError: From<E>
         {
             #[allow(non_snake_case)]
             fn call(&self, args: Vec<Value>) -> CResult {
-                if args.len() != $n {
-                    return Err(CError::ArgsNotMatching($n, args.len()));
-                }
+                check_arity(&[$(<$tp as Param>::ARITY),*], args.len())?;
 
                 let mut iter = args.into_iter();
+                let mut index = 0;
                 $(
-                    let $tp: $tp = match iter.next().unwrap().try_into() {
-                        Ok(v) => v,
-                        Err(e) => return Err(e)
-                    };
+                    let $tp = <$tp as Param>::take(&mut iter, &mut index)?;
                 )*
                 let r: Result<R, E> = (self)($($tp),*);
                 match r {
-                    Ok(r) => Ok(Value::from(r)),
+                    Ok(r) => Ok(r.into_value()),
                     Err(e) => Err(CError::from(e))
                 }
             }
@@ -115,18 +532,18 @@ macro_rules! impl_rust_callable_exc {
     }
 }
 
-impl_rust_callable_exc!(1, T1);
-impl_rust_callable_exc!(2, T1, T2);
-impl_rust_callable_exc!(3, T1, T2, T3);
-impl_rust_callable_exc!(4, T1, T2, T3, T4);
-impl_rust_callable_exc!(5, T1, T2, T3, T4, T5);
-impl_rust_callable_exc!(6, T1, T2, T3, T4, T5, T6);
-impl_rust_callable_exc!(7, T1, T2, T3, T4, T5, T6, T7);
-impl_rust_callable_exc!(8, T1, T2, T3, T4, T5, T6, T7, T8);
-impl_rust_callable_exc!(9, T1, T2, T3, T4, T5, T6, T7, T8, T9);
-impl_rust_callable_exc!(10, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
-impl_rust_callable_exc!(11, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
-impl_rust_callable_exc!(12, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
+impl_rust_callable_exc!(T1);
+impl_rust_callable_exc!(T1, T2);
+impl_rust_callable_exc!(T1, T2, T3);
+impl_rust_callable_exc!(T1, T2, T3, T4);
+impl_rust_callable_exc!(T1, T2, T3, T4, T5);
+impl_rust_callable_exc!(T1, T2, T3, T4, T5, T6);
+impl_rust_callable_exc!(T1, T2, T3, T4, T5, T6, T7);
+impl_rust_callable_exc!(T1, T2, T3, T4, T5, T6, T7, T8);
+impl_rust_callable_exc!(T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_rust_callable_exc!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+impl_rust_callable_exc!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+impl_rust_callable_exc!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
 
 #[macro_export]
 macro_rules! bind_rust_callable {
@@ -142,12 +559,43 @@ macro_rules! bind_rust_callable {
     };
 }
 
+/// A native with its registration, as generated by `#[c0i_fn]`.
+#[derive(Debug, Clone, Copy)]
+pub struct NativeDef {
+    pub name: &'static str,
+    pub module: &'static str,
+    pub is_pure: bool,
+    pub interface: NativeContextInterface,
+}
+
+impl NativeDef {
+    pub fn to_callable(&self) -> Callable {
+        Callable::Native(NativeFunction {
+            name: self.name,
+            from_module: self.module,
+            is_pure: self.is_pure,
+            interface: Arc::new(self.interface),
+        })
+    }
+}
+
+/// Turns a closure over Rust values into the body of a native, like `bind_rust_callable!` does for functions.
+pub fn bind_closure<F, TS, R, E>(f: F) -> NativeClosure
+    where F: RustCallable<TS, R, E> + Send + Sync + 'static,
+          TS: 'static,
+          R: 'static,
+          E: 'static
+{
+    Arc::new(move |_, args| f.call(args))
+}
+
 fn scope_register_rust_callable(
     locked_scope: &mut HashMap<Handle<Symbol>, Value>,
     location: Location,
     module_name: &'static str,
     registered_name: &'static str,
-    bound_fn: NativeInterface
+    is_pure: bool,
+    bound_fn: NativeClosure
 ) {
     let symbol = Handle::new(
         Symbol(
@@ -167,7 +615,7 @@ fn scope_register_rust_callable(
                 NativeFunction {
                     name: registered_name,
                     from_module: module_name,
-                    is_pure: true,
+                    is_pure,
                     interface: bound_fn
                 }
             )
@@ -179,6 +627,26 @@ pub fn scope_register_module(
     locked_scope: &mut HashMap<Handle<Symbol>, Value>,
     module_name: &'static str,
     registered_functions: &[(&'static str, NativeInterface)]
+) {
+    let location = Location::new(Handle::new(String::from(module_name)), 0, 0, 0);
+    for (registered_name, bound_fn) in registered_functions {
+        let bound_fn = *bound_fn;
+        scope_register_rust_callable(
+            locked_scope,
+            location.clone(),
+            module_name,
+            registered_name,
+            true,
+            Arc::new(move |_, args| bound_fn(args))
+        );
+    }
+}
+
+/// Like `scope_register_module`, for natives that need the interpreter context.
+pub fn scope_register_context_module(
+    locked_scope: &mut HashMap<Handle<Symbol>, Value>,
+    module_name: &'static str,
+    registered_functions: &[(&'static str, NativeContextInterface)]
 ) {
     let location = Location::new(Handle::new(String::from(module_name)), 0, 0, 0);
     for (registered_name, bound_fn) in registered_functions {
@@ -187,7 +655,44 @@ pub fn scope_register_module(
             location.clone(),
             module_name,
             registered_name,
-            *bound_fn
+            false,
+            Arc::new(*bound_fn)
+        );
+    }
+}
+
+/// Registers a native that may capture state, so it is not taken for pure.
+pub fn scope_register_closure(
+    locked_scope: &mut HashMap<Handle<Symbol>, Value>,
+    module_name: &'static str,
+    registered_name: &'static str,
+    bound_fn: NativeClosure
+) {
+    let location = Location::new(Handle::new(String::from(module_name)), 0, 0, 0);
+    scope_register_rust_callable(
+        locked_scope,
+        location,
+        module_name,
+        registered_name,
+        false,
+        bound_fn
+    );
+}
+
+/// Registers natives generated by `#[c0i_fn]`, each under its own module.
+pub fn scope_register_natives(
+    locked_scope: &mut HashMap<Handle<Symbol>, Value>,
+    natives: &[NativeDef]
+) {
+    for native in natives {
+        let location = Location::new(Handle::new(String::from(native.module)), 0, 0, 0);
+        scope_register_rust_callable(
+            locked_scope,
+            location,
+            native.module,
+            native.name,
+            native.is_pure,
+            Arc::new(native.interface)
         );
     }
 }
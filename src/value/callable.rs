@@ -1,25 +1,53 @@
 use std::collections::HashMap;
-use std::fmt::Display;
+use std::fmt::{Debug, Display};
+use std::sync::Arc;
 
 use sexpr_ir::gast::{symbol::Symbol, Handle};
 
+use super::context::Context;
 use super::result::{CError, CResult};
 use super::scope::{Scope, SimpleScope};
 use crate::ast::Function;
 
 use super::Value;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Closure(pub Function, pub Option<Handle<Scope>>);
 
+/// The environment is left out, it usually holds the closure itself.
+impl Debug for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Closure")
+            .field("name", &self.0.name)
+            .field("params", &self.0.params)
+            .field("pos", &self.0.pos)
+            .finish()
+    }
+}
+
 pub type NativeInterface = fn(Vec<Value>) -> CResult;
 
-#[derive(Debug, Clone)]
+pub type NativeContextInterface = fn(&Context, Vec<Value>) -> CResult;
+
+/// The body of a native, it may capture the state of the host.
+pub type NativeClosure = Arc<dyn Fn(&Context, Vec<Value>) -> CResult + Send + Sync>;
+
+#[derive(Clone)]
 pub struct NativeFunction {
     pub name: &'static str,
     pub from_module: &'static str,
     pub is_pure: bool,
-    pub interface: NativeInterface,
+    pub interface: NativeClosure,
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("from_module", &self.from_module)
+            .field("is_pure", &self.is_pure)
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -28,9 +56,17 @@ pub enum Callable {
     Native(NativeFunction),
 }
 
+/// Callables are equal if they are the same closure or native.
 impl PartialEq for Callable {
-    fn eq(&self, _other: &Self) -> bool {
-        false
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // each evaluation of a lambda makes a new environment
+            (Callable::Closure(Closure(_, Some(a))), Callable::Closure(Closure(_, Some(b)))) =>
+                Handle::ptr_eq(a, b),
+            (Callable::Native(a), Callable::Native(b)) =>
+                a.name == b.name && Arc::ptr_eq(&a.interface, &b.interface),
+            _ => false,
+        }
     }
 }
 
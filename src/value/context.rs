@@ -0,0 +1,140 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::io::{stderr, stdout, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use sexpr_ir::gast::Handle;
+
+#[cfg(feature = "c0i")] use crate::evaluation::module::ModuleLoader;
+#[cfg(feature = "c0i")] use crate::sexpr_to_ast::macros::Expander;
+
+use super::result::CError;
+use super::scope::Scope;
+
+
+/// What a native receives besides its arguments.
+#[derive(Clone)]
+pub struct Context {
+    /// the environment the native was called from
+    pub env: Handle<Scope>,
+    /// the number of calls in progress, this one included
+    pub depth: usize,
+    pub host: Handle<Host>,
+}
+
+impl Context {
+    pub fn stdout(&self) -> MutexGuard<'_, Box<dyn Write + Send>> {
+        self.host.stdout.lock().unwrap()
+    }
+
+    pub fn stderr(&self) -> MutexGuard<'_, Box<dyn Write + Send>> {
+        self.host.stderr.lock().unwrap()
+    }
+
+    pub fn user_data<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.host.user_data.as_ref()?.downcast_ref()
+    }
+
+    pub fn permit(&self, capability: Capability) -> Result<(), CError> {
+        self.host.permit(capability)
+    }
+}
+
+
+/// The part of the context that the host sets up, it is shared by every call.
+pub struct Host {
+    pub stdout: Mutex<Box<dyn Write + Send>>,
+    pub stderr: Mutex<Box<dyn Write + Send>>,
+    pub user_data: Option<Arc<dyn Any + Send + Sync>>,
+    /// calls nested deeper than this fail instead of overflowing the stack
+    pub max_depth: Option<usize>,
+    pub capabilities: HashSet<Capability>,
+    /// the macros that `eval` and `macroexpand` see, `Interpreter` sets its own
+    #[cfg(feature = "c0i")]
+    pub expander: Option<Handle<Mutex<Expander>>>,
+    /// the modules that `eval` and `load` import, `Interpreter` sets its own
+    #[cfg(feature = "c0i")]
+    pub loader: Option<Handle<ModuleLoader>>,
+}
+
+impl Host {
+    pub fn permit(&self, capability: Capability) -> Result<(), CError> {
+        if self.capabilities.contains(&capability) {
+            Ok(())
+        } else {
+            Err(CError::NotPermitted(capability))
+        }
+    }
+}
+
+impl Default for Host {
+    fn default() -> Self {
+        Host {
+            stdout: Mutex::new(Box::new(stdout())),
+            stderr: Mutex::new(Box::new(stderr())),
+            user_data: None,
+            max_depth: None,
+            capabilities: Capability::ALL.iter().copied().collect(),
+            #[cfg(feature = "c0i")]
+            expander: None,
+            #[cfg(feature = "c0i")]
+            loader: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    ReadStdin,
+    ReadFiles,
+    WriteFiles,
+    LoadExtensions,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 4] = [
+        Capability::ReadStdin,
+        Capability::ReadFiles,
+        Capability::WriteFiles,
+        Capability::LoadExtensions,
+    ];
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Capability::ReadStdin => write!(f, "reading stdin"),
+            Capability::ReadFiles => write!(f, "reading files"),
+            Capability::WriteFiles => write!(f, "writing files"),
+            Capability::LoadExtensions => write!(f, "loading native extensions"),
+        }
+    }
+}
+
+
+thread_local! {
+    static HOST: RefCell<Handle<Host>> = RefCell::new(Handle::new(Host::default()));
+}
+
+pub fn current_host() -> Handle<Host> {
+    HOST.with(|x| x.borrow().clone())
+}
+
+/// Runs `f` with `host` as the current host, the default host writes to the process stdout and stderr.
+/// The previous host is back even if `f` panics.
+pub fn with_host<T>(host: &Handle<Host>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Handle<Host>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            if let Some(prev) = self.0.take() {
+                HOST.with(|x| x.replace(prev));
+            }
+        }
+    }
+
+    let _restore = Restore(Some(HOST.with(|x| x.replace(host.clone()))));
+    f()
+}
@@ -2,13 +2,21 @@ pub mod callable;
 pub mod result;
 pub mod scope;
 pub mod autobind;
+pub mod context;
+pub mod number;
+#[cfg(feature = "serde")] pub mod serialize;
 
-use std::{collections::HashMap, convert::identity, fmt::Display, sync::{Arc, RwLock}};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, fmt::Display, hash::{Hash, Hasher}, sync::{Arc, RwLock}};
 
 use callable::Callable;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+use result::CError;
 use sexpr_ir::gast::Handle;
 
 pub use sexpr_ir::gast::symbol::Symbol;
+#[cfg(feature = "serde")] pub use serialize::{from_value, to_value};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
@@ -18,12 +26,15 @@ pub enum Value {
     Uint(u64),
     Int(i64),
     Float(f64),
+    BigInt(Handle<BigInt>),
+    Rational(Handle<BigRational>),
     Str(Handle<String>),
     Sym(Handle<Symbol>),
     Pair(Handle<Pair>),
     Dict(Dict),
     Vec(Vector),
     Callable(Callable),
+    Error(ErrorObject),
 }
 
 macro_rules! impl_value_from {
@@ -57,19 +68,24 @@ impl_value_from!(char, Char);
 impl_value_from!(u64, Uint);
 impl_value_from!(i64, Int);
 impl_value_from!(f64, Float);
+impl_value_from!(Handle<BigInt>, BigInt);
+impl_value_from!(Handle<BigRational>, Rational);
 impl_value_from!(Handle<String>, Str);
 impl_value_from!(Handle<Symbol>, Sym);
 impl_value_from!(Handle<Pair>, Pair);
 impl_value_from!(Dict, Dict);
 impl_value_from!(Vector, Vec);
 impl_value_from!(Callable, Callable);
+impl_value_from!(ErrorObject, Error);
 
 impl_value_from_non_handle!(String, Str);
+impl_value_from_non_handle!(BigInt, BigInt);
+impl_value_from_non_handle!(BigRational, Rational);
 impl_value_from_non_handle!(Symbol, Sym);
 impl_value_from_non_handle!(Pair, Pair);
 
-impl From<HashMap<Handle<String>, Value>> for Value {
-    fn from(v: HashMap<Handle<String>, Value>) -> Self {
+impl From<HashMap<Key, Value>> for Value {
+    fn from(v: HashMap<Key, Value>) -> Self {
         Value::Dict(Dict(Arc::new(RwLock::new(v))))
     }
 }
@@ -85,11 +101,11 @@ macro_rules! impl_value_try_into {
         impl std::convert::TryInto<$t> for Value {
             type Error = $crate::value::result::CError;
 
-            fn try_into(self) -> Result<$t, Self::Error> {
+            fn try_into(self) -> Result<$t, $crate::value::result::CError> {
                 match self {
                     Value::$variant(v) => Ok(v),
                     _ => Err(
-                        $crate::value::result::CError::TypeError((), self)
+                        $crate::value::result::CError::TypeError($crate::value::ValueType::$variant, self)
                     )
                 }
             }
@@ -102,11 +118,11 @@ macro_rules! impl_value_try_into_strip_handle {
         impl std::convert::TryInto<$t> for Value {
             type Error = $crate::value::result::CError;
 
-            fn try_into(self) -> Result<$t, Self::Error> {
+            fn try_into(self) -> Result<$t, $crate::value::result::CError> {
                 match self {
                     Value::$variant(v) => Ok((*v).clone()),
                     _ => Err(
-                        $crate::value::result::CError::TypeError((), self)
+                        $crate::value::result::CError::TypeError($crate::value::ValueType::$variant, self)
                     )
                 }
             }
@@ -119,14 +135,19 @@ impl_value_try_into!(char, Char);
 impl_value_try_into!(u64, Uint);
 impl_value_try_into!(i64, Int);
 impl_value_try_into!(f64, Float);
+impl_value_try_into!(Handle<BigInt>, BigInt);
+impl_value_try_into!(Handle<BigRational>, Rational);
 impl_value_try_into!(Handle<String>, Str);
 impl_value_try_into!(Handle<Symbol>, Sym);
 impl_value_try_into!(Handle<Pair>, Pair);
 impl_value_try_into!(Dict, Dict);
 impl_value_try_into!(Vector, Vec);
 impl_value_try_into!(Callable, Callable);
+impl_value_try_into!(ErrorObject, Error);
 
 impl_value_try_into_strip_handle!(String, Str);
+impl_value_try_into_strip_handle!(BigInt, BigInt);
+impl_value_try_into_strip_handle!(BigRational, Rational);
 impl_value_try_into_strip_handle!(Symbol, Sym);
 impl_value_try_into_strip_handle!(Pair, Pair);
 
@@ -139,6 +160,8 @@ impl PartialOrd for Value {
             (Value::Uint(a), Value::Uint(b)) => a.partial_cmp(b),
             (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
             (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            (Value::BigInt(a), Value::BigInt(b)) => a.partial_cmp(b),
+            (Value::Rational(a), Value::Rational(b)) => a.partial_cmp(b),
             (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
             (Value::Sym(a), Value::Sym(b)) => a.0.partial_cmp(&b.0),
             (Value::Pair(_), Value::Pair(_)) => None,
@@ -158,6 +181,8 @@ impl Display for Value {
             Value::Uint(v) => write!(f, "{}", v),
             Value::Int(v) => write!(f, "{}", v),
             Value::Float(v) => write!(f, "{}", v),
+            Value::BigInt(v) => write!(f, "{}", v),
+            Value::Rational(v) => write!(f, "{}", v),
             Value::Str(v) => write!(f, "\"{}\"", v),
             Value::Sym(v) => write!(f, "{}", v.0),
             Value::Char(v) => write!(f, "(char \"{}\")", v),
@@ -165,6 +190,7 @@ impl Display for Value {
             Value::Vec(v) => v.fmt(f),
             Value::Pair(v) => v.fmt(f),
             Value::Callable(v) => v.fmt(f),
+            Value::Error(v) => v.fmt(f),
         }
     }
 }
@@ -221,7 +247,7 @@ impl Display for Vector {
 impl Display for Dict {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let r = self.0.read().unwrap().iter()
-            .map(|(k, v)| format!("'(\"{}\" . {})", k, v))
+            .map(|(k, v)| format!("'({} . {})", k, v))
             .collect::<Vec<_>>();
         write!(f, "(dict {})", r.join(" "))
     }
@@ -237,7 +263,77 @@ macro_rules! impl_is_type {
 }
 
 
+/// The kinds of `Value`, used to describe what a function expected.
+/// `List`, `Number` and `Key` stand for more than one kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Nil,
+    Bool,
+    Char,
+    Uint,
+    Int,
+    Float,
+    BigInt,
+    Rational,
+    Str,
+    Sym,
+    Pair,
+    Dict,
+    Vec,
+    Callable,
+    Error,
+    List,
+    Number,
+    Key,
+}
+
+impl Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let r = match self {
+            ValueType::Nil => "nil",
+            ValueType::Bool => "bool",
+            ValueType::Char => "char",
+            ValueType::Uint => "uint",
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::BigInt => "bigint",
+            ValueType::Rational => "rational",
+            ValueType::Str => "str",
+            ValueType::Sym => "sym",
+            ValueType::Pair => "pair",
+            ValueType::Dict => "dict",
+            ValueType::Vec => "vec",
+            ValueType::Callable => "callable",
+            ValueType::Error => "error",
+            ValueType::List => "list",
+            ValueType::Number => "number",
+            ValueType::Key => "key",
+        };
+        write!(f, "{}", r)
+    }
+}
+
 impl Value {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::Nil => ValueType::Nil,
+            Value::Bool(_) => ValueType::Bool,
+            Value::Char(_) => ValueType::Char,
+            Value::Uint(_) => ValueType::Uint,
+            Value::Int(_) => ValueType::Int,
+            Value::Float(_) => ValueType::Float,
+            Value::BigInt(_) => ValueType::BigInt,
+            Value::Rational(_) => ValueType::Rational,
+            Value::Str(_) => ValueType::Str,
+            Value::Sym(_) => ValueType::Sym,
+            Value::Pair(_) => ValueType::Pair,
+            Value::Dict(_) => ValueType::Dict,
+            Value::Vec(_) => ValueType::Vec,
+            Value::Callable(_) => ValueType::Callable,
+            Value::Error(_) => ValueType::Error,
+        }
+    }
+
     pub fn is_nil(&self) -> bool {
         matches!(self, Value::Nil)
     }
@@ -246,24 +342,287 @@ impl Value {
     impl_is_type!(is_int, Int);
     impl_is_type!(is_uint, Uint);
     impl_is_type!(is_float, Float);
+    impl_is_type!(is_bigint, BigInt);
+    impl_is_type!(is_rational, Rational);
     impl_is_type!(is_str, Str);
     impl_is_type!(is_sym, Sym);
     impl_is_type!(is_pair, Pair);
     impl_is_type!(is_dict, Dict);
     impl_is_type!(is_vec, Vec);
     impl_is_type!(is_callable, Callable);
+    impl_is_type!(is_error, Error);
+
+    /// The items of a proper list, `None` if it is not one.
+    pub fn list_items(&self) -> Option<Vec<Value>> {
+        let mut items = vec![];
+        let mut this = self;
+        while let Value::Pair(pair) = this {
+            items.push(pair.0.clone());
+            this = &pair.1;
+        }
+        if this.is_nil() {
+            Some(items)
+        } else {
+            None
+        }
+    }
+
+    /// `eq?`, whether both are the same object.
+    /// Nil, bools, chars, ints, uints and floats are compared by value, and symbols by name,
+    /// as they have no identity.
+    pub fn is_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Char(a), Value::Char(b)) => a == b,
+            (Value::Uint(a), Value::Uint(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Sym(a), Value::Sym(b)) => a.0 == b.0,
+            (Value::Str(a), Value::Str(b)) => Handle::ptr_eq(a, b),
+            (Value::BigInt(a), Value::BigInt(b)) => Handle::ptr_eq(a, b),
+            (Value::Rational(a), Value::Rational(b)) => Handle::ptr_eq(a, b),
+            (Value::Pair(a), Value::Pair(b)) => Handle::ptr_eq(a, b),
+            (Value::Dict(a), Value::Dict(b)) => Arc::ptr_eq(&a.0, &b.0),
+            (Value::Vec(a), Value::Vec(b)) => Arc::ptr_eq(&a.0, &b.0),
+            (Value::Callable(a), Value::Callable(b)) => a == b,
+            (Value::Error(a), Value::Error(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// `eqv?`, `eq?` but exact numbers are compared by value, whatever their variant.
+    pub fn is_eqv(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Uint(a), Value::Int(b)) |
+            (Value::Int(b), Value::Uint(a)) => *b >= 0 && *b as u64 == *a,
+            (Value::BigInt(a), Value::BigInt(b)) => a == b,
+            (Value::Rational(a), Value::Rational(b)) => a == b,
+            _ => self.is_eq(other),
+        }
+    }
+
+    /// `equal?`, `eqv?` but strings are compared by content,
+    /// and pairs, vectors and dicts by their items, the order of dicts does not matter.
+    pub fn is_equal(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Pair(_), Value::Pair(_)) => {
+                // the tails of lists are compared iteratively
+                let (mut a, mut b) = (self, other);
+                while let (Value::Pair(x), Value::Pair(y)) = (a, b) {
+                    if Handle::ptr_eq(x, y) {
+                        return true;
+                    }
+                    if !x.0.is_equal(&y.0) {
+                        return false;
+                    }
+                    a = &x.1;
+                    b = &y.1;
+                }
+                a.is_equal(b)
+            },
+            (Value::Vec(a), Value::Vec(b)) => compare_once(&a.0, &b.0, |a, b| {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.is_equal(y))
+            }),
+            (Value::Dict(a), Value::Dict(b)) => compare_once(&a.0, &b.0, |a, b| {
+                a.len() == b.len() && a.iter().all(|(k, x)| b.get(k).is_some_and(|y| x.is_equal(y)))
+            }),
+            _ => self.is_eqv(other),
+        }
+    }
+}
+
+
+thread_local! {
+    // the pairs of vectors or dicts being compared on this thread
+    static COMPARING: RefCell<HashSet<(usize, usize)>> = RefCell::new(HashSet::new());
+}
+
+/// Compares the items of two vectors or dicts with `f`, which is given copies of them
+/// so that no lock is held while the items are compared.
+/// Containers that hold themselves are compared as far as the cycle goes:
+/// two containers met again while they are being compared are taken as equal there.
+fn compare_once<T: Clone>(a: &Arc<RwLock<T>>, b: &Arc<RwLock<T>>, f: impl FnOnce(&T, &T) -> bool) -> bool {
+    struct Done((usize, usize));
+
+    impl Drop for Done {
+        fn drop(&mut self) {
+            COMPARING.with(|x| x.borrow_mut().remove(&self.0));
+        }
+    }
+
+    if Arc::ptr_eq(a, b) {
+        return true;
+    }
+    let key = (Arc::as_ptr(a) as usize, Arc::as_ptr(b) as usize);
+    if !COMPARING.with(|x| x.borrow_mut().insert(key)) {
+        return true;
+    }
+    let _done = Done(key);
+    let a = a.read().unwrap().clone();
+    let b = b.read().unwrap().clone();
+    f(&a, &b)
 }
 
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Pair(pub Value, pub Value);
 
+impl Drop for Pair {
+    // unlink the tail iteratively, dropping a long list recursively overflows the stack
+    fn drop(&mut self) {
+        let mut next = std::mem::replace(&mut self.1, Value::Nil);
+        while let Value::Pair(pair) = next {
+            match Handle::try_unwrap(pair) {
+                Ok(mut pair) => next = std::mem::replace(&mut pair.1, Value::Nil),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
-pub struct Dict(pub Arc<RwLock<HashMap<Handle<String>, Value>>>);
+pub struct Dict(pub Arc<RwLock<HashMap<Key, Value>>>);
 
 impl PartialEq for Dict {
-    fn eq(&self, _other: &Self) -> bool {
-        false
+    fn eq(&self, other: &Self) -> bool {
+        compare_once(&self.0, &other.0, |a, b| a.len() == b.len() && a.iter().all(|(k, v)| b.get(k) == Some(v)))
+    }
+}
+
+/// A key of a dict: a string, a symbol, a char, a bool or an integer.
+///
+/// Integers are equal whatever their variant, strings and symbols are not.
+#[derive(Debug, Clone)]
+pub enum Key {
+    Str(Handle<String>),
+    Sym(Handle<Symbol>),
+    Char(char),
+    Bool(bool),
+    Integer(i128),
+    // the integers out of the range of `Integer`
+    BigInt(Handle<BigInt>),
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum KeyRef<'a> {
+    Str(&'a str),
+    Sym(&'a str),
+    Char(char),
+    Bool(bool),
+    Integer(i128),
+    BigInt(&'a BigInt),
+}
+
+impl Key {
+    pub fn new(v: Value) -> Result<Key, CError> {
+        match v {
+            Value::Str(x) => Ok(Key::Str(x)),
+            Value::Sym(x) => Ok(Key::Sym(x)),
+            Value::Char(x) => Ok(Key::Char(x)),
+            Value::Bool(x) => Ok(Key::Bool(x)),
+            Value::Uint(x) => Ok(Key::Integer(x as i128)),
+            Value::Int(x) => Ok(Key::Integer(x as i128)),
+            Value::BigInt(x) => Ok(x.to_i128().map_or(Key::BigInt(x), Key::Integer)),
+            v => Err(CError::TypeError(ValueType::Key, v)),
+        }
+    }
+
+    pub fn into_value(self) -> Value {
+        match self {
+            Key::Str(x) => Value::Str(x),
+            Key::Sym(x) => Value::Sym(x),
+            Key::Char(x) => Value::Char(x),
+            Key::Bool(x) => Value::Bool(x),
+            Key::Integer(x) => Value::from_bigint(x.into()),
+            Key::BigInt(x) => Value::BigInt(x),
+        }
+    }
+
+    /// The string of a string or symbol key.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Key::Str(x) => Some(x),
+            Key::Sym(x) => Some(&x.0),
+            _ => None,
+        }
+    }
+
+    fn key_ref(&self) -> KeyRef<'_> {
+        match self {
+            Key::Str(x) => KeyRef::Str(x),
+            Key::Sym(x) => KeyRef::Sym(&x.0),
+            Key::Char(x) => KeyRef::Char(*x),
+            Key::Bool(x) => KeyRef::Bool(*x),
+            Key::Integer(x) => KeyRef::Integer(*x),
+            Key::BigInt(x) => KeyRef::BigInt(x),
+        }
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.key_ref() == other.key_ref()
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key_ref().hash(state)
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.clone().into_value().fmt(f)
+    }
+}
+
+macro_rules! impl_key_from {
+    ($variant:ident, $t:ty) => {
+        impl From<$t> for Key {
+            fn from(v: $t) -> Self {
+                Key::$variant(v)
+            }
+        }
+    };
+}
+
+impl_key_from!(Str, Handle<String>);
+impl_key_from!(Sym, Handle<Symbol>);
+impl_key_from!(Char, char);
+impl_key_from!(Bool, bool);
+
+impl From<u64> for Key {
+    fn from(v: u64) -> Self {
+        Key::Integer(v as i128)
+    }
+}
+
+impl From<i64> for Key {
+    fn from(v: i64) -> Self {
+        Key::Integer(v as i128)
+    }
+}
+
+impl From<String> for Key {
+    fn from(v: String) -> Self {
+        Key::Str(Handle::new(v))
+    }
+}
+
+impl From<&str> for Key {
+    fn from(v: &str) -> Self {
+        Key::Str(Handle::new(v.to_string()))
+    }
+}
+
+impl From<Symbol> for Key {
+    fn from(v: Symbol) -> Self {
+        Key::Sym(Handle::new(v))
     }
 }
 
@@ -272,9 +631,7 @@ pub struct Vector(pub Arc<RwLock<Vec<Value>>>);
 
 impl PartialEq for Vector {
     fn eq(&self, other: &Self) -> bool {
-        self.0.read().unwrap().iter().zip(other.0.read().unwrap().iter())
-            .map(|(a, b)| *a == *b)
-            .reduce(|a, b| a && b).map_or(false, identity)
+        compare_once(&self.0, &other.0, |a, b| a == b)
     }
 }
 
@@ -284,14 +641,26 @@ impl PartialOrd for Vector {
     }
 }
 
+/// An error caught by a handler, it keeps the whole backtrace so that raising it again loses nothing.
+#[derive(Debug, Clone)]
+pub struct ErrorObject(pub Handle<CError>);
+
+impl PartialEq for ErrorObject {
+    fn eq(&self, other: &Self) -> bool {
+        Handle::ptr_eq(&self.0, &other.0)
+    }
+}
 
+impl Display for ErrorObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<error {}>", self.0.root().to_string().trim_end())
+    }
+}
+
+
+/// A list of the values.
 impl From<&[Value]> for Value {
     fn from(i: &[Value]) -> Self {
-        if let Some(left) = i.first() {
-            let right = Value::from(&i[1..]);
-            Value::Pair(Handle::new(Pair(left.clone(), right)))
-        } else {
-            Value::Nil
-        }
+        i.iter().rev().fold(Value::Nil, |prev, x| Value::Pair(Handle::new(Pair(x.clone(), prev))))
     }
 }
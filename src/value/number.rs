@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
+use sexpr_ir::gast::Handle;
+
+use super::Value;
+
+
+/// Exact numbers are kept in the smallest variant that holds them:
+/// a uint if it is not negative, an int, then a bigint,
+/// and a rational only if it is not an integer.
+impl Value {
+    pub fn from_bigint(x: BigInt) -> Value {
+        if let Some(x) = x.to_u64() {
+            Value::Uint(x)
+        } else if let Some(x) = x.to_i64() {
+            Value::Int(x)
+        } else {
+            Value::BigInt(Handle::new(x))
+        }
+    }
+
+    pub fn from_rational(x: BigRational) -> Value {
+        if x.is_integer() {
+            Value::from_bigint(x.to_integer())
+        } else {
+            Value::Rational(Handle::new(x))
+        }
+    }
+
+    /// Reads integers of any size and rationals as `n/d`,
+    /// the literals that do not fit in an int or uint.
+    pub fn parse_exact(s: &str) -> Option<Value> {
+        let digits = |x: &str| {
+            let x = x.strip_prefix(|c| c == '+' || c == '-').unwrap_or(x);
+            !x.is_empty() && x.bytes().all(|c| c.is_ascii_digit())
+        };
+        match s.split_once('/') {
+            None if digits(s) => BigInt::from_str(s.strip_prefix('+').unwrap_or(s)).ok().map(Value::from_bigint),
+            Some((n, d)) if digits(n) && d.bytes().all(|c| c.is_ascii_digit()) && !d.is_empty() => {
+                let n = BigInt::from_str(n.strip_prefix('+').unwrap_or(n)).ok()?;
+                let d = BigInt::from_str(d).ok()?;
+                if d.is_zero() {
+                    return None;
+                }
+                Some(Value::from_rational(BigRational::new(n, d)))
+            },
+            _ => None,
+        }
+    }
+
+    /// The exact value of a float, `None` if it is infinite or NaN.
+    pub fn exact_float(x: f64) -> Option<Value> {
+        BigRational::from_float(x).map(Value::from_rational)
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, Value::Uint(_) | Value::Int(_) | Value::BigInt(_) | Value::Rational(_))
+    }
+}
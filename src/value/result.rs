@@ -2,7 +2,8 @@ use std::fmt::Display;
 
 use sexpr_ir::gast::{Handle, symbol::{Location, Symbol}};
 
-use crate::value::Value;
+use crate::value::{Value, ValueType};
+use crate::value::context::Capability;
 
 use super::callable::Callable;
 
@@ -15,18 +16,137 @@ pub enum CError {
     StackBacktrace(Callable, Handle<CError>),
     Positional(Location, Handle<CError>),
     SymbolNotFound(Handle<Symbol>),
+    ModuleNotFound(Handle<Symbol>),
+    CircularImport(Handle<Symbol>),
+    // a module that could not be parsed or compiled
+    InvalidModule(Handle<Symbol>, Handle<String>),
     ValueIsNotCallable(Value),
     CondIsNotBoolean(Value),
     CondIsNotMatching,
     // CaptureVariableError(Handle<Symbol>),
     ArgsNotMatching(usize, usize),
-    TypeError((), Value),
+    TypeError(ValueType, Value),
+    // index of the argument, the expected type and the argument
+    ArgTypeError(usize, ValueType, Value),
+    BadArguments(&'static str, Handle<CError>),
     // MathError,
     ZeroDivisionError,
-    RuntimeError(Option<Value>),
+    OverflowError,
+    // the message and the irritants given to `error`
+    RuntimeError(Option<Value>, Vec<Value>),
     Unreachable(Option<Value>),
+    Raised(Value),
+    RecursionLimit(usize),
+    NotPermitted(Capability),
+    // code given to eval or load that could not be parsed or compiled
+    CompileError(Handle<String>),
+    KeyNotFound(Value),
+    // the index and the length, the index is wide enough for uints and negative ints
+    IndexOutOfRange(i128, usize),
+    // the path of a native extension that could not be loaded and why
+    ExtensionError(Handle<String>, Handle<String>),
+    // the name of the Rust type and a value that has not its shape
+    InvalidValue(&'static str, Value),
+    // a value that serde could not convert
+    ConversionError(Handle<String>),
+    // malformed JSON, with the line and the column of the error
+    JsonError(Handle<String>, usize, usize),
 }
 
+impl CError {
+    /// Turns a failed conversion of the argument at `index` into an `ArgTypeError`.
+    pub fn at_argument(self, index: usize) -> CError {
+        match self {
+            CError::TypeError(e, v) => CError::ArgTypeError(index, e, v),
+            e => e,
+        }
+    }
+
+    /// Names the native function that rejected its arguments.
+    pub fn in_native(self, name: &'static str) -> CError {
+        match self {
+            CError::ArgsNotMatching(..) | CError::ArgTypeError(..) | CError::InvalidValue(..) =>
+                CError::BadArguments(name, Handle::new(self)),
+            e => e,
+        }
+    }
+
+    /// The error without the backtrace and the positions it went through.
+    pub fn root(&self) -> &CError {
+        match self {
+            CError::StackBacktrace(_, e) |
+            CError::Positional(_, e) => e.root(),
+            e => e,
+        }
+    }
+
+    /// The message of the error, as seen by `error-message`.
+    pub fn message(&self) -> String {
+        match self.root() {
+            CError::SymbolNotFound(_) => "symbol not found".to_string(),
+            CError::ModuleNotFound(_) => "module not found".to_string(),
+            CError::CircularImport(_) => "circular import".to_string(),
+            CError::InvalidModule(_, e) => e.to_string(),
+            CError::ValueIsNotCallable(_) => "value is not callable".to_string(),
+            CError::CondIsNotBoolean(_) => "cond is not boolean".to_string(),
+            CError::CondIsNotMatching => "cond is not matching".to_string(),
+            CError::ArgsNotMatching(_, _) => "arguments are not matching".to_string(),
+            CError::TypeError(e, _) => format!("expected {}", e),
+            CError::ArgTypeError(i, e, _) => format!("argument {} should be {}", i + 1, e),
+            CError::BadArguments(n, e) => format!("{}: {}", n, e.message()),
+            CError::ZeroDivisionError => "division by zero".to_string(),
+            CError::OverflowError => "integer overflow".to_string(),
+            CError::RuntimeError(Some(Value::Str(e)), _) |
+            CError::Unreachable(Some(Value::Str(e))) => e.to_string(),
+            CError::RuntimeError(Some(e), _) |
+            CError::Unreachable(Some(e)) |
+            CError::Raised(e) => e.to_string(),
+            CError::RecursionLimit(_) => "recursion limit exceeded".to_string(),
+            CError::NotPermitted(e) => format!("{} is not permitted", e),
+            CError::CompileError(e) => e.to_string(),
+            CError::KeyNotFound(_) => "key not found".to_string(),
+            CError::IndexOutOfRange(_, _) => "index out of range".to_string(),
+            CError::ExtensionError(_, e) => e.to_string(),
+            CError::InvalidValue(t, _) => format!("expected {}", t),
+            CError::ConversionError(e) => e.to_string(),
+            CError::JsonError(e, line, column) => format!("{} at line {} column {}", e, line, column),
+            CError::RuntimeError(None, _) => "runtime error".to_string(),
+            CError::Unreachable(None) => "unreachable".to_string(),
+            CError::StackBacktrace(_, _) |
+            CError::Positional(_, _) => unreachable!(),
+        }
+    }
+
+    /// The values the error is about, as seen by `error-irritants`.
+    pub fn irritants(&self) -> Vec<Value> {
+        match self.root() {
+            CError::SymbolNotFound(e) |
+            CError::ModuleNotFound(e) |
+            CError::CircularImport(e) |
+            CError::InvalidModule(e, _) => vec![Value::Sym(e.clone())],
+            CError::ValueIsNotCallable(e) |
+            CError::CondIsNotBoolean(e) |
+            CError::TypeError(_, e) |
+            CError::ArgTypeError(_, _, e) |
+            CError::KeyNotFound(e) |
+            CError::InvalidValue(_, e) => vec![e.clone()],
+            CError::ArgsNotMatching(a, b) => vec![Value::Uint(*a as u64), Value::Uint(*b as u64)],
+            CError::IndexOutOfRange(a, b) => {
+                let a = if *a < 0 { Value::Int(*a as i64) } else { Value::Uint(*a as u64) };
+                vec![a, Value::Uint(*b as u64)]
+            },
+            CError::RecursionLimit(e) => vec![Value::Uint(*e as u64)],
+            CError::JsonError(_, line, column) => vec![Value::Uint(*line as u64), Value::Uint(*column as u64)],
+            CError::BadArguments(_, e) => e.irritants(),
+            CError::RuntimeError(_, e) => e.clone(),
+            CError::ExtensionError(e, _) => vec![Value::Str(e.clone())],
+            _ => vec![],
+        }
+    }
+}
+
+impl std::error::Error for CError {}
+
 impl Display for CError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -39,6 +159,9 @@ impl Display for CError {
                 t.fmt(f)
             },
             CError::SymbolNotFound(e) => writeln!(f, "SymbolNotFound: {}.", e),
+            CError::ModuleNotFound(e) => writeln!(f, "ModuleNotFound: {}.", e),
+            CError::CircularImport(e) => writeln!(f, "CircularImport: {}.", e),
+            CError::InvalidModule(e, t) => write!(f, "InvalidModule: {}:\n{}", e, t),
             CError::ValueIsNotCallable(e) => writeln!(f, "ValueIsNotCallable: {}.", e),
             CError::CondIsNotBoolean(e) => writeln!(f, "CondIsNotBoolean: {}.", e),
             CError::CondIsNotMatching => writeln!(f, "CondIsNotMatching."),
@@ -46,18 +169,48 @@ impl Display for CError {
                 writeln!(f, "ArgsMatchingError: this function takes {} arguments but {} argument was supplied.",
                     a, b),
             CError::TypeError(e, v) =>
-                writeln!(f, "TypeError: {:?} is not {:?} type.", v, e),
+                writeln!(f, "TypeError: expected {}, but {} is {}.", e, v, v.value_type()),
+            CError::ArgTypeError(i, e, v) =>
+                writeln!(f, "TypeError: argument {} should be {}, but {} is {}.", i + 1, e, v, v.value_type()),
+            CError::BadArguments(n, e) => match &**e {
+                CError::ArgsNotMatching(a, b) =>
+                    writeln!(f, "ArgsMatchingError: '{} takes {} arguments but {} argument was supplied.",
+                        n, a, b),
+                CError::ArgTypeError(i, e, v) =>
+                    writeln!(f, "TypeError: argument {} of '{} should be {}, but {} is {}.",
+                        i + 1, n, e, v, v.value_type()),
+                e => {
+                    writeln!(f, "\tin '{}", n)?;
+                    e.fmt(f)
+                },
+            },
             CError::ZeroDivisionError => writeln!(f, "ZeroDivisionError."),
-            CError::RuntimeError(e) => if let Some(e) = e {
-                writeln!(f, "RuntimeError: {}.", e)
-            } else {
-                writeln!(f, "RuntimeError.")
+            CError::OverflowError => writeln!(f, "OverflowError."),
+            CError::RuntimeError(e, irritants) => {
+                write!(f, "RuntimeError")?;
+                if let Some(e) = e {
+                    write!(f, ": {}", e)?;
+                }
+                for i in irritants {
+                    write!(f, " {}", i)?;
+                }
+                writeln!(f, ".")
             },
             CError::Unreachable(e) => if let Some(e) = e {
                 writeln!(f, "Unreachable: {}.", e)
             } else {
                 writeln!(f, "Unreachable.")
             },
+            CError::Raised(e) => writeln!(f, "Raised: {}.", e),
+            CError::RecursionLimit(e) => writeln!(f, "RecursionLimit: calls are nested deeper than {}.", e),
+            CError::NotPermitted(e) => writeln!(f, "NotPermitted: {} is not permitted.", e),
+            CError::CompileError(e) => write!(f, "CompileError:\n{}", e),
+            CError::KeyNotFound(e) => writeln!(f, "KeyNotFound: {}.", e),
+            CError::IndexOutOfRange(i, len) => writeln!(f, "IndexOutOfRange: {} is out of a length of {}.", i, len),
+            CError::ExtensionError(p, e) => writeln!(f, "ExtensionError: {}: {}.", p, e),
+            CError::InvalidValue(t, v) => writeln!(f, "InvalidValue: {} is not a valid {}.", v, t),
+            CError::ConversionError(e) => writeln!(f, "ConversionError: {}.", e),
+            CError::JsonError(e, line, column) => writeln!(f, "JsonError: {} at line {} column {}.", e, line, column),
         }
     }
 }
@@ -56,6 +56,14 @@ impl Scope {
         Handle::new(r)
     }
 
+    /// The outermost level, which usually holds the builtins.
+    pub fn root(self: &Handle<Scope>) -> Handle<Scope> {
+        match &self.parent {
+            Some(p) => p.root(),
+            None => self.clone(),
+        }
+    }
+
     pub fn set(self: &Handle<Scope>, k: &Handle<Symbol>, v: &Value) {
         let mut record = self.this_level.0.write().unwrap();
         record.insert(k.clone(), v.clone());
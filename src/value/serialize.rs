@@ -0,0 +1,748 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::sync::{Arc, RwLock};
+
+use num_traits::ToPrimitive;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess,
+    SeqAccess, VariantAccess, Visitor,
+};
+use serde::ser::{self, Serialize, Serializer};
+use serde::forward_to_deserialize_any;
+use sexpr_ir::gast::{Handle, symbol::Symbol};
+
+use super::{Dict, Key, Value};
+use super::result::CError;
+
+
+/// Converts any serializable value into a value of c0i.
+///
+/// Sequences and tuples become vectors, maps and structs dicts.
+/// Enums take the shape of `#[derive(IntoValue)]`: a unit variant is the symbol of its name,
+/// the other variants a vector of that symbol followed by the fields, or by a dict of them.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, CError> {
+    value.serialize(ValueSerializer).map_err(CError::from)
+}
+
+/// Converts a value of c0i into any deserializable value,
+/// enums are also read from a dict of a single variant name, as JSON has them.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, CError> {
+    T::deserialize(value).map_err(CError::from)
+}
+
+
+/// The error of serializing into and deserializing from `Value`.
+#[derive(Debug)]
+pub struct Error(String);
+
+impl From<Error> for CError {
+    fn from(e: Error) -> Self {
+        CError::ConversionError(Handle::new(e.0))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+
+/// Lists and vectors are sequences, symbols strings, and callables can not be serialized.
+/// Dicts are maps keyed by strings, their keys must be strings or symbols,
+/// and a string and a symbol of the same name can not both be keys.
+/// Vectors and dicts that hold themselves can not be serialized either.
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ser::{Error, SerializeMap, SerializeSeq};
+        match self {
+            Value::Nil => serializer.serialize_unit(),
+            Value::Bool(x) => serializer.serialize_bool(*x),
+            Value::Char(x) => serializer.serialize_char(*x),
+            Value::Uint(x) => serializer.serialize_u64(*x),
+            Value::Int(x) => serializer.serialize_i64(*x),
+            Value::Float(x) => serializer.serialize_f64(*x),
+            Value::BigInt(x) => match (x.to_i128(), x.to_u128()) {
+                (Some(x), _) => serializer.serialize_i128(x),
+                (_, Some(x)) => serializer.serialize_u128(x),
+                _ => Err(S::Error::custom(format!("{} is too large to be serialized", x))),
+            },
+            Value::Rational(x) => Err(S::Error::custom(format!("{} can not be serialized exactly", x))),
+            Value::Str(x) => serializer.serialize_str(x),
+            Value::Sym(x) => serializer.serialize_str(&x.0),
+            Value::Pair(_) => {
+                let items = self.list_items()
+                    .ok_or_else(|| S::Error::custom(format!("{} is not a list and can not be serialized", self)))?;
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for i in items.iter() {
+                    seq.serialize_element(i)?;
+                }
+                seq.end()
+            },
+            Value::Vec(x) => serialize_once(&x.0, |items| {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for i in items.iter() {
+                    seq.serialize_element(i)?;
+                }
+                seq.end()
+            }),
+            Value::Dict(x) => serialize_once(&x.0, |record| {
+                let mut keys = HashSet::new();
+                let mut map = serializer.serialize_map(Some(record.len()))?;
+                for (k, v) in record.iter() {
+                    let key = k.as_str()
+                        .ok_or_else(|| S::Error::custom(format!("{} can not be serialized as the key of a map", k)))?;
+                    if !keys.insert(key) {
+                        return Err(S::Error::custom(format!("{} is a key both as a string and as a symbol", key)));
+                    }
+                    map.serialize_entry(key, v)?;
+                }
+                map.end()
+            }),
+            Value::Callable(x) => Err(S::Error::custom(format!("{} can not be serialized", x))),
+            Value::Error(x) => Err(S::Error::custom(format!("{} can not be serialized", x))),
+        }
+    }
+}
+
+thread_local! {
+    // the vectors and dicts being serialized on this thread
+    static SERIALIZING: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+}
+
+/// Serializes the items of a vector or dict with `f`,
+/// meeting the container again while its items are serialized means that it holds itself.
+fn serialize_once<T, R, E: ser::Error>(x: &Arc<RwLock<T>>, f: impl FnOnce(&T) -> Result<R, E>) -> Result<R, E> {
+    struct Done(usize);
+
+    impl Drop for Done {
+        fn drop(&mut self) {
+            SERIALIZING.with(|x| x.borrow_mut().remove(&self.0));
+        }
+    }
+
+    let key = Arc::as_ptr(x) as usize;
+    if !SERIALIZING.with(|x| x.borrow_mut().insert(key)) {
+        return Err(E::custom("a vector or dict that holds itself can not be serialized"));
+    }
+    let _done = Done(key);
+    f(&x.read().unwrap())
+}
+
+impl<'de> de::Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a value of c0i")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Uint(v))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Value, E> {
+        Ok(Value::from_bigint(v.into()))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Value, E> {
+        Ok(Value::from_bigint(v.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_char<E: de::Error>(self, v: char) -> Result<Value, E> {
+        Ok(Value::Char(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::Str(Handle::new(v.to_string())))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::Str(Handle::new(v)))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = vec![];
+        while let Some(i) = seq.next_element()? {
+            items.push(i);
+        }
+        Ok(Value::from(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut record = HashMap::new();
+        while let Some((k, v)) = map.next_entry::<Value, Value>()? {
+            record.insert(Key::new(k).map_err(|e| de::Error::custom(e.message()))?, v);
+        }
+        Ok(Value::from(record))
+    }
+}
+
+
+struct ValueSerializer;
+
+fn tagged(variant: &str, fields: Vec<Value>) -> Value {
+    let mut items = vec![Value::Sym(Handle::new(Symbol::new(variant)))];
+    items.extend(fields);
+    Value::from(items)
+}
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::Int(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        Ok(Value::Uint(v as u64))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        Ok(Value::Uint(v as u64))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        Ok(Value::Uint(v as u64))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        Ok(Value::Uint(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, Error> {
+        Ok(Value::from_bigint(v.into()))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, Error> {
+        Ok(Value::from_bigint(v.into()))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        Ok(Value::Float(v as f64))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::Char(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::Str(Handle::new(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        Ok(Value::from(v.iter().map(|x| Value::Uint(*x as u64)).collect::<Vec<_>>()))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Value, Error> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> Result<Value, Error> {
+        Ok(Value::Sym(Handle::new(Symbol::new(variant))))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T
+    ) -> Result<Value, Error> {
+        Ok(tagged(variant, vec![value.serialize(self)?]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer { variant: None, items: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize
+    ) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer { variant: Some(variant), items: Vec::with_capacity(len) })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer { variant: None, record: HashMap::new(), key: None })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<MapSerializer, Error> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize
+    ) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer { variant: Some(variant), record: HashMap::new(), key: None })
+    }
+}
+
+struct SeqSerializer {
+    variant: Option<&'static str>,
+    items: Vec<Value>,
+}
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, Error> {
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, self.items),
+            None => Value::from(self.items),
+        })
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+struct MapSerializer {
+    variant: Option<&'static str>,
+    record: HashMap<Key, Value>,
+    key: Option<Key>,
+}
+
+impl MapSerializer {
+    fn finish(self) -> Result<Value, Error> {
+        let record = Value::from(self.record);
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, vec![record]),
+            None => record,
+        })
+    }
+}
+
+fn map_key(key: Value) -> Result<Key, Error> {
+    Key::new(key).map_err(|e| match e {
+        CError::TypeError(_, key) => Error(format!("{} can not be the key of a dict", key)),
+        e => Error(e.message()),
+    })
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(map_key(key.serialize(ValueSerializer)?)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().ok_or_else(|| Error("a value is serialized before its key".to_string()))?;
+        self.record.insert(key, value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.record.insert(Key::from(key), value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.record.insert(Key::from(key), value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+
+impl<'de> Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Nil => visitor.visit_unit(),
+            Value::Bool(x) => visitor.visit_bool(x),
+            Value::Char(x) => visitor.visit_char(x),
+            Value::Uint(x) => visitor.visit_u64(x),
+            Value::Int(x) => visitor.visit_i64(x),
+            Value::Float(x) => visitor.visit_f64(x),
+            Value::BigInt(x) => match (x.to_i128(), x.to_u128()) {
+                (Some(x), _) => visitor.visit_i128(x),
+                (_, Some(x)) => visitor.visit_u128(x),
+                _ => Err(Error(format!("{} is too large to be deserialized", x))),
+            },
+            Value::Rational(x) => Err(Error(format!("{} can not be deserialized exactly", x))),
+            Value::Str(x) => visitor.visit_string(x.to_string()),
+            Value::Sym(x) => visitor.visit_string(x.0.to_string()),
+            Value::Pair(_) => match self.list_items() {
+                Some(items) => visitor.visit_seq(SeqDeserializer(items.into_iter())),
+                None => Err(Error(format!("{} is not a list", self))),
+            },
+            Value::Vec(x) => {
+                let items = x.0.read().unwrap().clone();
+                visitor.visit_seq(SeqDeserializer(items.into_iter()))
+            },
+            Value::Dict(x) => visitor.visit_map(MapDeserializer::new(&x)),
+            Value::Callable(x) => Err(Error(format!("{} can not be deserialized", x))),
+            Value::Error(x) => Err(Error(format!("{} can not be deserialized", x))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Nil => visitor.visit_none(),
+            v => visitor.visit_some(v),
+        }
+    }
+
+    /// `nil` is also the empty list.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Nil => visitor.visit_seq(SeqDeserializer(vec![].into_iter())),
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _: &'static [&'static str],
+        visitor: V
+    ) -> Result<V::Value, Error> {
+        let invalid = |v: &Value| Error(format!("{} is not a variant of {}", v, name));
+        let (variant, fields) = match &self {
+            Value::Sym(x) => (x.0.to_string(), vec![]),
+            Value::Str(x) => (x.to_string(), vec![]),
+            Value::Vec(x) => {
+                let items = x.0.read().unwrap();
+                match items.split_first() {
+                    Some((Value::Sym(tag), fields)) => (tag.0.to_string(), fields.to_vec()),
+                    _ => return Err(invalid(&self)),
+                }
+            },
+            Value::Dict(x) => {
+                let record = x.0.read().unwrap();
+                match record.iter().next() {
+                    Some((k, v)) if record.len() == 1 && k.as_str().is_some() =>
+                        (k.as_str().unwrap().to_string(), vec![v.clone()]),
+                    _ => return Err(invalid(&self)),
+                }
+            },
+            _ => return Err(invalid(&self)),
+        };
+        visitor.visit_enum(EnumDeserializer { variant, fields })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
+
+struct SeqDeserializer(std::vec::IntoIter<Value>);
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        self.0.next().map(|v| seed.deserialize(v)).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct MapDeserializer {
+    entries: std::vec::IntoIter<(Key, Value)>,
+    value: Option<Value>,
+}
+
+impl MapDeserializer {
+    fn new(dict: &Dict) -> Self {
+        let entries: Vec<_> = dict.0.read().unwrap().iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        MapDeserializer { entries: entries.into_iter(), value: None }
+    }
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((k, v)) => {
+                self.value = Some(v);
+                seed.deserialize(k.into_value()).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(v) => seed.deserialize(v),
+            None => Err(Error("a value is deserialized before its key".to_string())),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    fields: Vec<Value>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, VariantDeserializer), Error> {
+        let variant = seed.deserialize(Value::Str(Handle::new(self.variant.clone())))?;
+        Ok((variant, VariantDeserializer { variant: self.variant, fields: self.fields }))
+    }
+}
+
+struct VariantDeserializer {
+    variant: String,
+    fields: Vec<Value>,
+}
+
+impl VariantDeserializer {
+    fn single(self) -> Result<Value, Error> {
+        let mut fields = self.fields;
+        if fields.len() == 1 {
+            Ok(fields.pop().unwrap())
+        } else {
+            Err(Error(format!("the variant {} takes one field, but {} are given", self.variant, fields.len())))
+        }
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        if self.fields.is_empty() {
+            Ok(())
+        } else {
+            Err(Error(format!("the variant {} takes no fields", self.variant)))
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.single()?)
+    }
+
+    /// The fields follow the symbol, or come as a vector of them from JSON.
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        match &self.fields[..] {
+            [Value::Vec(_)] | [Value::Pair(_)] if len != 1 => self.single()?.deserialize_any(visitor),
+            _ => visitor.visit_seq(SeqDeserializer(self.fields.into_iter())),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        self.single()?.deserialize_any(visitor)
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use c0i::{Error, Interpreter};
+use c0i::value::{Value, ValueType};
+use c0i::value::autobind::{FromValue, IntoValue, Variadic};
+use c0i::value::result::CError;
+
+
+fn bad_arguments(interpreter: &mut Interpreter, source: &str) -> CError {
+    match interpreter.eval_str(source) {
+        Err(Error::Runtime(e)) => match e.root() {
+            CError::BadArguments(_, e) => (**e).clone(),
+            e => panic!("expected bad arguments, got {}", e),
+        },
+        r => panic!("expected a runtime error, got {:?}", r.map(|x| x.to_string())),
+    }
+}
+
+#[test]
+fn integers_are_taken_from_uint_and_int() {
+    assert_eq!(i64::from_value(Value::Uint(5)).unwrap(), 5);
+    assert_eq!(i64::from_value(Value::Int(-5)).unwrap(), -5);
+    assert_eq!(u64::from_value(Value::Int(5)).unwrap(), 5);
+    assert_eq!(i32::from_value(Value::Uint(7)).unwrap(), 7);
+    assert_eq!(usize::from_value(Value::Int(7)).unwrap(), 7);
+    assert_eq!(u32::from_value(Value::Uint(7)).unwrap(), 7);
+}
+
+#[test]
+fn integers_out_of_range_are_invalid() {
+    let e = u32::from_value(Value::Uint(5000000000)).unwrap_err();
+    assert!(matches!(e, CError::InvalidValue("u32", Value::Uint(5000000000))), "{}", e);
+    assert_eq!(e.to_string(), "InvalidValue: 5000000000 is not a valid u32.\n");
+    assert!(matches!(u64::from_value(Value::Int(-1)), Err(CError::InvalidValue("u64", _))));
+    assert!(matches!(i64::from_value(Value::Uint(u64::MAX)), Err(CError::InvalidValue("i64", _))));
+    assert!(matches!(i32::from_value(Value::Int(i64::MIN)), Err(CError::InvalidValue("i32", _))));
+    let e = i64::from_value(Value::Float(1.0)).unwrap_err();
+    assert!(matches!(e, CError::TypeError(ValueType::Int, _)), "{}", e);
+}
+
+#[test]
+fn bound_functions_report_invalid_arguments() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define_fn("small", |x: u32| x);
+    assert_eq!(interpreter.eval_str("(small -0)").unwrap(), Value::Uint(0));
+    let e = bad_arguments(&mut interpreter, "(small 5000000000)");
+    assert!(matches!(e, CError::InvalidValue("u32", _)), "{}", e);
+    let e = bad_arguments(&mut interpreter, "(small -1)");
+    assert!(matches!(e, CError::InvalidValue("u32", Value::Int(-1))), "{}", e);
+}
+
+#[test]
+fn collections_and_tuples_are_converted() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define_fn("sum", |xs: Vec<i64>| xs.iter().sum::<i64>());
+    interpreter.define_fn("swap", |p: (u64, String)| (p.1, p.0));
+    interpreter.define_fn("total", |m: HashMap<String, u64>| m.values().sum::<u64>());
+    assert_eq!(interpreter.eval_str("(sum (list 1 -2 3))").unwrap(), Value::Int(2));
+    assert_eq!(interpreter.eval_str("(swap (cons 1 \"a\"))").unwrap().to_string(), "(\"a\" . 1)");
+    let r = interpreter.eval_str("(define d (make-dict)) (dict-set! d \"a\" 1) (dict-set! d \"b\" 2) (total d)");
+    assert_eq!(r.unwrap(), Value::Uint(3));
+    let e = bad_arguments(&mut interpreter, "(sum (list 1 'a))");
+    assert!(matches!(e, CError::ArgTypeError(0, _, _)), "{}", e);
+}
+
+#[test]
+fn optional_and_variadic_arguments() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define_fn("greet", |name: String, punct: Option<String>| {
+        format!("hi {}{}", name, punct.unwrap_or_default())
+    });
+    interpreter.define_fn("count", |first: u64, rest: Variadic<u64>| first + rest.0.len() as u64);
+    assert_eq!(interpreter.eval_str("(greet \"a\")").unwrap().to_string(), "\"hi a\"");
+    assert_eq!(interpreter.eval_str("(greet \"a\" \"!\")").unwrap().to_string(), "\"hi a!\"");
+    assert_eq!(interpreter.eval_str("(count 10 1 2 3)").unwrap(), Value::Uint(13));
+    assert_eq!(interpreter.eval_str("(count 10)").unwrap(), Value::Uint(10));
+    let e = bad_arguments(&mut interpreter, "(count)");
+    assert!(matches!(e, CError::ArgsNotMatching(..)), "{}", e);
+}
+
+#[test]
+fn returned_collections_become_values() {
+    assert_eq!(vec![1u64, 2].into_value().to_string(), "(vec 1 2)");
+    assert_eq!(Some(3i64).into_value(), Value::Int(3));
+    assert_eq!(None::<u64>.into_value(), Value::Nil);
+    assert_eq!(5usize.into_value(), Value::Uint(5));
+}
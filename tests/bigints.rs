@@ -0,0 +1,57 @@
+mod common;
+
+use c0i::value::Value;
+use c0i::value::result::CError;
+
+use common::{eval, runtime_err, show};
+
+
+#[test]
+fn large_literals_are_bigints() {
+    assert_eq!(show("18446744073709551616"), "18446744073709551616");
+    assert_eq!(eval("(bigint? 18446744073709551616)"), Value::Bool(true));
+    assert_eq!(eval("(bigint? 18446744073709551615)"), Value::Bool(false));
+    assert_eq!(eval("(bigint? -9223372036854775809)"), Value::Bool(true));
+    assert_eq!(show("'(1/3 4/2)"), "(1/3 2)");
+    assert_eq!(eval("(rational? 2/4)"), Value::Bool(true));
+}
+
+#[test]
+fn results_are_kept_in_the_smallest_type() {
+    assert_eq!(eval("(- 18446744073709551616 1)"), Value::Uint(u64::MAX));
+    assert_eq!(eval("(- 18446744073709551616 18446744073709551617)"), Value::Int(-1));
+    assert_eq!(eval("(+ 1/3 2/3)"), Value::Uint(1));
+    assert_eq!(eval("(exact? (* 1/2 4))"), Value::Bool(true));
+    assert_eq!(show("(* 18446744073709551616 18446744073709551616)"), "340282366920938463463374607431768211456");
+    assert_eq!(show("(/ 18446744073709551616 3)"), "18446744073709551616/3");
+    assert_eq!(show("(% 18446744073709551617 2)"), "1");
+}
+
+#[test]
+fn bigints_compare_with_other_numbers() {
+    assert_eq!(eval("(< 18446744073709551615 18446744073709551616 1e20)"), Value::Bool(true));
+    assert_eq!(eval("(= 18446744073709551616 (+ 18446744073709551615 1))"), Value::Bool(true));
+    assert_eq!(eval("(eqv? 18446744073709551616 (+ 18446744073709551615 1))"), Value::Bool(true));
+    assert_eq!(eval("(> 1/3 0.3)"), Value::Bool(true));
+}
+
+#[test]
+fn exact_and_inexact_numbers_convert() {
+    assert_eq!(eval("(exact->inexact 1/4)"), Value::Float(0.25));
+    assert_eq!(eval("(exact->inexact 18446744073709551616)"), Value::Float(18446744073709551616.0));
+    assert_eq!(show("(inexact->exact 0.5)"), "1/2");
+    assert_eq!(eval("(inexact->exact 2.0)"), Value::Uint(2));
+    assert_eq!(show("(string->number \"-18446744073709551616\")"), "-18446744073709551616");
+    assert_eq!(show("(string->number \"6/4\")"), "3/2");
+    assert_eq!(eval("(string->number \"1/0\")"), Value::Nil);
+    assert!(matches!(runtime_err("(inexact->exact (/ 1.0 0.0))"), CError::BadArguments("inexact->exact", _)));
+    assert!(matches!(runtime_err("(/ 18446744073709551616 0)"), CError::ZeroDivisionError));
+}
+
+#[test]
+fn only_the_generic_operators_promote() {
+    assert!(matches!(runtime_err("(+u 18446744073709551615 1)"), CError::OverflowError));
+    assert!(matches!(runtime_err("(*i -9223372036854775807 -2)"), CError::OverflowError));
+    assert!(matches!(runtime_err("(-u 0 1)"), CError::OverflowError));
+    assert!(matches!(runtime_err("(+u 18446744073709551616 1)"), CError::BadArguments("+u", _)));
+}
@@ -0,0 +1,48 @@
+use std::convert::TryInto;
+
+use pr47::data::Value;
+use pr47::vm::al31f::alloc::default_alloc::DefaultAlloc;
+use pr47::vm::al31f::executor::{create_vm_main_thread, vm_thread_run_function};
+use xjbutil::std_ext::ResultExt;
+use xjbutil::unchecked::UncheckedSendSync;
+
+use c0i::eval47::commons::CompiledProgram;
+use c0i::eval47::min_scope_analysis::AnalyseResult;
+
+
+pub fn func_id(analyse_result: &AnalyseResult, name: &str) -> usize {
+    (0..).find(|x| {
+        let func_name: String = analyse_result.functions.get_raw_key(*x, "ResolvedFunctionName")
+            .unwrap()
+            .clone()
+            .try_into()
+            .unwrap();
+        func_name == name
+    }).unwrap()
+}
+
+/// The values that the function `func_id` of `program` returns for `args` on the al31f VM.
+pub fn run(program: &CompiledProgram, func_id: usize, args: Vec<Value>) -> Vec<Value> {
+    match try_run(program, func_id, args) {
+        Some(ret) => ret,
+        None => panic!("the function {} raised an exception", func_id),
+    }
+}
+
+/// `run`, `None` if the function raised an exception.
+pub fn try_run(program: &CompiledProgram, func_id: usize, args: Vec<Value>) -> Option<Vec<Value>> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(async {
+            let mut vm_thread = create_vm_main_thread(DefaultAlloc::new(), program).await;
+            let ret = unsafe {
+                vm_thread_run_function::<DefaultAlloc, false>(
+                    UncheckedSendSync::new((&mut vm_thread, func_id, &args))
+                ).unwrap_no_debug().await.into_inner()
+            };
+            vm_thread.vm.finish().await;
+            ret.ok()
+        })
+}
@@ -0,0 +1,95 @@
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex};
+
+use c0i::{Error, Interpreter};
+use c0i::value::Value;
+use c0i::value::context::Host;
+use c0i::value::result::CError;
+
+#[cfg(feature = "c047")]
+pub mod eval47;
+
+/// A fresh directory for the files of one test.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("c0i-test-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn write_file(dir: &Path, name: &str, source: &str) -> PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, source).unwrap();
+    path
+}
+
+/// What the scripts write to their stdout.
+#[derive(Clone, Default)]
+pub struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    pub fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sends the stdout of the scripts of `interpreter` to the returned `Output`.
+pub fn capture(interpreter: &mut Interpreter, mut host: Host) -> Output {
+    let output = Output::default();
+    host.stdout = Mutex::new(Box::new(output.clone()));
+    interpreter.set_host(host);
+    output
+}
+
+pub fn eval(source: &str) -> Value {
+    match Interpreter::new().eval_str(source) {
+        Ok(v) => v,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/// The text of the value of `source`, as `displayln` writes it.
+pub fn show(source: &str) -> String {
+    eval(source).to_string()
+}
+
+pub fn eval_err(source: &str) -> Error {
+    match Interpreter::new().eval_str(source) {
+        Ok(v) => panic!("expected an error, got {}", v),
+        Err(e) => e,
+    }
+}
+
+/// The runtime error of `source`, without its backtrace.
+pub fn runtime_err(source: &str) -> CError {
+    match eval_err(source) {
+        Error::Runtime(e) => e.root().clone(),
+        e => panic!("expected a runtime error, got {}", e),
+    }
+}
+
+/// The root of the runtime error of `r`, without its backtrace.
+pub fn runtime_root(r: Result<Value, Error>) -> CError {
+    match r {
+        Err(Error::Runtime(e)) => e.root().clone(),
+        Err(e) => panic!("expected a runtime error, got {}", e),
+        Ok(v) => panic!("expected an error, got {}", v),
+    }
+}
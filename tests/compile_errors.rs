@@ -0,0 +1,79 @@
+mod common;
+
+use std::fs;
+
+use c0i::{Error, Interpreter};
+use c0i::error::CompilerError;
+
+use common::{eval_err, show, temp_dir, write_file};
+
+
+fn compile_errors(e: Error) -> Vec<CompilerError> {
+    match e {
+        Error::Compile(e) => e,
+        e => panic!("expected a compile error, got {}", e),
+    }
+}
+
+#[test]
+fn errors_are_located_in_their_file() {
+    let dir = temp_dir("located");
+    let path = write_file(&dir, "bad.scm", "(define x 1)\n  (lambda)\n");
+    let e = compile_errors(Interpreter::new().eval_file(&path).unwrap_err());
+    assert_eq!(e.len(), 1);
+    let pos = e[0].location().unwrap();
+    assert_eq!(pos.location.line, 2);
+    assert_eq!(pos.location.colum, 4);
+    assert_eq!(pos.source_line.as_deref(), Some("  (lambda)"));
+
+    let text = e[0].to_string();
+    assert!(text.contains(":2:4\""), "{}", text);
+    assert!(text.contains("\t|   (lambda)\n\t|    ^\n"), "{}", text);
+}
+
+#[test]
+fn the_source_line_is_kept_after_the_file_is_gone() {
+    let dir = temp_dir("file-gone");
+    let path = write_file(&dir, "gone.scm", "(lambda)");
+    let e = compile_errors(Interpreter::new().eval_file(&path).unwrap_err());
+    fs::remove_file(&path).unwrap();
+    assert!(e[0].to_string().contains("\t| (lambda)\n"), "{}", e[0]);
+}
+
+#[test]
+fn errors_in_strings_show_their_source_line() {
+    let e = compile_errors(eval_err("(define x 1)\n (lambda)"));
+    let pos = e[0].location().unwrap();
+    assert_eq!(pos.location.path.as_str(), "<eval>");
+    assert_eq!(pos.source_line.as_deref(), Some(" (lambda)"));
+    assert!(e[0].to_string().contains("\t|  (lambda)\n\t|   ^\n"), "{}", e[0]);
+}
+
+#[test]
+fn every_string_shows_its_own_lines() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(define x 1)\n(define y 2)\n(define z 3)").unwrap();
+    let e = compile_errors(interpreter.eval_str("\n\n(lambda)").unwrap_err());
+    assert_eq!(e[0].location().unwrap().source_line.as_deref(), Some("(lambda)"));
+}
+
+#[test]
+fn forms_without_symbols_are_located_at_the_enclosing_form() {
+    let e = compile_errors(eval_err("(define (f)\n  (let ((1 2)) 3))"));
+    let inner = e.iter().find(|x| x.to_string().contains("1 is not a symbol")).unwrap();
+    assert_eq!(inner.location().unwrap().location.line, 2);
+}
+
+#[test]
+fn every_error_of_a_form_is_reported() {
+    let e = compile_errors(eval_err("(cond (1) (2))"));
+    assert!(!e.is_empty());
+    assert!(e.iter().all(|x| x.location().is_some()));
+}
+
+#[test]
+fn quote_takes_one_datum() {
+    assert!(matches!(eval_err("(quote a b)"), Error::Compile(_)));
+    assert!(matches!(eval_err("(quote a . b)"), Error::Compile(_)));
+    assert_eq!(show("(car (quote (quote a)))"), "quote");
+}
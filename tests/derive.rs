@@ -0,0 +1,156 @@
+mod common;
+
+use c0i::{c0i_fn, FromValue, IntoValue, Interpreter};
+use c0i::value::Value;
+use c0i::value::autobind::{self, FromValue as _};
+use c0i::value::context::Context;
+use c0i::value::result::CError;
+
+use common::runtime_root;
+
+
+#[c0i_fn]
+fn add_two(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+#[c0i_fn(name = "string-repeat", module = "text", pure)]
+fn repeat(s: String, n: usize) -> String {
+    s.repeat(n)
+}
+
+#[c0i_fn]
+fn call_depth(ctx: &Context) -> usize {
+    ctx.depth
+}
+
+#[c0i_fn]
+fn depth_plus(ctx: &Context, n: usize) -> usize {
+    ctx.depth + n
+}
+
+#[c0i_fn]
+fn checked(n: u64) -> Result<u64, CError> {
+    n.checked_sub(1).ok_or(CError::OverflowError)
+}
+
+#[derive(Debug, Clone, PartialEq, FromValue, IntoValue)]
+struct Point {
+    x: i64,
+    #[c0i(rename = "y-coord")]
+    y: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, FromValue, IntoValue)]
+struct Pair(u64, String);
+
+#[derive(Debug, Clone, PartialEq, FromValue, IntoValue)]
+struct Meters(f64);
+
+#[derive(Debug, Clone, PartialEq, FromValue, IntoValue)]
+enum Shape {
+    Empty,
+    #[c0i(rename = "dot")]
+    Dot(Point),
+    Segment(Point, Point),
+    Circle { center: Point, radius: u64 },
+}
+
+#[test]
+fn natives_are_named_after_the_function() {
+    assert_eq!(ADD_TWO_NATIVE.name, "add-two");
+    assert_eq!(ADD_TWO_NATIVE.module, module_path!());
+    assert!(!ADD_TWO_NATIVE.is_pure);
+    assert_eq!(REPEAT_NATIVE.name, "string-repeat");
+    assert_eq!(REPEAT_NATIVE.module, "text");
+    assert!(REPEAT_NATIVE.is_pure);
+    // the function itself is kept
+    assert_eq!(add_two(1, 2), 3);
+}
+
+#[test]
+fn natives_check_their_arity() {
+    let mut interpreter = Interpreter::new();
+    interpreter.register(ADD_TWO_NATIVE);
+    interpreter.register(REPEAT_NATIVE);
+    assert_eq!(interpreter.eval_str("(add-two 1 -3)").unwrap(), Value::Int(-2));
+    assert_eq!(interpreter.eval_str("(string-repeat \"ab\" 2)").unwrap().to_string(), "\"abab\"");
+    let e = runtime_root(interpreter.eval_str("(add-two 1)"));
+    assert!(matches!(e, CError::BadArguments("add-two", ref e) if matches!(**e, CError::ArgsNotMatching(2, 1))), "{}", e);
+    let e = runtime_root(interpreter.eval_str("(string-repeat \"ab\" 'x)"));
+    assert!(matches!(e, CError::BadArguments("string-repeat", ref e) if matches!(**e, CError::ArgTypeError(1, ..))), "{}", e);
+}
+
+#[test]
+fn a_first_context_parameter_is_not_an_argument() {
+    let mut interpreter = Interpreter::new();
+    interpreter.register(CALL_DEPTH_NATIVE);
+    interpreter.register(DEPTH_PLUS_NATIVE);
+    assert!(matches!(interpreter.eval_str("(call-depth)"), Ok(Value::Uint(d)) if d > 0));
+    let top = interpreter.eval_str("(call-depth)").unwrap();
+    let nested = interpreter.eval_str("(define (f) (call-depth)) (+ 0 (f))").unwrap();
+    assert!(nested > top, "{} {}", nested, top);
+    assert!(matches!(interpreter.eval_str("(depth-plus 100)"), Ok(Value::Uint(d)) if d > 100));
+    let e = runtime_root(interpreter.eval_str("(call-depth 1)"));
+    assert!(matches!(e, CError::BadArguments("call-depth", ref e) if matches!(**e, CError::ArgsNotMatching(0, 1))), "{}", e);
+}
+
+#[test]
+fn errors_of_natives_are_raised() {
+    let mut interpreter = Interpreter::new();
+    interpreter.register(CHECKED_NATIVE);
+    assert_eq!(interpreter.eval_str("(checked 1)").unwrap(), Value::Uint(0));
+    assert!(matches!(runtime_root(interpreter.eval_str("(checked 0)")), CError::OverflowError));
+}
+
+fn round_trip<T>(x: T) -> Value
+    where T: autobind::FromValue + autobind::IntoValue + Clone + PartialEq + std::fmt::Debug
+{
+    let v = x.clone().into_value();
+    assert_eq!(T::from_value(v.clone()).unwrap(), x);
+    v
+}
+
+#[test]
+fn structs_round_trip() {
+    let v = round_trip(Point { x: 1, y: -2 });
+    assert!(matches!(v, Value::Dict(_)), "{}", v);
+    let v = round_trip(Pair(1, "a".to_string()));
+    assert_eq!(v.to_string(), "(vec 1 \"a\")");
+    let v = round_trip(Meters(1.5));
+    assert_eq!(v, Value::Float(1.5));
+}
+
+#[test]
+fn enums_round_trip() {
+    let p = Point { x: 0, y: 1 };
+    assert_eq!(round_trip(Shape::Empty).to_string(), "Empty");
+    assert!(round_trip(Shape::Dot(p.clone())).to_string().starts_with("(vec dot "));
+    round_trip(Shape::Segment(p.clone(), p.clone()));
+    round_trip(Shape::Circle { center: p, radius: 3 });
+}
+
+#[test]
+fn values_of_scripts_convert_into_derived_types() {
+    let mut interpreter = Interpreter::new();
+    let v = interpreter.eval_str("(define d (make-dict)) (dict-set! d \"x\" 3) (dict-set! d \"y-coord\" 4) d").unwrap();
+    assert_eq!(Point::from_value(v).unwrap(), Point { x: 3, y: 4 });
+    // symbols of the names of fields are keys too
+    let v = interpreter.eval_str("(define s (make-dict)) (dict-set! s 'x 5) (dict-set! s 'y-coord 6) s").unwrap();
+    assert_eq!(Point::from_value(v).unwrap(), Point { x: 5, y: 6 });
+    let v = interpreter.eval_str("(make-vector 'Segment d d)").unwrap();
+    let p = Point { x: 3, y: 4 };
+    assert_eq!(Shape::from_value(v).unwrap(), Shape::Segment(p.clone(), p));
+}
+
+#[test]
+fn values_of_another_shape_are_invalid() {
+    let mut interpreter = Interpreter::new();
+    assert!(matches!(Shape::from_value(Value::Uint(1)), Err(CError::InvalidValue("Shape", _))));
+    let v = interpreter.eval_str("(make-vector 'Unknown)").unwrap();
+    assert!(matches!(Shape::from_value(v), Err(CError::InvalidValue("Shape", _))));
+    let v = interpreter.eval_str("(make-vector 'Empty 1)").unwrap();
+    assert!(Shape::from_value(v).is_err());
+    assert!(Pair::from_value(Value::from(vec![Value::Uint(1)])).is_err());
+    assert!(Point::from_value(Value::Nil).is_err());
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use c0i::value::Value;
+use c0i::value::result::CError;
+
+use common::{eval, runtime_err, show};
+
+
+#[test]
+fn entries_are_set_and_read() {
+    let r = eval("(define d (make-dict)) (dict-set! d 'a 1) (dict-set! d 'a 2) (list (dict-ref d 'a) (dict-count d))");
+    assert_eq!(r.to_string(), "(2 1)");
+    assert_eq!(eval("(dict-ref (make-dict) 'a 0)"), Value::Uint(0));
+    assert!(matches!(runtime_err("(dict-ref (make-dict) 'a)"), CError::KeyNotFound(_)));
+    let r = eval("(define d (make-dict)) (dict-set! d 1 'x) (list (dict-remove! d 1) (dict-remove! d 1) (dict-has? d 1))");
+    assert_eq!(r.to_string(), "(x nil false)");
+}
+
+#[test]
+fn keys_of_the_same_value_are_the_same() {
+    // a uint and an int of the same value, or a bigint that fits
+    let r = eval("(define d (make-dict)) (dict-set! d 1 'a) (dict-set! d (- 2 1) 'b) (dict-set! d (- 18446744073709551617 18446744073709551616) 'c) (dict->alist d)");
+    assert_eq!(r.to_string(), "((1 . c))");
+    // but strings and symbols of a name are not
+    let r = eval("(define d (make-dict)) (dict-set! d \"a\" 1) (dict-set! d 'a 2) (dict-set! d \"1\" 3) (dict-set! d 1 4) (dict-count d)");
+    assert_eq!(r, Value::Uint(4));
+    assert_eq!(eval("(define d (make-dict)) (dict-set! d true 1) (dict-ref d (= 1 1))"), Value::Uint(1));
+}
+
+#[test]
+fn values_that_are_not_keys_are_rejected() {
+    assert!(matches!(runtime_err("(dict-set! (make-dict) 1.5 1)"), CError::BadArguments("dict-set!", _)));
+    assert!(matches!(runtime_err("(dict-set! (make-dict) (list 1) 1)"), CError::BadArguments("dict-set!", _)));
+    assert!(matches!(runtime_err("(dict-ref 1 1)"), CError::BadArguments("dict-ref", _)));
+}
+
+#[test]
+fn dicts_convert_to_and_from_alists() {
+    let r = show("(define d (alist->dict (list (cons 'a 1) (cons 'b 2) (cons 'a 3)))) (list (dict-ref d 'a) (dict-ref d 'b))");
+    assert_eq!(r, "(3 2)");
+    let r = eval("(define d (alist->dict (list (cons 'a 1) (cons 'b 2)))) (+ (dict-fold d (lambda (k v acc) (+ v acc)) 0) (length (dict-keys d)) (length (dict-values d)))");
+    assert_eq!(r, Value::Uint(7));
+}
+
+#[test]
+fn dicts_are_updated_and_merged() {
+    let r = eval("(define d (make-dict)) (dict-set! d 'n 1) (dict-update! d 'n (lambda (x) (+ x 1))) (dict-update! d 'm (lambda (x) (+ x 1)) 10) (list (dict-ref d 'n) (dict-ref d 'm))");
+    assert_eq!(r.to_string(), "(2 11)");
+    let r = eval("(define a (make-dict)) (dict-set! a 'x 1) (define b (make-dict)) (dict-set! b 'x 2) (dict-set! b 'y 3) (define m (dict-merge a b)) (list (dict-ref m 'x) (dict-count m) (dict-count a))");
+    assert_eq!(r.to_string(), "(2 2 1)");
+    let r = eval("(define d (make-dict)) (dict-set! d 'a 1) (define e (make-dict)) (dict-for-each d (lambda (k v) (dict-set! e v k))) (dict-ref e 1)");
+    assert_eq!(r.to_string(), "a");
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use c0i::Interpreter;
+use c0i::value::Value;
+
+use common::eval;
+
+
+fn is(source: &str) -> bool {
+    match eval(source) {
+        Value::Bool(x) => x,
+        v => panic!("expected a bool, got {}", v),
+    }
+}
+
+#[test]
+fn eq_compares_identity() {
+    assert!(is("(eq? 'a 'a)"));
+    assert!(is("(define l (list 1)) (eq? l l)"));
+    assert!(!is("(eq? (list 1) (list 1))"));
+    assert!(!is("(eq? \"a\" \"a\")"));
+    assert!(!is("(eq? 1 -0)"));
+    assert!(!is("(eq? (make-vector) (make-vector))"));
+}
+
+#[test]
+fn eqv_compares_numbers_by_value() {
+    assert!(is("(eqv? 1 (- 2 1))"));
+    assert!(is("(eqv? 18446744073709551616 (+ 18446744073709551615 1))"));
+    assert!(is("(eqv? 1/2 (/ 2 4))"));
+    assert!(!is("(eqv? 1 1.0)"));
+    assert!(!is("(eqv? \"a\" \"a\")"));
+}
+
+#[test]
+fn equal_compares_structure() {
+    assert!(is("(equal? \"a\" \"a\")"));
+    assert!(is("(equal? (list 1 (list 2 \"b\")) (list 1 (list 2 \"b\")))"));
+    assert!(!is("(equal? (list 1 2) (list 1 2 3))"));
+    assert!(is("(equal? (make-vector 1 (list 2)) (make-vector 1 (list 2)))"));
+    assert!(!is("(equal? (make-vector 1) (list 1))"));
+    // the order of dicts does not matter
+    assert!(is("(define a (make-dict)) (dict-set! a 'x 1) (dict-set! a 'y \"s\")
+                (define b (make-dict)) (dict-set! b 'y \"s\") (dict-set! b 'x 1) (equal? a b)"));
+    assert!(!is("(define a (make-dict)) (dict-set! a 'x 1) (define b (make-dict)) (dict-set! b 'x 2) (equal? a b)"));
+}
+
+#[test]
+fn long_lists_are_compared() {
+    let r = eval("(define (build n acc) (cond ((= n 0) acc) (else (build (- n 1) (cons n acc)))))
+                  (equal? (build 100000 nil) (build 100000 nil))");
+    assert_eq!(r, Value::Bool(true));
+}
+
+#[test]
+fn containers_that_hold_themselves_are_compared() {
+    let source = "
+        (define a (make-vector 1 nil)) (vec-set! a 1 a)
+        (define b (make-vector 1 nil)) (vec-set! b 1 b)
+        (define c (make-vector 2 nil)) (vec-set! c 1 c)";
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(source).unwrap();
+    let mut is = |source: &str| interpreter.eval_str(source).unwrap() == Value::Bool(true);
+    assert!(is("(equal? a b)"));
+    assert!(!is("(equal? a c)"));
+    assert!(is("(equal? a a)"));
+    // through dicts, and vectors that hold each other
+    assert!(is("(define d (make-dict)) (dict-set! d 'self d) (define e (make-dict)) (dict-set! e 'self e) (equal? d e)"));
+    assert!(is("(define x (make-vector 0 nil)) (define y (make-vector 0 x)) (vec-set! x 1 y) (equal? x (make-vector 0 y))"));
+
+    // and so is == of Rust
+    let a = interpreter.get_global("a").unwrap();
+    let b = interpreter.get_global("b").unwrap();
+    let c = interpreter.get_global("c").unwrap();
+    assert!(a == b);
+    assert!(a != c);
+}
@@ -0,0 +1,175 @@
+#![cfg(feature = "c047")]
+
+mod common;
+
+use std::sync::Arc;
+
+use pr47::data::Value;
+use pr47::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use pr47::vm::al31f::insc::Insc;
+use sexpr_ir::syntax::sexpr::parse;
+use xjbutil::unchecked::UnsafeFrom;
+
+use c0i::ast::TopLevel;
+use c0i::error::CompilerError;
+use c0i::eval47::builtins::{DISPLAY_BIND, number_bind_id};
+use c0i::eval47::compile::CompileContext;
+use c0i::eval47::min_scope_analysis::AnalyseContext;
+use c0i::sexpr_to_ast::FromSexpr;
+use c0i::value::Value as PreludeValue;
+
+use common::eval47::{func_id, try_run};
+
+
+fn top_levels(source: &str) -> Vec<TopLevel> {
+    let source = format!("(define (f a b c) {}) (define (application-start) (f true true true))", source);
+    parse(&source, Arc::new("<test>".to_string()))
+        .unwrap()
+        .iter()
+        .map(|x| TopLevel::from_sexpr(x).unwrap())
+        .collect()
+}
+
+fn analyse_context() -> AnalyseContext {
+    let mut context = AnalyseContext::new();
+    context.register_ffi("display", &DISPLAY_BIND);
+    context
+}
+
+/// The instructions of `source`, in which `(f a b c)` calls the builtins on parameters.
+fn compile(source: &str, f: impl FnOnce(&[Insc])) {
+    let top_levels = top_levels(source);
+    let context = analyse_context();
+    let mut analyse_result = context.min_scope_analyse(&top_levels).unwrap();
+    let result = CompileContext::new(&[], &[]).compile(&top_levels, &mut analyse_result);
+    f(&result.program().code)
+}
+
+fn count(code: &[Insc], f: impl Fn(&Insc) -> bool) -> usize {
+    code.iter().filter(|x| f(x)).count()
+}
+
+fn is_number_call(insc: &Insc, op: &str) -> bool {
+    matches!(insc, Insc::FFICallRtlc(id, ..) if *id == number_bind_id(op))
+}
+
+/// The value of `source` on the VM as a value of the prelude, `None` if it raised.
+fn on_vm(source: &str) -> Option<PreludeValue> {
+    let top_levels = top_levels(source);
+    let mut analyse_result = analyse_context().min_scope_analyse(&top_levels).unwrap();
+    let (ffi_functions, async_ffi_functions) = analyse_result.ffi_functions();
+    let result = CompileContext::new(&ffi_functions, &async_ffi_functions)
+        .compile(&top_levels, &mut analyse_result);
+    let f = func_id(&analyse_result, "f");
+    let ret = try_run(result.program(), f, vec![Value::new_bool(true); 3])?;
+    let value = unsafe {
+        match ValueTypeTag::unsafe_from((ret[0].vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
+            ValueTypeTag::Int => PreludeValue::Int(ret[0].vt_data.inner.int_value),
+            ValueTypeTag::Float => PreludeValue::Float(ret[0].vt_data.inner.float_value),
+            ValueTypeTag::Bool => PreludeValue::Bool(ret[0].vt_data.inner.bool_value),
+        }
+    };
+    Some(value)
+}
+
+#[test]
+fn arithmetic_folds_its_arguments() {
+    for &op in &["+", "*", "-", "/"] {
+        compile(&format!("({} a b c)", op), |code| {
+            assert_eq!(count(code, |x| is_number_call(x, op)), 2);
+        });
+    }
+}
+
+#[test]
+fn a_single_argument_is_applied_to_the_unit() {
+    compile("(- a)", |code| {
+        let i = code.iter().position(|x| matches!(x, Insc::MakeIntConst(0, _))).unwrap();
+        assert!(is_number_call(&code[i + 1], "-"));
+    });
+    compile("(/ a)", |code| {
+        assert!(code.iter().any(|x| matches!(x, Insc::MakeIntConst(1, _))));
+        assert!(code.iter().any(|x| is_number_call(x, "/")));
+    });
+    compile("(+)", |code| {
+        assert!(code.iter().any(|x| matches!(x, Insc::MakeIntConst(0, _))));
+    });
+}
+
+#[test]
+fn comparisons_chain() {
+    compile("(< a b c)", |code| {
+        assert_eq!(count(code, |x| is_number_call(x, "<")), 2);
+        let first = code.iter().position(|x| is_number_call(x, "<")).unwrap();
+        // the second comparison is skipped if the first fails
+        assert!(matches!(code[first + 1], Insc::JumpIfFalse(_, dest) if dest == first + 3));
+    });
+    compile("(= a b)", |code| {
+        assert_eq!(count(code, |x| is_number_call(x, "=")), 1);
+        assert_eq!(count(code, |x| matches!(x, Insc::JumpIfFalse(..))), 0);
+    });
+}
+
+#[test]
+fn numbers_larger_than_an_int_are_compile_errors() {
+    let top_levels = top_levels("(display 18446744073709551616 1/3)");
+    let e = match analyse_context().min_scope_analyse(&top_levels) {
+        Err(e) => e,
+        Ok(_) => panic!("expected compile errors"),
+    };
+    assert_eq!(e.len(), 2);
+    assert!(matches!(&e[0], CompilerError::Unsupported(None, e) if e.contains("18446744073709551616")), "{:?}", e);
+}
+
+#[test]
+fn exception_forms_are_compile_errors() {
+    let top_levels = top_levels("(try (display a) (catch (e) (display e)))");
+    let e = match analyse_context().min_scope_analyse(&top_levels) {
+        Err(e) => e,
+        Ok(_) => panic!("expected compile errors"),
+    };
+    assert_eq!(e.len(), 1);
+    assert!(matches!(&e[0], CompilerError::Unsupported(None, e) if e.contains("try-call")), "{:?}", e);
+}
+
+#[test]
+fn builtins_given_the_wrong_number_of_arguments_are_compile_errors() {
+    for &(source, takes, given) in &[("(%)", 3, 1), ("(-)", 2, 1), ("(< 1)", 3, 2), ("(not a b)", 2, 3)] {
+        let top_levels = top_levels(source);
+        let e = match analyse_context().min_scope_analyse(&top_levels) {
+            Err(e) => e,
+            Ok(_) => panic!("expected compile errors for {}", source),
+        };
+        assert_eq!(e.len(), 1);
+        assert!(
+            matches!(&e[0], CompilerError::InvalidExprLength(Some(_), a, b, _) if (*a, *b) == (takes, given)),
+            "{:?}", e
+        );
+    }
+}
+
+#[test]
+fn numbers_give_what_they_give_in_the_prelude() {
+    let sources = [
+        "(+ 1 2.5)", "(- 1 2)", "(* 3 -2.0)", "(- 5)", "(/ 6 3)", "(/ 1 4.0)", "(/ 0.5)",
+        "(% 7 -2)", "(% 7.5 2)", "(= 1 1.0)", "(= 1 1 2)", "(!= 1 1.0)", "(= true true)",
+        "(< 1 1.5 2)", "(> 2 1.5)", "(>= 2 2.0 1)", "(<= 1 0.5)",
+    ];
+    for source in &sources {
+        let vm = on_vm(source).unwrap_or_else(|| panic!("{} raised on the VM", source));
+        let prelude = common::eval(source);
+        assert!(vm.is_eqv(&prelude), "{} is {} on the VM and {} in the prelude", source, vm, prelude);
+    }
+}
+
+#[test]
+fn numbers_the_vm_can_not_hold_raise() {
+    // the prelude makes a rational or a bigint of the first ones
+    let sources = [
+        "(/ 7 2)", "(/ 2)", "(* 9223372036854775807 2)", "(- -9223372036854775807 2)",
+        "(/ 1 0)", "(% 1 0)", "(+ 1 true)", "(< 1 true)",
+    ];
+    for source in &sources {
+        assert!(on_vm(source).is_none(), "{} did not raise on the VM", source);
+    }
+}
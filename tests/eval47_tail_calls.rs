@@ -0,0 +1,86 @@
+#![cfg(feature = "c047")]
+
+mod common;
+
+use std::sync::Arc;
+
+use pr47::data::Value;
+use pr47::vm::al31f::insc::Insc;
+use sexpr_ir::syntax::sexpr::parse;
+
+use c0i::ast::TopLevel;
+use c0i::eval47::builtins::DISPLAY_BIND;
+use c0i::eval47::compile::{CompileContext, CompileResult};
+use c0i::eval47::min_scope_analysis::{AnalyseContext, AnalyseResult};
+use c0i::sexpr_to_ast::FromSexpr;
+
+use common::eval47::{func_id, run};
+
+
+fn compile(source: &str, f: impl FnOnce(&CompileResult, &AnalyseResult)) {
+    let top_levels = parse(source, Arc::new("<test>".to_string()))
+        .unwrap()
+        .iter()
+        .map(|x| TopLevel::from_sexpr(x).unwrap())
+        .collect::<Vec<_>>();
+    let mut context = AnalyseContext::new();
+    context.register_ffi("display", &DISPLAY_BIND);
+    let mut analyse_result = context.min_scope_analyse(&top_levels).unwrap();
+    let (ffi_functions, async_ffi_functions) = analyse_result.ffi_functions();
+    let result = CompileContext::new(&ffi_functions, &async_ffi_functions)
+        .compile(&top_levels, &mut analyse_result);
+    f(&result, &analyse_result)
+}
+
+#[test]
+fn tail_calls_to_known_functions_are_jumps() {
+    let source = "
+        (define (ping x) (cond ((= nil x) x) (else (pong x))))
+        (define (pong x) (ping nil))
+        (define (application-start) (ping true))";
+    compile(source, |result, analyse_result| {
+        let program = result.program();
+        assert!(!program.code.iter().any(|x| matches!(x, Insc::Call(..) | Insc::CallPtr(..))));
+
+        let ping = func_id(analyse_result, "ping");
+        let jumps_to_ping = program.code.iter()
+            .filter(|x| matches!(x, Insc::Jump(addr) if *addr == program.functions[ping].start_addr))
+            .count();
+        assert_eq!(jumps_to_ping, 2);
+
+        // the frame of a function is large enough for those it jumps into
+        let start = func_id(analyse_result, "application-start");
+        assert!(program.functions[start].stack_size >= program.functions[ping].stack_size);
+    });
+}
+
+#[test]
+fn calls_not_in_tail_position_push_frames() {
+    let source = "
+        (define (id x) x)
+        (define (application-start) (display (id true)) (id nil))";
+    compile(source, |result, analyse_result| {
+        let program = result.program();
+        let id = func_id(analyse_result, "id");
+        let calls = program.code.iter()
+            .filter(|x| matches!(x, Insc::Call(func_id, ..) if *func_id == id))
+            .count();
+        assert_eq!(calls, 1);
+    });
+}
+
+#[test]
+fn mutually_recursive_tail_calls_run_in_constant_space() {
+    // pong takes its arguments in the other order, so a jump has to move them into place
+    let source = "
+        (define (ping n acc) (cond ((= n 0) acc) (else (pong (+ acc 2) (- n 1)))))
+        (define (pong acc n) (ping n (- acc 1)))
+        (define (application-start) (ping 3 0))";
+    let mut ret = vec![];
+    compile(source, |result, analyse_result| {
+        let ping = func_id(analyse_result, "ping");
+        ret = run(result.program(), ping, vec![Value::new_int(1_000_000), Value::new_int(0)]);
+    });
+    assert_eq!(ret.len(), 1);
+    assert_eq!(unsafe { ret[0].vt_data.inner.int_value }, 1_000_000);
+}
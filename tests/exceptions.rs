@@ -0,0 +1,71 @@
+mod common;
+
+use c0i::Interpreter;
+use c0i::value::Value;
+use c0i::value::context::Host;
+use c0i::value::result::CError;
+
+use common::{capture, runtime_err, show};
+
+
+#[test]
+fn guard_hands_the_raised_value_to_its_clauses() {
+    assert_eq!(show("(guard (e ((str? e) e)) (raise \"boom\"))"), "\"boom\"");
+    assert_eq!(show("(guard (e ((eq? e 1) 'one) (else 'other)) (raise 2))"), "other");
+    assert_eq!(show("(guard (e (else 'never)) 5)"), "5");
+}
+
+#[test]
+fn guard_raises_again_when_no_clause_matches() {
+    let e = runtime_err("(guard (e ((eq? e 1) 'one)) (raise 2))");
+    assert!(matches!(e, CError::Raised(Value::Uint(2))), "{}", e);
+    assert_eq!(show("(guard (o (else (list 'outer o))) (guard (i ((eq? i 1) 'one)) (raise 2)))"), "(outer 2)");
+}
+
+#[test]
+fn runtime_errors_can_be_caught() {
+    assert_eq!(show("(try (car 1) (catch (e) (error-message e)))"), "\"car: argument 1 should be pair\"");
+    assert_eq!(show("(try (error \"bad\" 1 2) (catch (e) (error-irritants e)))"), "(1 2)");
+    assert_eq!(show("(guard (e (else (error-message e))) (error \"bad\"))"), "\"bad\"");
+}
+
+#[test]
+fn finally_runs_whether_the_body_fails_or_not() {
+    let mut interpreter = Interpreter::new();
+    let output = capture(&mut interpreter, Host::default());
+    let r = interpreter.eval_str("(try 5 (finally (displayln 'first)))").unwrap();
+    assert_eq!(r, Value::Uint(5));
+    let r = interpreter.eval_str("(try (raise 1) (catch (e) e) (finally (displayln 'second)))").unwrap();
+    assert_eq!(r, Value::Uint(1));
+    assert!(interpreter.eval_str("(try (raise 1) (finally (displayln 'third)))").is_err());
+    assert_eq!(output.text(), "first\nsecond\nthird\n");
+}
+
+#[test]
+fn handlers_of_raise_continuable_give_a_value_back() {
+    let source = "(with-exception-handler (lambda (e) (+ e 1)) (lambda () (+ 1 (raise-continuable 41))))";
+    assert_eq!(show(source), "43");
+}
+
+#[test]
+fn bindings_named_like_the_builtins_do_not_change_the_forms() {
+    let source = "
+        (define (k try-call raise dynamic-wind ignore) (guard (e (else 7)) (raise 1)))
+        (k 1 2 3 4)";
+    assert_eq!(show(source), "7");
+    // the raise of the body is the parameter, the one raising again is the builtin
+    let source = "
+        (define (g raise) (guard (e ((eq? e 2) 'two)) (raise 3)))
+        (guard (e (else 'outer)) (g 1))";
+    assert_eq!(show(source), "outer");
+    let source = "(define (f dynamic-wind) (try dynamic-wind (finally 1))) (f 2)";
+    assert_eq!(show(source), "2");
+}
+
+#[test]
+fn the_forms_work_without_the_builtins() {
+    let mut interpreter = Interpreter::without_builtins();
+    let r = interpreter.eval_str("(guard (e (else e)) (guard (e) (try (quote x) (finally 1))))").unwrap();
+    assert_eq!(r.to_string(), "x");
+    assert!(matches!(interpreter.eval_str("(guard (e) 1)"), Ok(Value::Uint(1))));
+}
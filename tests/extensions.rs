@@ -0,0 +1,163 @@
+mod common;
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use c0i::{Error, Interpreter};
+use c0i::extension::abi::{C0iValue, C0I_ABI_VERSION};
+use c0i::value::Value;
+use c0i::value::callable::Callable;
+use c0i::value::context::{Capability, Host};
+use c0i::value::result::CError;
+
+use common::{runtime_root, temp_dir};
+
+
+fn library_name(name: &str) -> String {
+    format!("{}{}{}", DLL_PREFIX, name, DLL_SUFFIX)
+}
+
+/// The example extension, built in a target directory of its own
+/// since the one of the tests is locked while they run.
+fn example_extension() -> &'static Path {
+    static PATH: OnceLock<PathBuf> = OnceLock::new();
+    PATH.get_or_init(|| {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let target = root.join("target").join("extension-tests");
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--quiet"])
+            .arg("--manifest-path")
+            .arg(root.join("examples").join("extension").join("Cargo.toml"))
+            .env("CARGO_TARGET_DIR", &target)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        target.join("debug").join(library_name("c0i_extension_example"))
+    })
+}
+
+/// A library made of `source` alone, for extensions that do not follow the ABI.
+fn fixture(name: &str, source: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    let file = dir.join("lib.rs");
+    std::fs::write(&file, source).unwrap();
+    let out = dir.join(library_name(name));
+    let status = Command::new("rustc")
+        .args(["--crate-type", "cdylib", "--edition", "2018", "-o"])
+        .arg(&out)
+        .arg(&file)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    out
+}
+
+fn extension_error<T>(r: Result<T, Error>) -> String {
+    match r {
+        Err(Error::Runtime(CError::ExtensionError(_, e))) => e.to_string(),
+        Err(e) => panic!("expected an extension error, got {}", e),
+        Ok(_) => panic!("expected an extension error"),
+    }
+}
+
+#[test]
+fn functions_of_an_extension_are_defined() {
+    let mut interpreter = Interpreter::new();
+    let name = interpreter.load_extension(example_extension()).unwrap();
+    assert_eq!(name.as_str(), "example");
+    let r = interpreter.eval_str("(greet \"world\")").unwrap();
+    assert_eq!(r.to_string(), "\"Hello, world!\"");
+    assert_eq!(interpreter.eval_str("(count 1 'a \"b\")").unwrap(), Value::Uint(3));
+    let e = runtime_root(interpreter.eval_str("(greet)"));
+    assert!(matches!(e, CError::BadArguments("greet", ref e) if matches!(**e, CError::ArgsNotMatching(1, 0))), "{}", e);
+}
+
+#[test]
+fn scripts_load_extensions_with_the_capability() {
+    let mut interpreter = Interpreter::new();
+    let source = format!("(load-extension {:?}) (checked-div -7 -2)", example_extension().to_string_lossy());
+    assert_eq!(interpreter.eval_str(&source).unwrap(), Value::Int(3));
+
+    let mut interpreter = Interpreter::new();
+    let mut host = Host::default();
+    host.capabilities.remove(&Capability::LoadExtensions);
+    interpreter.set_host(host);
+    let e = runtime_root(interpreter.eval_str(&source));
+    assert!(matches!(e, CError::NotPermitted(Capability::LoadExtensions)), "{}", e);
+}
+
+#[test]
+fn an_error_status_is_a_runtime_error() {
+    let mut interpreter = Interpreter::new();
+    interpreter.load_extension(example_extension()).unwrap();
+    // checked-div takes ints, the literals of which are negative
+    let e = runtime_root(interpreter.eval_str("(checked-div -1 -0)"));
+    match e {
+        CError::RuntimeError(Some(Value::Str(e)), _) => assert_eq!(e.as_str(), "checked-div: division by zero"),
+        e => panic!("{}", e),
+    }
+    let r = interpreter.eval_str("(try (greet 1) (catch (e) (error-message e)))").unwrap();
+    assert_eq!(r.to_string(), "\"greet: the name should be a string\"");
+}
+
+#[test]
+fn opaque_values_come_back_as_they_were_given() {
+    let mut interpreter = Interpreter::new();
+    interpreter.load_extension(example_extension()).unwrap();
+    let r = interpreter.eval_str("(define l (list 1 2)) (eq? (first l 'x) l)").unwrap();
+    assert_eq!(r, Value::Bool(true));
+    assert_eq!(interpreter.eval_str("(first 'sym)").unwrap().to_string(), "sym");
+    assert_eq!(interpreter.eval_str("(first \"s\" 1)").unwrap().to_string(), "\"s\"");
+    assert_eq!(interpreter.eval_str("(first -1.5)").unwrap(), Value::Float(-1.5));
+}
+
+#[test]
+fn names_are_kept_once_for_every_load() {
+    let name_of = |interpreter: &Interpreter| match interpreter.get_global("greet") {
+        Some(Value::Callable(Callable::Native(f))) => (f.name.as_ptr(), f.from_module.as_ptr()),
+        v => panic!("{:?}", v),
+    };
+    let a = Interpreter::new();
+    a.load_extension(example_extension()).unwrap();
+    let b = Interpreter::new();
+    b.load_extension(example_extension()).unwrap();
+    assert_eq!(name_of(&a), name_of(&b));
+}
+
+#[test]
+fn extensions_of_another_abi_version_are_rejected() {
+    let source = format!(
+        "#[no_mangle] pub extern \"C\" fn c0i_extension_abi_version() -> u32 {{ {} }}",
+        C0I_ABI_VERSION + 1
+    );
+    let path = fixture("abi-mismatch", &source);
+    let e = extension_error(Interpreter::new().load_extension(path));
+    assert!(e.contains(&format!("built for ABI version {}", C0I_ABI_VERSION + 1)), "{}", e);
+}
+
+#[test]
+fn booleans_are_read_from_any_byte() {
+    let mut v = C0iValue::boolean(false);
+    assert_eq!(v.as_bool(), Some(false));
+    // what an extension in C may put in the payload
+    v.payload.boolean = 2;
+    assert_eq!(v.as_bool(), Some(true));
+}
+
+#[test]
+fn libraries_without_the_symbols_are_rejected() {
+    let path = fixture("no-symbols", "#[no_mangle] pub extern \"C\" fn unrelated() {}");
+    extension_error(Interpreter::new().load_extension(path));
+
+    let source = format!(
+        "#[no_mangle] pub extern \"C\" fn c0i_extension_abi_version() -> u32 {{ {} }}",
+        C0I_ABI_VERSION
+    );
+    let path = fixture("no-init", &source);
+    let e = extension_error(Interpreter::new().load_extension(path));
+    assert!(e.contains("c0i_extension_init"), "{}", e);
+
+    extension_error(Interpreter::new().load_extension("/nonexistent/libnothing.so"));
+}
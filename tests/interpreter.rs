@@ -0,0 +1,79 @@
+mod common;
+
+use c0i::{Error, Interpreter};
+use c0i::value::Value;
+use c0i::value::result::CError;
+
+use common::{temp_dir, write_file};
+
+
+#[test]
+fn definitions_are_kept_between_evaluations() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(define x 40) (define (add y) (+ x y))").unwrap();
+    assert_eq!(interpreter.eval_str("(add 2)").unwrap(), Value::Uint(42));
+    assert_eq!(interpreter.get_global("x"), Some(Value::Uint(40)));
+    assert_eq!(interpreter.get_global("y"), None);
+}
+
+#[test]
+fn globals_of_the_host_are_seen_by_scripts() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define_global("limit", Value::Uint(3));
+    assert_eq!(interpreter.eval_str("(+ limit 1)").unwrap(), Value::Uint(4));
+    // a script may define it again
+    interpreter.eval_str("(define limit 5)").unwrap();
+    assert_eq!(interpreter.get_global("limit"), Some(Value::Uint(5)));
+}
+
+#[test]
+fn functions_of_scripts_are_called_by_name() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(define (pair a b) (list b a)) (define n 1)").unwrap();
+    let r = interpreter.call_function("pair", &[Value::Uint(1), Value::Uint(2)]).unwrap();
+    assert_eq!(r.to_string(), "(2 1)");
+
+    let e = interpreter.call_function("pair", &[]).unwrap_err();
+    assert!(matches!(e, Error::Runtime(ref e) if matches!(e.root(), CError::ArgsNotMatching(2, 0))), "{}", e);
+    let e = interpreter.call_function("n", &[]).unwrap_err();
+    assert!(matches!(e, Error::Runtime(CError::ValueIsNotCallable(Value::Uint(1)))), "{}", e);
+    let e = interpreter.call_function("missing", &[]).unwrap_err();
+    assert!(matches!(e, Error::Runtime(CError::SymbolNotFound(_))), "{}", e);
+}
+
+#[test]
+fn each_stage_has_its_error() {
+    let mut interpreter = Interpreter::new();
+    assert!(matches!(interpreter.eval_str("(car 1"), Err(Error::Parse(_))));
+    assert!(matches!(interpreter.eval_str("(lambda)"), Err(Error::Compile(_))));
+    assert!(matches!(interpreter.eval_str("(car 1)"), Err(Error::Runtime(_))));
+    // the interpreter is still usable
+    assert_eq!(interpreter.eval_str("(car '(1))").unwrap(), Value::Uint(1));
+}
+
+#[test]
+fn files_are_evaluated_with_their_directory_as_search_path() {
+    let dir = temp_dir("interpreter-file");
+    write_file(&dir, "lib.scm", "(export twice) (define (twice x) (* x 2))");
+    let main = write_file(&dir, "main.scm", "(import lib) (define r (twice 21)) r");
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.eval_file(&main).unwrap(), Value::Uint(42));
+    assert_eq!(interpreter.get_global("r"), Some(Value::Uint(42)));
+    assert!(matches!(interpreter.eval_file(dir.join("missing.scm")), Err(Error::Parse(_))));
+}
+
+#[test]
+fn interpreters_without_builtins_have_only_the_special_forms() {
+    let mut interpreter = Interpreter::without_builtins();
+    let e = interpreter.eval_str("(car '(1))").unwrap_err();
+    assert!(matches!(e, Error::Runtime(CError::SymbolNotFound(_))), "{}", e);
+    assert_eq!(interpreter.eval_str("(cond (true 'a) (else 'b))").unwrap().to_string(), "a");
+}
+
+#[test]
+fn interpreters_do_not_share_globals() {
+    let mut a = Interpreter::new();
+    let mut b = Interpreter::new();
+    a.eval_str("(define only-a 1)").unwrap();
+    assert!(b.eval_str("only-a").is_err());
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "json")]
+
+mod common;
+
+use c0i::value::Value;
+use c0i::value::result::CError;
+
+use common::{eval, runtime_err, show};
+
+
+#[test]
+fn json_is_read_into_values() {
+    assert_eq!(show("(json->value \"[1, -2, 1.5, null, true, \\\"a\\\"]\")"), "(vec 1 -2 1.5 nil true \"a\")");
+    assert_eq!(eval("(json->value \"18446744073709551615\")"), Value::Uint(u64::MAX));
+    let r = eval("(define d (json->value \"{\\\"a\\\": {\\\"b\\\": [1]}}\")) (vec-ref (dict-ref (dict-ref d \"a\") \"b\") 0)");
+    assert_eq!(r, Value::Uint(1));
+}
+
+#[test]
+fn values_are_written_as_json() {
+    assert_eq!(show("(value->json (list 1 -2 'a nil))"), "\"[1,-2,\"a\",null]\"");
+    assert_eq!(show("(define d (make-dict)) (dict-set! d \"k\" (make-vector 1.5)) (value->json d)"), "\"{\"k\":[1.5]}\"");
+    assert_eq!(show("(json-pretty (list 1))"), "\"[\n  1\n]\"");
+}
+
+#[test]
+fn json_round_trips() {
+    let r = eval("(define s \"{\\\"a\\\":[1,-2,{\\\"b\\\":null}]}\") (equal? (value->json (json->value s)) s)");
+    assert_eq!(r, Value::Bool(true));
+}
+
+#[test]
+fn malformed_json_reports_its_position() {
+    let e = runtime_err("(json->value \"[1,\\n 2\")");
+    assert!(matches!(e, CError::JsonError(_, 2, _)), "{}", e);
+    let r = show("(try (json->value \"{\") (catch (e) (error-irritants e)))");
+    assert_eq!(r, "(1 1)");
+}
+
+#[test]
+fn values_without_json_are_conversion_errors() {
+    assert!(matches!(runtime_err("(value->json car)"), CError::ConversionError(_)));
+    assert!(matches!(runtime_err("(value->json (cons 1 2))"), CError::ConversionError(_)));
+    assert!(matches!(runtime_err("(value->json (list (/ 1.0 0.0)))"), CError::ConversionError(_)));
+    assert!(matches!(runtime_err("(json->value 1)"), CError::BadArguments("json->value", _)));
+}
+
+#[test]
+fn keys_of_dicts_are_strings() {
+    assert_eq!(show("(define d (make-dict)) (dict-set! d 'a 1) (value->json d)"), "\"{\"a\":1}\"");
+    let e = runtime_err("(define d (make-dict)) (dict-set! d 1 1) (value->json d)");
+    assert!(matches!(&e, CError::ConversionError(m) if m.contains("key")), "{}", e);
+    // "1" and 1 or "a" and 'a would be the same key of an object
+    runtime_err("(define d (make-dict)) (dict-set! d 1 1) (dict-set! d \"1\" 2) (value->json d)");
+    let e = runtime_err("(define d (make-dict)) (dict-set! d 'a 1) (dict-set! d \"a\" 2) (value->json d)");
+    assert!(matches!(&e, CError::ConversionError(m) if m.contains("both as a string and as a symbol")), "{}", e);
+}
+
+#[test]
+fn containers_that_hold_themselves_are_conversion_errors() {
+    let e = runtime_err("(define v (make-vector 1 nil)) (vec-set! v 1 v) (value->json v)");
+    assert!(matches!(&e, CError::ConversionError(m) if m.contains("holds itself")), "{}", e);
+    let e = runtime_err("(define d (make-dict)) (dict-set! d \"self\" (list d)) (json-pretty d)");
+    assert!(matches!(&e, CError::ConversionError(_)), "{}", e);
+    assert_eq!(show("(define w (make-vector 1)) (value->json (make-vector w w))"), "\"[[1],[1]]\"");
+}
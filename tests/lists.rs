@@ -0,0 +1,104 @@
+mod common;
+
+use c0i::value::{Value, ValueType};
+use c0i::value::result::CError;
+
+use common::{eval, runtime_err, show};
+
+
+#[test]
+fn lists_are_built_and_read() {
+    assert_eq!(show("(list 1 2 3)"), "(1 2 3)");
+    assert_eq!(eval("(list)"), Value::Nil);
+    assert_eq!(eval("(length (list 1 2 3))"), Value::Uint(3));
+    assert_eq!(eval("(length nil)"), Value::Uint(0));
+    assert_eq!(show("(reverse (list 1 2 3))"), "(3 2 1)");
+    assert_eq!(eval("(list-ref (list 'a 'b) 1)").to_string(), "b");
+    assert_eq!(show("(list-tail (list 1 2 3) 1)"), "(2 3)");
+    assert_eq!(eval("(last (list 1 2 3))"), Value::Uint(3));
+}
+
+#[test]
+fn append_ends_with_its_last_argument() {
+    assert_eq!(show("(append (list 1) (list 2 3) nil (list 4))"), "(1 2 3 4)");
+    assert_eq!(show("(append (list 1 2) 3)"), "(1 2 . 3)");
+    assert_eq!(eval("(append)"), Value::Nil);
+    assert_eq!(show("(define l (list 1)) (eq? (append nil l) l)"), "true");
+}
+
+#[test]
+fn items_out_of_range_are_errors() {
+    assert!(matches!(runtime_err("(list-ref (list 1) 1)"), CError::IndexOutOfRange(1, 1)));
+    assert!(matches!(runtime_err("(list-tail (list 1) 2)"), CError::IndexOutOfRange(2, 1)));
+    assert!(matches!(runtime_err("(last nil)"), CError::BadArguments("last", _)));
+    assert!(matches!(runtime_err("(list-ref (list 1) -1)"), CError::IndexOutOfRange(-1, 1)));
+    assert!(matches!(runtime_err("(list-tail (list 1 2) -1)"), CError::IndexOutOfRange(-1, 2)));
+    assert!(matches!(runtime_err("(list-ref (list 1) 'a)"), CError::BadArguments("list-ref", _)));
+}
+
+#[test]
+fn indexes_may_be_ints() {
+    assert_eq!(eval("(+ -1 2)"), Value::Int(1));
+    assert_eq!(eval("(list-ref (list 'a 'b) (+ -1 2))").to_string(), "b");
+    assert_eq!(show("(list-tail (list 1 2 3) (+ -1 2))"), "(2 3)");
+    assert_eq!(show("(iota (+ -1 3))"), "(0 1)");
+    assert!(matches!(runtime_err("(iota -1)"), CError::BadArguments("iota", _)));
+}
+
+#[test]
+fn improper_lists_are_rejected() {
+    for source in ["(length (cons 1 2))", "(reverse (cons 1 2))", "(map car (cons 1 2))", "(member 3 (cons 1 2))"] {
+        assert!(matches!(runtime_err(source), CError::BadArguments(..)), "{}", source);
+    }
+    assert!(matches!(runtime_err("(append (cons 1 2) nil)"), CError::BadArguments("append", _)));
+}
+
+#[test]
+fn higher_order_functions() {
+    assert_eq!(show("(map (lambda (x) (* x x)) (list 1 2 3))"), "(1 4 9)");
+    assert_eq!(show("(map + (list 1 2 3) (list 10 20))"), "(11 22)");
+    assert_eq!(show("(filter (lambda (x) (> x 1)) (list 1 2 3))"), "(2 3)");
+    assert_eq!(show("(fold-left (lambda (acc x) (cons x acc)) nil (list 1 2 3))"), "(3 2 1)");
+    assert_eq!(show("(fold-right cons nil (list 1 2 3))"), "(1 2 3)");
+    assert_eq!(eval("(fold-left + 0 (list 1 2) (list 10 20))"), Value::Uint(33));
+    assert_eq!(show("(reduce (lambda (x acc) (list acc x)) 0 (list 1 2 3))"), "((1 2) 3)");
+    assert_eq!(eval("(reduce + 0 nil)"), Value::Uint(0));
+    let r = eval("(define d (make-dict)) (for-each (lambda (k v) (dict-set! d k v)) (list 'a 'b) (list 1 2)) (dict-ref d 'b)");
+    assert_eq!(r, Value::Uint(2));
+}
+
+#[test]
+fn errors_of_callbacks_are_not_bad_arguments() {
+    let e = runtime_err("(map (lambda (x) (error \"no\")) (list 1))");
+    assert!(matches!(e, CError::RuntimeError(..)), "{}", e);
+    let e = runtime_err("(filter (lambda (x) 1) (list 1))");
+    assert!(matches!(e, CError::TypeError(ValueType::Bool, Value::Uint(1))), "{}", e);
+    assert!(matches!(runtime_err("(map 1 (list 1))"), CError::BadArguments("map", _)));
+}
+
+#[test]
+fn items_are_searched() {
+    assert_eq!(show("(assoc \"b\" (list (cons \"a\" 1) (cons \"b\" 2)))"), "(\"b\" . 2)");
+    assert_eq!(eval("(assq \"b\" (list (cons \"a\" 1) (cons \"b\" 2)))"), Value::Nil);
+    assert_eq!(show("(assq 'b (list (cons 'a 1) (cons 'b 2)))"), "(b . 2)");
+    assert_eq!(show("(member (list 2) (list 1 (list 2) 3))"), "((2) 3)");
+    assert_eq!(eval("(member 4 (list 1 2 3))"), Value::Nil);
+    assert!(matches!(runtime_err("(assoc 1 (list 1))"), CError::BadArguments("assoc", _)));
+}
+
+#[test]
+fn iota_counts() {
+    assert_eq!(show("(iota 3)"), "(0 1 2)");
+    assert_eq!(show("(iota 3 1)"), "(1 2 3)");
+    assert_eq!(show("(iota 3 0 -2)"), "(0 -2 -4)");
+    assert_eq!(show("(iota 3 0 0.1)"), "(0 0.1 0.2)");
+    assert_eq!(eval("(iota 0)"), Value::Nil);
+    assert!(matches!(runtime_err("(iota 1 'a)"), CError::BadArguments("iota", _)));
+}
+
+#[test]
+fn lists_and_vectors_convert() {
+    assert_eq!(show("(list->vector (list 1 2))"), "(vec 1 2)");
+    assert_eq!(show("(vector->list (make-vector 1 2))"), "(1 2)");
+    assert_eq!(show("(list->vector nil)"), "(vec )");
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use c0i::Error;
+use c0i::value::result::CError;
+
+use common::{eval_err, runtime_err, show};
+
+
+const SWAP: &str = "
+    (define-syntax swap!
+      (syntax-rules ()
+        ((_ a b) (let ((tmp a)) (set! a b) (set! b tmp)))))";
+
+const EITHER: &str = "
+    (define-syntax either
+      (syntax-rules ()
+        ((_ a b) (let ((t a)) (cond (t t) (else b))))))";
+
+const INC: &str = "(define-syntax inc (syntax-rules () ((_ a) (+ a 1))))";
+
+#[test]
+fn syntax_rules_match_and_repeat() {
+    let source = "
+        (define-syntax my-or
+          (syntax-rules ()
+            ((_) false)
+            ((_ e) e)
+            ((_ e r ...) (let ((t e)) (cond (t t) (else (my-or r ...)))))))
+        (list (my-or) (my-or false 2) (my-or false false))";
+    assert_eq!(show(source), "(false 2 false)");
+}
+
+#[test]
+fn binders_of_a_template_do_not_capture_user_variables() {
+    let source = format!("{} (define t 5) (either false t)", EITHER);
+    assert_eq!(show(&source), "5");
+    let source = format!("{} (let ((t 5)) (either false t))", EITHER);
+    assert_eq!(show(&source), "5");
+}
+
+#[test]
+fn free_symbols_of_a_template_see_the_definition_environment() {
+    let source = format!("{} (define (h +) (inc 5)) (h -)", INC);
+    assert_eq!(show(&source), "6");
+    // the user binder is renamed, not lost
+    let source = format!("{} (define (h +) (list (inc 5) (+ 5 1))) (h -)", INC);
+    assert_eq!(show(&source), "(6 4)");
+    let source = format!("{} (let ((+ -)) (inc 5))", INC);
+    assert_eq!(show(&source), "6");
+}
+
+#[test]
+fn local_macros_see_the_variables_around_their_definition() {
+    let source = "
+        (define (f x)
+          (let-syntax ((get (syntax-rules () ((_) x))))
+            (let ((x 2)) (list x (get)))))
+        (f 1)";
+    assert_eq!(show(source), "(2 1)");
+    let source = "
+        (define (g x)
+          (define-syntax twice (syntax-rules () ((_) (+ x x))))
+          (twice))
+        (g 4)";
+    assert_eq!(show(source), "8");
+}
+
+#[test]
+fn macro_uses_in_quasiquoted_data_are_not_expanded() {
+    let source = format!("{} (define x 1) (define y 2) `(swap! x y)", SWAP);
+    assert_eq!(show(&source), "(swap! x y)");
+    let source = format!("{} `(1 ,(inc 1) (inc 1))", INC);
+    assert_eq!(show(&source), "(1 2 (inc 1))");
+    let source = format!("{} `(1 `(,(inc 1) ,,(inc 1)))", INC);
+    assert_eq!(show(&source), "(1 (quasiquote ((unquote (inc 1)) (unquote 2))))");
+}
+
+#[test]
+fn quasiquotes_in_templates_give_the_original_symbols() {
+    let source = "
+        (define-syntax tag (syntax-rules () ((_ v) (let ((tmp v)) `(tmp ,tmp)))))
+        (tag 3)";
+    assert_eq!(show(source), "(tmp 3)");
+}
+
+#[test]
+fn macros_can_define_macros() {
+    let source = "
+        (define-syntax def-const
+          (syntax-rules ()
+            ((_ name v) (define-syntax name (syntax-rules () ((_) v))))))
+        (def-const five 5)
+        (five)";
+    assert_eq!(show(source), "5");
+}
+
+#[test]
+fn procedural_macros_build_forms() {
+    let source = "
+        (define-macro (unless c body) (list 'cond (list c nil) (list 'else body)))
+        (unless false 2)";
+    assert_eq!(show(source), "2");
+}
+
+#[test]
+fn a_use_matching_no_rule_is_a_compile_error() {
+    let e = eval_err(&format!("{} (inc)", INC));
+    assert!(matches!(e, Error::Compile(_)), "{}", e);
+}
+
+#[test]
+fn macroexpand_takes_a_runtime_value() {
+    assert_eq!(show(&format!("{} (macroexpand '(inc 5))", INC)), "(+ 5 1)");
+    assert_eq!(show(&format!("{} (define form (list 'inc 'x)) (macroexpand form)", INC)), "(+ x 1)");
+    assert_eq!(show(&format!("{} (macroexpand '(list (inc 1) 'a))", INC)), "(list (+ 1 1) (quote a))");
+    assert_eq!(show("(macroexpand 1)"), "1");
+    let e = runtime_err("(macroexpand (lambda () 1))");
+    assert!(matches!(e, CError::CompileError(_)), "{}", e);
+}
+
+#[test]
+fn eval_sees_the_macros_of_the_interpreter() {
+    assert_eq!(show(&format!("{} (eval '(inc 1))", INC)), "2");
+    assert_eq!(show("(eval (quote (define-syntax two (syntax-rules () ((_) 2))))) (two)"), "2");
+    // while the expander is busy, a procedural macro evaluates with a fresh one
+    assert_eq!(show("(define-macro (three) (eval '(+ 1 2))) (three)"), "3");
+}
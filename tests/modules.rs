@@ -0,0 +1,187 @@
+mod common;
+
+use c0i::{Error, Interpreter};
+use c0i::value::Value;
+use c0i::value::context::{Capability, Host};
+use c0i::value::result::CError;
+
+use common::{capture, runtime_root, temp_dir, write_file};
+
+
+fn run_file(interpreter: &mut Interpreter, dir: &std::path::Path, source: &str) -> Result<Value, Error> {
+    let main = write_file(dir, "main.scm", source);
+    interpreter.eval_file(main)
+}
+
+#[test]
+fn import_binds_the_definitions_of_a_module() {
+    let dir = temp_dir("import-binds");
+    write_file(&dir, "geometry.scm", "
+        (define (square x) (* x x))
+        (define unit 1)");
+    let mut interpreter = Interpreter::new();
+    let r = run_file(&mut interpreter, &dir, "(import geometry) (+ (square 3) unit)");
+    assert_eq!(r.unwrap(), Value::Uint(10));
+}
+
+#[test]
+fn modules_are_evaluated_once() {
+    let dir = temp_dir("import-once");
+    write_file(&dir, "counter.scm", "(displayln (quote loading)) (define count 0)");
+    write_file(&dir, "user.scm", "(import counter) (define used count)");
+    let mut interpreter = Interpreter::new();
+    let output = capture(&mut interpreter, Host::default());
+    run_file(&mut interpreter, &dir, "(import counter) (import user) (import counter)").unwrap();
+    assert_eq!(output.text(), "loading\n");
+}
+
+#[test]
+fn modules_do_not_see_the_globals_of_the_importer() {
+    let dir = temp_dir("import-private");
+    write_file(&dir, "peek.scm", "(define (peek) secret)");
+    let mut interpreter = Interpreter::new();
+    let r = run_file(&mut interpreter, &dir, "(define secret 1) (import peek) (peek)");
+    assert!(matches!(runtime_root(r), CError::SymbolNotFound(x) if x.0.as_str() == "secret"));
+}
+
+#[test]
+fn missing_module_is_an_error() {
+    let dir = temp_dir("import-missing");
+    let mut interpreter = Interpreter::new();
+    let r = run_file(&mut interpreter, &dir, "(import nowhere)");
+    assert!(matches!(runtime_root(r), CError::ModuleNotFound(x) if x.0.as_str() == "nowhere"));
+}
+
+#[test]
+fn circular_import_is_an_error() {
+    let dir = temp_dir("import-circular");
+    write_file(&dir, "ping.scm", "(import pong) (define ping 1)");
+    write_file(&dir, "pong.scm", "(import ping) (define pong 2)");
+    let mut interpreter = Interpreter::new();
+    let r = run_file(&mut interpreter, &dir, "(import ping)");
+    assert!(matches!(runtime_root(r), CError::CircularImport(x) if x.0.as_str() == "ping"));
+}
+
+#[test]
+fn broken_module_is_an_error() {
+    let dir = temp_dir("import-broken");
+    write_file(&dir, "unclosed.scm", "(define (f x) x");
+    write_file(&dir, "invalid.scm", "(define)");
+    let mut interpreter = Interpreter::new();
+    let r = run_file(&mut interpreter, &dir, "(import unclosed)");
+    assert!(matches!(runtime_root(r), CError::InvalidModule(x, _) if x.0.as_str() == "unclosed"));
+    let r = run_file(&mut interpreter, &dir, "(import invalid)");
+    assert!(matches!(runtime_root(r), CError::InvalidModule(x, _) if x.0.as_str() == "invalid"));
+}
+
+fn namespaces_dir(name: &str) -> std::path::PathBuf {
+    let dir = temp_dir(name);
+    write_file(&dir, "m1.scm", "
+        (export a b)
+        (define a 1)
+        (define b 2)
+        (define hidden 3)");
+    write_file(&dir, "m2.scm", "(define a 10) (define b 20)");
+    write_file(&dir, "m3.scm", "(import m2) (define c 30)");
+    dir
+}
+
+fn is_defined(interpreter: &mut Interpreter, dir: &std::path::Path, imports: &str, name: &str) -> bool {
+    let source = format!("{} (try-call (lambda () {} true) (lambda (e) false))", imports, name);
+    run_file(interpreter, dir, &source).unwrap() == Value::Bool(true)
+}
+
+#[test]
+fn export_lists_limit_what_a_module_gives() {
+    let dir = namespaces_dir("export-list");
+    let mut interpreter = Interpreter::new();
+    let r = run_file(&mut interpreter, &dir, "(import m1) (+ a b)");
+    assert_eq!(r.unwrap(), Value::Uint(3));
+    assert!(!is_defined(&mut interpreter, &dir, "(import m1)", "hidden"));
+    assert!(!is_defined(&mut interpreter, &dir, "(import m1)", "m1/hidden"));
+}
+
+#[test]
+fn import_sets_select_and_rename() {
+    let dir = namespaces_dir("import-sets");
+    let mut interpreter = Interpreter::new();
+    let r = run_file(&mut interpreter, &dir, "(import (prefix m2 m2:)) (+ m2:a m2:b)");
+    assert_eq!(r.unwrap(), Value::Uint(30));
+    let r = run_file(&mut interpreter, &dir, "(import (rename m2 (a x))) x");
+    assert_eq!(r.unwrap(), Value::Uint(10));
+
+    let mut interpreter = Interpreter::new();
+    assert!(is_defined(&mut interpreter, &dir, "(import (only m2 a))", "a"));
+    assert!(!is_defined(&mut interpreter, &dir, "", "b"));
+    let mut interpreter = Interpreter::new();
+    assert!(!is_defined(&mut interpreter, &dir, "(import (except m2 a))", "a"));
+    assert!(is_defined(&mut interpreter, &dir, "", "b"));
+}
+
+#[test]
+fn qualified_names_follow_the_import_set() {
+    let dir = namespaces_dir("qualified");
+    let mut interpreter = Interpreter::new();
+    let r = run_file(&mut interpreter, &dir, "(import m2) (define a 0) (+ a m2/a)");
+    assert_eq!(r.unwrap(), Value::Uint(10));
+
+    let mut interpreter = Interpreter::new();
+    assert!(!is_defined(&mut interpreter, &dir, "(import (only m2))", "m2/a"));
+    let mut interpreter = Interpreter::new();
+    assert!(is_defined(&mut interpreter, &dir, "(import (only m2 b))", "m2/b"));
+    assert!(!is_defined(&mut interpreter, &dir, "", "m2/a"));
+    let mut interpreter = Interpreter::new();
+    assert!(is_defined(&mut interpreter, &dir, "(import (rename m2 (a x)))", "m2/a"));
+}
+
+#[test]
+fn implicit_exports_leave_out_imports() {
+    let dir = namespaces_dir("implicit-exports");
+    let mut interpreter = Interpreter::new();
+    assert!(is_defined(&mut interpreter, &dir, "(import m3)", "m3/c"));
+    assert!(!is_defined(&mut interpreter, &dir, "", "m3/a"));
+    assert!(!is_defined(&mut interpreter, &dir, "", "m3/m2/a"));
+    assert!(!is_defined(&mut interpreter, &dir, "", "a"));
+}
+
+#[test]
+fn eval_and_load_share_the_modules_of_the_interpreter() {
+    let dir = temp_dir("import-shared");
+    write_file(&dir, "counter.scm", "(displayln (quote loading)) (define count 0)");
+    write_file(&dir, "loaded.scm", "(import counter) (define from-load count)");
+    let mut interpreter = Interpreter::new();
+    interpreter.add_search_path(&dir);
+    let output = capture(&mut interpreter, Host::default());
+    interpreter.eval_str("(import counter)").unwrap();
+    interpreter.eval_str("(eval '(import counter))").unwrap();
+    let load = format!("(load {:?})", dir.join("loaded.scm").to_string_lossy());
+    interpreter.eval_str(&load).unwrap();
+    assert_eq!(output.text(), "loading\n");
+    assert_eq!(interpreter.get_global("from-load"), Some(Value::Uint(0)));
+}
+
+#[test]
+fn importing_needs_the_capability_to_read_files() {
+    let dir = temp_dir("import-denied");
+    write_file(&dir, "secret.scm", "(define secret 42)");
+    let mut interpreter = Interpreter::new();
+    interpreter.add_search_path(&dir);
+    let mut host = Host::default();
+    host.capabilities.remove(&Capability::ReadFiles);
+    interpreter.set_host(host);
+    for source in ["(import secret)", "(eval '(import secret))"] {
+        let e = interpreter.eval_str(source).unwrap_err();
+        assert!(matches!(runtime_root(Err(e)), CError::NotPermitted(Capability::ReadFiles)), "{}", source);
+    }
+    assert_eq!(interpreter.get_global("secret"), None);
+
+    // modules loaded before are not read again, but the import still needs the capability
+    let mut interpreter = Interpreter::new();
+    interpreter.add_search_path(&dir);
+    interpreter.eval_str("(import secret)").unwrap();
+    let mut host = Host::default();
+    host.capabilities.clear();
+    interpreter.set_host(host);
+    let r = interpreter.eval_str("(import secret)");
+    assert!(matches!(runtime_root(r), CError::NotPermitted(Capability::ReadFiles)));
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use c0i::Interpreter;
+use c0i::value::{Value, ValueType};
+use c0i::value::autobind::{bind_closure, scope_register_closure};
+use c0i::value::context::{Capability, Host, current_host};
+use c0i::value::result::CError;
+
+use common::{runtime_root, temp_dir, write_file};
+
+
+#[test]
+fn natives_keep_the_state_they_capture() {
+    let counter = Arc::new(AtomicU64::new(0));
+    let mut interpreter = Interpreter::new();
+    let c = counter.clone();
+    interpreter.define_native("tick", move |args| {
+        let n = c.fetch_add(args.len() as u64 + 1, Ordering::SeqCst);
+        Ok(Value::Uint(n))
+    });
+    let r = interpreter.eval_str("(tick) (tick 'a) (tick)").unwrap();
+    assert_eq!(r, Value::Uint(3));
+    assert_eq!(counter.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn context_natives_see_the_host() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_host(Host {
+        user_data: Some(Arc::new(String::from("config"))),
+        ..Host::default()
+    });
+    interpreter.define_context_native("config", |ctx, _| {
+        let data = ctx.user_data::<String>().cloned().unwrap_or_default();
+        Ok(Value::Str(data.into()))
+    });
+    assert_eq!(interpreter.eval_str("(config)").unwrap().to_string(), "\"config\"");
+}
+
+#[test]
+fn closures_are_bound_with_converted_arguments() {
+    let mut interpreter = Interpreter::new();
+    let offset = 10;
+    interpreter.define_fn("shift", move |x: u64| x + offset);
+    interpreter.define_fn("checked-div", |a: u64, b: u64| a.checked_div(b).ok_or(CError::ZeroDivisionError));
+    assert_eq!(interpreter.eval_str("(shift 5)").unwrap(), Value::Uint(15));
+    assert_eq!(interpreter.eval_str("(checked-div 9 3)").unwrap(), Value::Uint(3));
+
+    let e = runtime_root(interpreter.eval_str("(checked-div 1 0)"));
+    assert!(matches!(e, CError::ZeroDivisionError), "{}", e);
+    let e = runtime_root(interpreter.eval_str("(shift 'a)"));
+    match e {
+        CError::BadArguments("shift", e) =>
+            assert!(matches!(&*e, CError::ArgTypeError(0, ValueType::Uint, _)), "{}", e),
+        e => panic!("{}", e),
+    }
+    let e = runtime_root(interpreter.eval_str("(shift)"));
+    assert!(e.to_string().contains("'shift takes 1 arguments but 0"), "{}", e);
+}
+
+#[test]
+fn closures_registered_with_the_builtins_are_seen_by_modules() {
+    let dir = temp_dir("native-modules");
+    write_file(&dir, "lib.scm", "(export greet) (define (greet) (host-name))");
+    let main = write_file(&dir, "main.scm", "(import lib) (greet)");
+    let mut interpreter = Interpreter::new();
+    {
+        let mut builtins = interpreter.builtins().this_level.0.write().unwrap();
+        scope_register_closure(&mut builtins, "host", "host-name", bind_closure(|| "c0i"));
+    }
+    assert_eq!(interpreter.eval_file(&main).unwrap().to_string(), "\"c0i\"");
+}
+
+#[test]
+fn closure_natives_are_not_pure() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define_native("answer", |_| Ok(Value::Uint(42)));
+    match interpreter.get_global("answer") {
+        Some(Value::Callable(c)) => assert!(format!("{:?}", c).contains("is_pure: false")),
+        v => panic!("{:?}", v),
+    }
+    assert_eq!(interpreter.eval_str("(answer)").unwrap(), Value::Uint(42));
+}
+
+#[test]
+fn a_panicking_native_leaves_the_host_and_the_depth_as_they_were() {
+    let mut interpreter = Interpreter::new();
+    let mut host = Host::default();
+    host.capabilities.clear();
+    host.max_depth = Some(50);
+    interpreter.set_host(host);
+    interpreter.define_native("boom", |_| panic!("boom"));
+    interpreter.eval_str("
+        (define (deep n f) (cond ((= n 0) (f)) (else (+ 1 (deep (- n 1) f)))))
+        (define (zero) 0)").unwrap();
+
+    let r = catch_unwind(AssertUnwindSafe(|| interpreter.eval_str("(deep 40 boom)")));
+    assert!(r.is_err());
+    assert!(current_host().capabilities.contains(&Capability::ReadFiles));
+    // the calls that were in progress when it panicked are not counted anymore
+    assert_eq!(interpreter.eval_str("(deep 40 zero)").unwrap(), Value::Uint(40));
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use c0i::value::{Value, ValueType};
+use c0i::value::result::CError;
+
+use common::{eval, runtime_err, show};
+
+
+#[test]
+fn uints_ints_and_floats_mix() {
+    assert_eq!(eval("(+ 1 2)"), Value::Uint(3));
+    assert_eq!(eval("(+ 1 -2)"), Value::Int(-1));
+    assert_eq!(eval("(+ 3 -2)"), Value::Int(1));
+    assert_eq!(eval("(- 1 2)"), Value::Int(-1));
+    assert_eq!(eval("(* 2 1.5)"), Value::Float(3.0));
+    assert_eq!(eval("(+ 1 -2 0.5)"), Value::Float(-0.5));
+}
+
+#[test]
+fn operators_take_any_number_of_arguments() {
+    assert_eq!(eval("(+)"), Value::Uint(0));
+    assert_eq!(eval("(*)"), Value::Uint(1));
+    assert_eq!(eval("(+ 1 2 3 4)"), Value::Uint(10));
+    assert_eq!(eval("(- 10 1 2)"), Value::Uint(7));
+    assert_eq!(eval("(- 1)"), Value::Int(-1));
+    assert_eq!(eval("(- 1.5)"), Value::Float(-1.5));
+    assert_eq!(eval("(/ 2)"), eval("(/ 1 2)"));
+    assert_eq!(eval("(/ 12 2 3)"), Value::Uint(2));
+    assert!(matches!(runtime_err("(-)"), CError::BadArguments("-", _)));
+    assert!(matches!(runtime_err("(% 1)"), CError::BadArguments("%", _)));
+}
+
+#[test]
+fn integers_that_overflow_become_bigints() {
+    assert_eq!(show("(+ 18446744073709551615 1)"), "18446744073709551616");
+    assert_eq!(show("(* 4294967296 4294967296)"), "18446744073709551616");
+    assert_eq!(show("(- -9223372036854775807 2)"), "-9223372036854775809");
+    assert_eq!(eval("(- (+ 18446744073709551615 1) 1)"), Value::Uint(u64::MAX));
+}
+
+#[test]
+fn division_is_exact() {
+    assert_eq!(eval("(/ 6 3)"), Value::Uint(2));
+    assert_eq!(eval("(/ -6 3)"), Value::Int(-2));
+    assert_eq!(show("(/ 1 3)"), "1/3");
+    assert_eq!(eval("(* (/ 1 3) 3)"), Value::Uint(1));
+    assert_eq!(eval("(/ 1 2.0)"), Value::Float(0.5));
+    assert_eq!(eval("(% -7 2)"), Value::Int(-1));
+    assert!(matches!(runtime_err("(/ 1 0)"), CError::ZeroDivisionError));
+    assert!(matches!(runtime_err("(% 1 0)"), CError::ZeroDivisionError));
+    assert_eq!(eval("(/ 1 0.0)"), Value::Float(f64::INFINITY));
+}
+
+#[test]
+fn comparisons_chain_and_compare_values() {
+    assert_eq!(eval("(< 1 2 3)"), Value::Bool(true));
+    assert_eq!(eval("(< 1 3 2)"), Value::Bool(false));
+    assert_eq!(eval("(<= 1 1 -0.5)"), Value::Bool(false));
+    assert_eq!(eval("(>= 2 2.0 -1)"), Value::Bool(true));
+    assert_eq!(eval("(= 1 -0 1.0)"), Value::Bool(false));
+    assert_eq!(eval("(= 1 1.0 (/ 2 2))"), Value::Bool(true));
+    assert_eq!(eval("(< (/ 1 3) 0.34)"), Value::Bool(true));
+    assert_eq!(eval("(define nan (/ 0.0 0.0)) (or (< nan 1) (= nan nan))"), Value::Bool(false));
+    assert!(matches!(runtime_err("(< 1)"), CError::BadArguments("<", _)));
+}
+
+#[test]
+fn equality_takes_any_value() {
+    assert_eq!(eval("(= 'a 'a)"), Value::Bool(true));
+    assert_eq!(eval("(= 1 'a)"), Value::Bool(false));
+    assert_eq!(eval("(!= nil 1)"), Value::Bool(true));
+    assert_eq!(eval("(define l (list 1)) (= l l)"), Value::Bool(true));
+    assert_eq!(eval("(= (list 1) (list 1))"), Value::Bool(false));
+}
+
+#[test]
+fn arithmetic_on_other_values_is_a_type_error() {
+    match runtime_err("(+ 1 'a)") {
+        CError::BadArguments("+", e) => assert!(matches!(*e, CError::ArgTypeError(1, ValueType::Number, _)), "{}", e),
+        e => panic!("{}", e),
+    }
+    assert!(matches!(runtime_err("(< 1 \"a\")"), CError::BadArguments("<", _)));
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use c0i::Interpreter;
+
+use common::show;
+
+
+#[test]
+fn unquote_evaluates_in_the_template() {
+    assert_eq!(show("(define x 2) `(1 ,x ,(+ x 1))"), "(1 2 3)");
+    assert_eq!(show("`(a (b ,(car '(c d))) e)"), "(a (b c) e)");
+    assert_eq!(show("`x"), "x");
+}
+
+#[test]
+fn unquote_splicing_inserts_the_items() {
+    assert_eq!(show("(define l '(2 3)) `(1 ,@l 4)"), "(1 2 3 4)");
+    assert_eq!(show("`(,@'() 1)"), "(1)");
+    assert_eq!(show("`(1 ,@'(2 3))"), "(1 2 3)");
+}
+
+#[test]
+fn unquote_in_the_tail_of_a_list() {
+    assert_eq!(show("`(1 . ,(+ 1 1))"), "(1 . 2)");
+    assert_eq!(show("(define l '(3 4)) `(1 2 . ,l)"), "(1 2 3 4)");
+}
+
+#[test]
+fn nested_quasiquotes_keep_the_inner_unquotes() {
+    assert_eq!(show("`(1 `(2 ,(3 ,(+ 1 3))))"), "(1 (quasiquote (2 (unquote (3 4)))))");
+}
+
+#[test]
+fn templates_do_not_see_bindings_named_like_the_builtins() {
+    assert_eq!(show("(define (f cons) `(1 ,cons)) (f 2)"), "(1 2)");
+    assert_eq!(show("(define (g append) `(,@append 3)) (g '(1 2))"), "(1 2 3)");
+}
+
+#[test]
+fn templates_work_without_the_builtins() {
+    let mut interpreter = Interpreter::without_builtins();
+    let r = interpreter.eval_str("(define l '(2 3)) `(1 ,@l . ,l)").unwrap();
+    assert_eq!(r.to_string(), "(1 2 3 2 3)");
+}
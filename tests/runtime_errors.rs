@@ -0,0 +1,60 @@
+mod common;
+
+use c0i::value::{Value, ValueType};
+use c0i::value::result::CError;
+
+use common::runtime_err;
+
+
+fn bad_arguments(source: &str) -> (&'static str, CError) {
+    match runtime_err(source) {
+        CError::BadArguments(name, e) => (name, (*e).clone()),
+        e => panic!("expected bad arguments, got {}", e),
+    }
+}
+
+#[test]
+fn natives_report_the_index_and_the_expected_type() {
+    let (name, e) = bad_arguments("(car 1)");
+    assert_eq!(name, "car");
+    assert!(matches!(e, CError::ArgTypeError(0, ValueType::Pair, Value::Uint(1))), "{}", e);
+
+    let (name, e) = bad_arguments("(+ 1 'a)");
+    assert_eq!(name, "+");
+    assert!(matches!(e, CError::ArgTypeError(1, ValueType::Number, Value::Sym(_))), "{}", e);
+
+    let (name, e) = bad_arguments("(set-vec! (make-vector 1) 'a 2)");
+    assert_eq!(name, "set-vec!");
+    assert!(matches!(e, CError::ArgTypeError(1, ValueType::Uint, _)), "{}", e);
+}
+
+#[test]
+fn natives_report_the_count_they_take() {
+    let (name, e) = bad_arguments("(car)");
+    assert_eq!(name, "car");
+    assert!(matches!(e, CError::ArgsNotMatching(1, 0)), "{}", e);
+    assert!(matches!(bad_arguments("(read-stdin 1)").1, CError::ArgsNotMatching(0, 1)));
+    assert!(matches!(bad_arguments("(make-dict 1)").1, CError::ArgsNotMatching(0, 1)));
+    assert!(matches!(bad_arguments("(set-vec! (make-vector 1) 0)").1, CError::ArgsNotMatching(3, 2)));
+}
+
+#[test]
+fn functions_report_the_count_they_take() {
+    let e = runtime_err("(define (f x y) x) (f 1)");
+    assert!(matches!(e, CError::ArgsNotMatching(2, 1)), "{}", e);
+}
+
+#[test]
+fn errors_are_displayed_with_the_function_and_the_types() {
+    let e = runtime_err("(cdr nil)");
+    assert_eq!(e.to_string(), "TypeError: argument 1 of 'cdr should be pair, but nil is nil.\n");
+    let e = runtime_err("(car)");
+    assert_eq!(e.to_string(), "ArgsMatchingError: 'car takes 1 arguments but 0 argument was supplied.\n");
+}
+
+#[test]
+fn messages_and_irritants_come_from_the_root_error() {
+    let e = runtime_err("(car 1)");
+    assert_eq!(e.message(), "car: argument 1 should be pair");
+    assert!(matches!(&e.irritants()[..], [Value::Uint(1)]));
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use c0i::{Error, Interpreter};
+use c0i::evaluation::call::ScriptFn;
+use c0i::value::Value;
+use c0i::value::context::Host;
+use c0i::value::result::CError;
+
+use common::capture;
+
+
+#[test]
+fn functions_of_scripts_are_called_with_rust_values() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(define (scale x k) (* x k)) (define (greet name) (list name))").unwrap();
+    let scale = interpreter.get_function::<(u64, u64), u64>("scale").unwrap();
+    assert_eq!(scale.call((6, 7)).unwrap(), 42);
+    let greet = interpreter.get_function::<(String,), Vec<String>>("greet").unwrap();
+    assert_eq!(greet.call(("you".to_string(),)).unwrap(), vec!["you"]);
+}
+
+#[test]
+fn failures_are_errors_of_the_call() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(define (fail) (error \"no\")) (define (id x) x) (define n 1)").unwrap();
+    let fail = interpreter.get_function::<(), Value>("fail").unwrap();
+    let e = fail.call(()).unwrap_err();
+    assert!(matches!(e, CError::StackBacktrace(..)), "{}", e);
+    assert!(matches!(e.root(), CError::RuntimeError(..)), "{}", e);
+
+    // the result that does not convert
+    let id = interpreter.get_function::<(Value,), u64>("id").unwrap();
+    assert!(matches!(id.call((Value::Bool(true),)), Err(CError::TypeError(..))));
+    // the arguments that do not match
+    let id = interpreter.get_function::<(u64, u64), u64>("id").unwrap();
+    assert!(matches!(id.call((1, 2)).unwrap_err().root(), CError::ArgsNotMatching(1, 2)));
+
+    assert!(matches!(interpreter.get_function::<(), Value>("n"), Err(Error::Runtime(CError::ValueIsNotCallable(_)))));
+    assert!(matches!(interpreter.get_function::<(), Value>("none"), Err(Error::Runtime(CError::SymbolNotFound(_)))));
+}
+
+#[test]
+fn calls_write_to_the_host_of_the_interpreter() {
+    let mut interpreter = Interpreter::new();
+    let output = capture(&mut interpreter, Host::default());
+    interpreter.eval_str("(define (say x) (displayln x))").unwrap();
+    let say = interpreter.get_function::<(u64,), ()>("say").unwrap();
+    say.call((1,)).unwrap();
+    say.clone().call((2,)).unwrap();
+    assert_eq!(output.text(), "1\n2\n");
+}
+
+#[test]
+fn natives_take_callbacks_of_scripts() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define_fn("apply-twice", |f: ScriptFn<(u64,), u64>, x: u64| -> Result<u64, CError> {
+        f.call((f.call((x,))?,))
+    });
+    assert_eq!(interpreter.eval_str("(apply-twice (lambda (x) (* x 3)) 2)").unwrap(), Value::Uint(18));
+    let e = interpreter.eval_str("(apply-twice 1 2)").unwrap_err();
+    assert!(matches!(&e, Error::Runtime(e) if matches!(e.root(), CError::BadArguments("apply-twice", _))), "{}", e);
+}
+
+#[test]
+fn callables_are_invoked_with_converted_arguments() {
+    let mut interpreter = Interpreter::new();
+    let f = match interpreter.eval_str("(lambda (a b) (list b a))").unwrap() {
+        Value::Callable(f) => f,
+        v => panic!("{}", v),
+    };
+    let r: Vec<u64> = f.invoke((1u64, 2u64)).unwrap();
+    assert_eq!(r, vec![2, 1]);
+}
+
+#[test]
+fn errors_of_calls_can_be_debugged() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(define (f) (missing))").unwrap();
+    let f = interpreter.get_function::<(), Value>("f").unwrap();
+    // the closure is in its own environment
+    let e = format!("{:?}", f.call(()).unwrap_err());
+    assert!(e.contains("SymbolNotFound"), "{}", e);
+}
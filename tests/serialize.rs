@@ -0,0 +1,109 @@
+#![cfg(feature = "json")]
+
+use serde::{Deserialize, Serialize};
+
+use c0i::Interpreter;
+use c0i::value::{from_value, to_value, Value};
+use c0i::value::result::CError;
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Shape {
+    Empty,
+    Dot(Point),
+    Segment(Point, Point),
+    Circle { center: Point, radius: u64 },
+}
+
+fn round_trip<T>(x: T) -> Value
+    where T: Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug
+{
+    let v = to_value(&x).unwrap();
+    assert_eq!(from_value::<T>(v.clone()).unwrap(), x);
+    v
+}
+
+#[test]
+fn values_round_trip() {
+    assert!(matches!(round_trip(Point { x: 1, y: -2 }), Value::Dict(_)));
+    assert_eq!(round_trip(vec![1u64, 2]).to_string(), "(vec 1 2)");
+    assert_eq!(round_trip((1u8, "a".to_string(), 'c')).to_string(), "(vec 1 \"a\" (char \"c\"))");
+    assert_eq!(round_trip(Some(1.5f64)), Value::Float(1.5));
+    assert_eq!(round_trip(None::<u64>), Value::Nil);
+    assert_eq!(round_trip(-1i128 << 100).to_string(), (-1i128 << 100).to_string());
+}
+
+#[test]
+fn enums_round_trip() {
+    let p = Point { x: 0, y: 1 };
+    assert_eq!(round_trip(Shape::Empty).to_string(), "Empty");
+    assert!(round_trip(Shape::Dot(p.clone())).to_string().starts_with("(vec Dot "));
+    round_trip(Shape::Segment(p.clone(), p.clone()));
+    round_trip(Shape::Circle { center: p, radius: 3 });
+}
+
+#[test]
+fn lists_of_scripts_are_sequences() {
+    let mut interpreter = Interpreter::new();
+    let v = interpreter.eval_str("(list 1 2 3)").unwrap();
+    assert_eq!(from_value::<Vec<u8>>(v).unwrap(), vec![1, 2, 3]);
+    let v = interpreter.eval_str("(list 1 \"a\")").unwrap();
+    assert_eq!(from_value::<(u64, String)>(v).unwrap(), (1, "a".to_string()));
+    // the empty list is nil
+    let v = interpreter.eval_str("(list)").unwrap();
+    assert_eq!(v, Value::Nil);
+    assert_eq!(from_value::<Vec<u8>>(v.clone()).unwrap(), Vec::<u8>::new());
+    assert_eq!(from_value::<()>(v.clone()).unwrap(), ());
+    assert_eq!(from_value::<Option<Vec<u8>>>(v).unwrap(), None);
+}
+
+#[test]
+fn improper_lists_are_rejected() {
+    let mut interpreter = Interpreter::new();
+    let v = interpreter.eval_str("(cons 1 2)").unwrap();
+    let e = from_value::<Vec<u64>>(v.clone()).unwrap_err();
+    assert!(matches!(&e, CError::ConversionError(m) if m.contains("is not a list")), "{}", e);
+    let e = serde_json::to_string(&v).unwrap_err();
+    assert!(e.to_string().contains("is not a list"), "{}", e);
+}
+
+#[test]
+fn callables_are_rejected() {
+    let mut interpreter = Interpreter::new();
+    let v = interpreter.eval_str("(lambda (x) x)").unwrap();
+    assert!(matches!(from_value::<Value>(v.clone()), Err(CError::ConversionError(_))));
+    assert!(serde_json::to_string(&v).is_err());
+    let v = interpreter.eval_str("(list 1 car)").unwrap();
+    assert!(matches!(from_value::<Vec<u64>>(v), Err(CError::ConversionError(_))));
+}
+
+#[test]
+fn values_of_another_shape_are_conversion_errors() {
+    assert!(matches!(from_value::<Point>(Value::Uint(1)), Err(CError::ConversionError(_))));
+    assert!(matches!(from_value::<Shape>(Value::Uint(1)), Err(CError::ConversionError(_))));
+    assert!(matches!(from_value::<u8>(Value::Uint(300)), Err(CError::ConversionError(_))));
+    let mut interpreter = Interpreter::new();
+    let v = interpreter.eval_str("(/ 1 3)").unwrap();
+    assert!(matches!(v, Value::Rational(_)), "{}", v);
+    assert!(matches!(from_value::<f64>(v.clone()), Err(CError::ConversionError(_))));
+    assert!(serde_json::to_string(&v).is_err());
+}
+
+#[test]
+fn containers_that_hold_themselves_are_rejected() {
+    let mut interpreter = Interpreter::new();
+    let v = interpreter.eval_str("(define v (make-vector 1 nil)) (vec-set! v 1 v) v").unwrap();
+    let e = serde_json::to_string(&v).unwrap_err();
+    assert!(e.to_string().contains("holds itself"), "{}", e);
+    let v = interpreter.eval_str("(define d (make-dict)) (dict-set! d \"self\" (list d)) d").unwrap();
+    assert!(serde_json::to_string(&v).is_err());
+    // the same vector twice is not a cycle
+    let v = interpreter.eval_str("(define w (make-vector 1)) (make-vector w w)").unwrap();
+    assert_eq!(serde_json::to_string(&v).unwrap(), "[[1],[1]]");
+}
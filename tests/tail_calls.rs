@@ -0,0 +1,53 @@
+mod common;
+
+use c0i::value::Value;
+use c0i::value::result::CError;
+
+use common::{eval, runtime_err};
+
+
+// the test threads have a small stack, these loops would overflow it if calls in tail position pushed frames
+
+#[test]
+fn self_calls_in_cond_and_let_bodies_run_in_constant_stack() {
+    let r = eval("
+        (define (count n acc)
+          (cond ((eq? n 0) acc)
+                (else (let ((m (-u n 1))) (count m (+u acc 1))))))
+        (count 200000 0)");
+    assert_eq!(r, Value::Uint(200000));
+}
+
+#[test]
+fn mutual_recursion_runs_in_constant_stack() {
+    let r = eval("
+        (define (even? n) (cond ((eq? n 0) true) (else (odd? (-u n 1)))))
+        (define (odd? n) (cond ((eq? n 0) false) (else (even? (-u n 1)))))
+        (even? 100001)");
+    assert_eq!(r, Value::Bool(false));
+}
+
+#[test]
+fn loops_over_long_lists() {
+    let r = eval("
+        (define (build n l) (cond ((eq? n 0) l) (else (build (-u n 1) (cons n l)))))
+        (define (len l acc) (cond ((nil? l) acc) (else (len (cdr l) (+u acc 1)))))
+        (len (build 100000 nil) 0)");
+    assert_eq!(r, Value::Uint(100000));
+}
+
+#[test]
+fn calls_not_in_tail_position_still_return() {
+    let r = eval("
+        (define (sum n) (cond ((eq? n 0) 0) (else (+u n (sum (-u n 1))))))
+        (sum 100)");
+    assert_eq!(r, Value::Uint(5050));
+}
+
+#[test]
+fn errors_in_tail_calls_are_reported() {
+    let e = runtime_err("
+        (define (bad n) (cond ((eq? n 0) (car 1)) (else (bad (-u n 1)))))
+        (bad 3)");
+    assert!(matches!(e, CError::BadArguments("car", _)), "{}", e);
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use c0i::value::{Value, ValueType};
+use c0i::value::result::CError;
+
+use common::{eval, runtime_err, show};
+
+
+#[test]
+fn vectors_are_read_and_changed() {
+    assert_eq!(eval("(vec-ref (make-vector 'a 'b) 1)").to_string(), "b");
+    assert_eq!(eval("(vec-length (make-vector 1 nil))"), Value::Uint(2));
+    assert_eq!(show("(define v (make-vector 1 2)) (vec-set! v 0 3) v"), "(vec 3 2)");
+    assert_eq!(show("(define v (make-vector)) (vec-push! v 1) (vec-push! v 2) v"), "(vec 1 2)");
+    assert_eq!(show("(define v (make-vector 1 2)) (list (vec-pop! v) v)"), "(2 (vec 1))");
+    assert_eq!(eval("(vec-pop! (make-vector))"), Value::Nil);
+    assert_eq!(show("(define v (make-vector 1 2)) (vec-fill! v 0) v"), "(vec 0 0)");
+    assert_eq!(eval("(vec? (make-vector))"), Value::Bool(true));
+    assert_eq!(eval("(vec? (list 1))"), Value::Bool(false));
+}
+
+#[test]
+fn copies_and_slices_are_new_vectors() {
+    assert_eq!(show("(define v (make-vector 1 2)) (define c (vec-copy v)) (vec-set! c 0 3) (list v c)"), "((vec 1 2) (vec 3 2))");
+    assert_eq!(show("(vec-slice (make-vector 1 2 3) 1)"), "(vec 2 3)");
+    assert_eq!(show("(vec-slice (make-vector 1 2 3) 0 2)"), "(vec 1 2)");
+    assert_eq!(show("(vec-slice (make-vector 1 2 3) 3)"), "(vec )");
+    assert_eq!(show("(define v (make-vector 1 2)) (define s (vec-slice v 0)) (vec-push! s 3) v"), "(vec 1 2)");
+}
+
+#[test]
+fn items_out_of_range_are_errors() {
+    assert!(matches!(runtime_err("(vec-ref (make-vector 1) 1)"), CError::IndexOutOfRange(1, 1)));
+    assert!(matches!(runtime_err("(vec-set! (make-vector) 0 1)"), CError::IndexOutOfRange(0, 0)));
+    assert!(matches!(runtime_err("(vec-slice (make-vector 1 2) 0 3)"), CError::IndexOutOfRange(3, 2)));
+    assert!(matches!(runtime_err("(vec-slice (make-vector 1 2) 2 1)"), CError::IndexOutOfRange(2, 1)));
+    assert!(matches!(runtime_err("(vec-ref (make-vector 1) -1)"), CError::IndexOutOfRange(-1, 1)));
+    assert!(matches!(runtime_err("(vec-set! (make-vector 1 2) -2 0)"), CError::IndexOutOfRange(-2, 2)));
+    assert!(matches!(runtime_err("(vec-ref (make-vector 1) 'a)"), CError::BadArguments("vec-ref", _)));
+}
+
+#[test]
+fn indexes_may_be_ints() {
+    let last = "(define v (make-vector 1 2 3)) (define i (+ (vec-length v) -1))";
+    assert_eq!(eval(&format!("{} i", last)), Value::Int(2));
+    assert_eq!(eval(&format!("{} (vec-ref v i)", last)), Value::Uint(3));
+    assert_eq!(show(&format!("{} (vec-set! v i 0) v", last)), "(vec 1 2 0)");
+    assert_eq!(show(&format!("{} (set-vec! v (- i 1) 0) v", last)), "(vec 1 0 3)");
+    assert_eq!(show(&format!("{} (vec-slice v (- i 1) i)", last)), "(vec 2)");
+}
+
+#[test]
+fn arguments_of_the_wrong_type_are_rejected() {
+    assert!(matches!(runtime_err("(vec-ref (list 1) 0)"), CError::BadArguments("vec-ref", _)));
+    assert!(matches!(runtime_err("(vec-length 1)"), CError::BadArguments("vec-length", _)));
+    assert!(matches!(runtime_err("(vec-map car (list 1))"), CError::BadArguments("vec-map", _)));
+    assert!(matches!(runtime_err("(vec-sort! (make-vector 1) 1)"), CError::BadArguments("vec-sort!", _)));
+    assert!(matches!(runtime_err("(vec-push! (make-vector))"), CError::BadArguments("vec-push!", _)));
+}
+
+#[test]
+fn higher_order_functions() {
+    assert_eq!(show("(vec-map (lambda (x) (* x x)) (make-vector 1 2 3))"), "(vec 1 4 9)");
+    assert_eq!(show("(vec-map + (make-vector 1 2 3) (make-vector 10 20))"), "(vec 11 22)");
+    assert_eq!(show("(vec-filter (lambda (x) (> x 1)) (make-vector 1 2 3))"), "(vec 2 3)");
+    assert_eq!(show("(vec-reduce (lambda (acc x) (list acc x)) (make-vector 1 2 3))"), "((1 2) 3)");
+    assert_eq!(eval("(vec-reduce + (make-vector))"), Value::Nil);
+    let r = eval("(define d (make-dict)) (vec-for-each (lambda (k v) (dict-set! d k v)) (make-vector 'a 'b) (make-vector 1 2)) (dict-ref d 'b)");
+    assert_eq!(r, Value::Uint(2));
+}
+
+#[test]
+fn callbacks_may_change_the_vector() {
+    assert_eq!(show("(define v (make-vector 1 2)) (vec-for-each (lambda (x) (vec-push! v x)) v) v"), "(vec 1 2 1 2)");
+    let e = runtime_err("(vec-filter (lambda (x) 1) (make-vector 1))");
+    assert!(matches!(e, CError::TypeError(ValueType::Bool, Value::Uint(1))), "{}", e);
+}
+
+#[test]
+fn sort_is_stable() {
+    let r = show("(define v (make-vector (cons 2 'a) (cons 1 'b) (cons 2 'c) (cons 1 'd) (cons 0 'e)))
+                  (vec-sort! v (lambda (a b) (< (car a) (car b))))
+                  v");
+    assert_eq!(r, "(vec (0 . e) (1 . b) (1 . d) (2 . a) (2 . c))");
+    assert_eq!(show("(define v (make-vector 3 1 2)) (vec-sort! v >) v"), "(vec 3 2 1)");
+    assert_eq!(show("(define v (make-vector)) (vec-sort! v <) v"), "(vec )");
+}
+
+#[test]
+fn errors_of_less_leave_the_vector_as_it_was() {
+    let r = show("(define v (make-vector 2 'a 1))
+                  (try (vec-sort! v <) (catch (e) nil))
+                  v");
+    assert_eq!(r, "(vec 2 a 1)");
+    let e = runtime_err("(vec-sort! (make-vector 2 1) (lambda (a b) 'yes))");
+    assert!(matches!(e, CError::TypeError(ValueType::Bool, _)), "{}", e);
+}
+
+#[test]
+fn binary_search_finds_the_first_equal_item() {
+    assert_eq!(eval("(vec-binary-search (make-vector 1 2 2 2 3) 2 <)"), Value::Uint(1));
+    assert_eq!(eval("(vec-binary-search (make-vector 1 2 3) 3 <)"), Value::Uint(2));
+    assert_eq!(eval("(vec-binary-search (make-vector 1 3) 2 <)"), Value::Nil);
+    assert_eq!(eval("(vec-binary-search (make-vector 1 3) 4 <)"), Value::Nil);
+    assert_eq!(eval("(vec-binary-search (make-vector) 1 <)"), Value::Nil);
+    assert_eq!(eval("(vec-binary-search (make-vector 3 2 1) 1 >)"), Value::Uint(2));
+}